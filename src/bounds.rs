@@ -0,0 +1,134 @@
+//! Bounding boxes of what is loaded, and where the camera goes to see
+//! them.
+
+use three_d::*;
+
+/// Axis-aligned box around a set of points. It is empty until a point
+/// is added.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl BoundingBox {
+    pub fn new() -> BoundingBox {
+        BoundingBox {
+            min: vec3(f32::MAX, f32::MAX, f32::MAX),
+            max: vec3(f32::MIN, f32::MIN, f32::MIN),
+        }
+    }
+
+    /// Box around positions given as x, y and z after each other.
+    pub fn from_positions(positions: &[f32]) -> BoundingBox {
+        let mut bounds = BoundingBox::new();
+        for position in positions.chunks_exact(3) {
+            bounds.add_point(vec3(position[0], position[1], position[2]));
+        }
+        bounds
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn add_point(&mut self, point: Vec3) {
+        for axis in 0..3 {
+            self.min[axis] = self.min[axis].min(point[axis]);
+            self.max[axis] = self.max[axis].max(point[axis]);
+        }
+    }
+
+    pub fn add_box(&mut self, other: &BoundingBox) {
+        if !other.is_empty() {
+            self.add_point(other.min);
+            self.add_point(other.max);
+        }
+    }
+
+    /// Box around this one after it is transformed.
+    pub fn transformed(&self, transform: &Mat4) -> BoundingBox {
+        let mut bounds = BoundingBox::new();
+        if self.is_empty() {
+            return bounds;
+        }
+        for corner in 0..8 {
+            let point = vec4(
+                if corner & 1 == 0 {
+                    self.min.x
+                } else {
+                    self.max.x
+                },
+                if corner & 2 == 0 {
+                    self.min.y
+                } else {
+                    self.max.y
+                },
+                if corner & 4 == 0 {
+                    self.min.z
+                } else {
+                    self.max.z
+                },
+                1.0,
+            );
+            let transformed = transform * point;
+            bounds.add_point(transformed.truncate() / transformed.w);
+        }
+        bounds
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Radius of the sphere around the box.
+    pub fn radius(&self) -> f32 {
+        (self.max - self.min).magnitude() * 0.5
+    }
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Placement of a perspective camera that shows all of a bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Framing {
+    /// Point the camera looks at, the center of the box.
+    pub target: Vec3,
+    /// Distance of the camera from the target.
+    pub distance: f32,
+    pub z_near: f32,
+    pub z_far: f32,
+}
+
+impl Framing {
+    /// Frames the sphere around `bounds` in the narrower of the
+    /// vertical field of view `fovy` and the horizontal one.
+    pub fn new(bounds: &BoundingBox, fovy: Degrees, aspect: f32) -> Framing {
+        // A box around a single point still gets some room.
+        let radius = bounds.radius().max(1e-3);
+        let half_fovy = Radians::from(fovy).0 * 0.5;
+        let half_fovx = (half_fovy.tan() * aspect).atan();
+        let distance = radius / half_fovy.min(half_fovx).sin();
+        // The far plane leaves room to zoom out, and the near plane is
+        // close enough to zoom into the box.
+        Framing {
+            target: bounds.center(),
+            distance,
+            z_near: radius * 0.01,
+            z_far: (distance + radius) * 10.0,
+        }
+    }
+
+    /// Distance of the camera from the target after zooming by `amount`,
+    /// which scales it exponentially. It stays far enough from both
+    /// planes that the target can still be seen.
+    pub fn zoomed_distance(&self, distance: f32, amount: f32) -> f32 {
+        (distance * amount.exp())
+            .max(self.z_near * 2.0)
+            .min(self.z_far * 0.5)
+    }
+}
@@ -0,0 +1,488 @@
+use crate::environment::Environment;
+use crate::io::{Material, PbrMaterial};
+use three_d::core::Error;
+use three_d::*;
+
+/// Layers of the geometry pass: the diffuse color, the normal with the
+/// specular intensity, the emissive color with the specular power, and
+/// the ambient color with the lighting model. Metallic-roughness
+/// surfaces keep their metallic value and roughness in place of the
+/// specular intensity and power.
+const GEOMETRY_LAYERS: usize = 4;
+
+/// Most lights of each kind that surfaces of the forward pass are lit
+/// by, as in material.frag.
+const MAX_FORWARD_LIGHTS: usize = 8;
+
+/// Light that reaches every surface equally.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AmbientLight {
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+/// Light from far away, shining in one direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionalLight {
+    pub color: Vec3,
+    pub intensity: f32,
+    pub direction: Vec3,
+    /// Is the direction relative to the camera, turning with it? The
+    /// camera looks along -z.
+    pub attached_to_camera: bool,
+}
+
+impl DirectionalLight {
+    /// Direction the light shines in, in the scene.
+    pub fn world_direction(&self, camera: &Camera) -> Vec3 {
+        self.direction_for_view(camera.get_view())
+    }
+
+    /// Direction the light shines in, in the scene, seen from a camera
+    /// with the view matrix `view`.
+    pub fn direction_for_view(&self, view: &Mat4) -> Vec3 {
+        let direction = if self.attached_to_camera {
+            let view_inverse = view.invert().unwrap();
+            (view_inverse * self.direction.extend(0.0)).truncate()
+        } else {
+            self.direction
+        };
+        if direction.magnitude2() > 0.0 {
+            direction.normalize()
+        } else {
+            vec3(0.0, -1.0, 0.0)
+        }
+    }
+}
+
+/// Light from a point, getting weaker with distance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    pub color: Vec3,
+    pub intensity: f32,
+    pub position: Vec3,
+    /// Constant, linear and quadratic attenuation.
+    pub attenuation: Vec3,
+}
+
+/// All lights of the scene.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lights {
+    pub ambient: AmbientLight,
+    pub directional: Vec<DirectionalLight>,
+    pub point: Vec<PointLight>,
+}
+
+/// Pass that surfaces are drawn in. Opaque surfaces are drawn into the
+/// layers of the geometry pass, and transparent ones are lit and blended
+/// over the lit image in the forward pass.
+#[derive(Clone, Copy)]
+pub enum Pass<'a> {
+    Geometry,
+    Forward(&'a ForwardLights),
+}
+
+/// Sizes, in floats, of the members of the ForwardLights block of
+/// material.frag: the light counts, then an array of vec4 for each of
+/// the directional light colors and directions, and the point light
+/// colors, positions and attenuations.
+const FORWARD_LIGHT_BLOCK_SIZES: [u32; 6] = [
+    4,
+    4 * MAX_FORWARD_LIGHTS as u32,
+    4 * MAX_FORWARD_LIGHTS as u32,
+    4 * MAX_FORWARD_LIGHTS as u32,
+    4 * MAX_FORWARD_LIGHTS as u32,
+    4 * MAX_FORWARD_LIGHTS as u32,
+];
+
+/// Contents of the members of the ForwardLights block, in the std140
+/// layout, for a camera with the view matrix `view`. Lights beyond the
+/// first MAX_FORWARD_LIGHTS of each kind are left out.
+pub fn forward_light_block(lights: &Lights, view: &Mat4) -> Vec<Vec<f32>> {
+    let mut block: Vec<Vec<f32>> = FORWARD_LIGHT_BLOCK_SIZES
+        .iter()
+        .map(|size| vec![0.0; *size as usize])
+        .collect();
+    let directional = &lights.directional[..MAX_FORWARD_LIGHTS.min(lights.directional.len())];
+    let point = &lights.point[..MAX_FORWARD_LIGHTS.min(lights.point.len())];
+    block[0][0] = directional.len() as f32;
+    block[0][1] = point.len() as f32;
+    let mut set = |member: usize, i: usize, value: Vec3| {
+        block[member][i * 4..i * 4 + 3].copy_from_slice(&[value.x, value.y, value.z]);
+    };
+    for (i, light) in directional.iter().enumerate() {
+        set(1, i, light.color * light.intensity);
+        set(2, i, light.direction_for_view(view));
+    }
+    for (i, light) in point.iter().enumerate() {
+        set(3, i, light.color * light.intensity);
+        set(4, i, light.position);
+        set(5, i, light.attenuation);
+    }
+    block
+}
+
+/// The lights of the forward pass, in a uniform buffer that the
+/// programs of every transparent surface share.
+pub struct ForwardLights {
+    ambient: Vec3,
+    buffer: UniformBuffer,
+}
+
+impl ForwardLights {
+    pub fn new(gl: &Gl, lights: &Lights, camera: &Camera) -> Result<ForwardLights, Error> {
+        let mut buffer = UniformBuffer::new(gl, &FORWARD_LIGHT_BLOCK_SIZES)?;
+        for (i, member) in forward_light_block(lights, camera.get_view())
+            .iter()
+            .enumerate()
+        {
+            buffer.update(i, member)?;
+        }
+        Ok(ForwardLights {
+            ambient: lights.ambient.color * lights.ambient.intensity,
+            buffer,
+        })
+    }
+}
+
+/// Programs drawing a kind of surface in each pass.
+pub struct MaterialPrograms {
+    geometry: program::Program,
+    forward: program::Program,
+}
+
+impl MaterialPrograms {
+    /// Programs for a fragment shader that writes surfaces with
+    /// `write_surface` or `write_pbr_surface` of `material_shader`.
+    pub fn new(gl: &Gl, vertex_shader: &str, fragment_shader: &str) -> MaterialPrograms {
+        MaterialPrograms {
+            geometry: program::Program::from_source(
+                gl,
+                vertex_shader,
+                &material_shader(fragment_shader),
+            )
+            .unwrap(),
+            forward: program::Program::from_source(
+                gl,
+                vertex_shader,
+                &format!(
+                    "#define FORWARD\n{}\n{}\n{}",
+                    include_str!("shaders/lighting.frag"),
+                    include_str!("shaders/material.frag"),
+                    fragment_shader
+                ),
+            )
+            .unwrap(),
+        }
+    }
+
+    /// The program of a pass, with the lights set for the forward pass.
+    pub fn program(&self, pass: Pass, camera: &Camera) -> &program::Program {
+        let lights = match pass {
+            Pass::Geometry => return &self.geometry,
+            Pass::Forward(lights) => lights,
+        };
+        let program = &self.forward;
+        program
+            .add_uniform_vec3("ambient_light", &lights.ambient)
+            .unwrap();
+        program
+            .add_uniform_vec3("eyePosition", camera.position())
+            .unwrap();
+        // Uniform arrays can only be set through their first element
+        // in three-d, so the lights are set as a block.
+        program.use_uniform_block(&lights.buffer, "ForwardLights");
+        program
+    }
+}
+
+/// Renders surfaces into layers first, then lights them. Works like the
+/// deferred pipeline of three-d, with layers for the ambient and
+/// emissive colors of materials.
+pub struct DeferredRenderer {
+    gl: Gl,
+    ambient_light_effect: ImageEffect,
+    environment_light_effect: ImageEffect,
+    background_effect: ImageEffect,
+    directional_light_effect: ImageEffect,
+    point_light_effect: ImageEffect,
+    geometry_pass_texture: Option<Texture2DArray>,
+    geometry_pass_depth_texture: Option<Texture2DArray>,
+}
+
+impl DeferredRenderer {
+    pub fn new(gl: &Gl) -> Result<DeferredRenderer, Error> {
+        let light_shared = format!(
+            "{}\n{}",
+            include_str!("shaders/lighting.frag"),
+            include_str!("shaders/light_shared.frag")
+        );
+        let environment_shared = format!(
+            "{}\n{}",
+            include_str!("shaders/lighting.frag"),
+            include_str!("shaders/environment.frag")
+        );
+        Ok(DeferredRenderer {
+            gl: gl.clone(),
+            ambient_light_effect: ImageEffect::new(gl, include_str!("shaders/ambient_light.frag"))?,
+            environment_light_effect: ImageEffect::new(
+                gl,
+                &format!(
+                    "{}\n{}\n{}",
+                    light_shared,
+                    include_str!("shaders/environment.frag"),
+                    include_str!("shaders/environment_light.frag")
+                ),
+            )?,
+            background_effect: ImageEffect::new(
+                gl,
+                &format!(
+                    "{}\n{}",
+                    environment_shared,
+                    include_str!("shaders/background.frag")
+                ),
+            )?,
+            directional_light_effect: ImageEffect::new(
+                gl,
+                &format!(
+                    "{}\n{}",
+                    light_shared,
+                    include_str!("shaders/directional_light.frag")
+                ),
+            )?,
+            point_light_effect: ImageEffect::new(
+                gl,
+                &format!(
+                    "{}\n{}",
+                    light_shared,
+                    include_str!("shaders/point_light.frag")
+                ),
+            )?,
+            geometry_pass_texture: None,
+            geometry_pass_depth_texture: None,
+        })
+    }
+
+    /// Renders the surfaces drawn by `render_scene` into the layers.
+    /// Their fragment shaders write them with `write_surface` of
+    /// `material_shader`.
+    pub fn geometry_pass(
+        &mut self,
+        width: usize,
+        height: usize,
+        render_scene: &dyn Fn(),
+    ) -> Result<(), Error> {
+        state::depth_write(&self.gl, true);
+        state::depth_test(&self.gl, state::DepthTestType::LessOrEqual);
+        state::cull(&self.gl, state::CullType::None);
+        state::blend(&self.gl, state::BlendType::None);
+
+        let resized = match &self.geometry_pass_texture {
+            Some(texture) => texture.width != width || texture.height != height,
+            None => true,
+        };
+        if resized {
+            self.geometry_pass_texture = Some(Texture2DArray::new(
+                &self.gl,
+                width,
+                height,
+                GEOMETRY_LAYERS,
+                Interpolation::Nearest,
+                Interpolation::Nearest,
+                None,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+                Format::RGBA8,
+            )?);
+            self.geometry_pass_depth_texture = Some(Texture2DArray::new(
+                &self.gl,
+                width,
+                height,
+                1,
+                Interpolation::Nearest,
+                Interpolation::Nearest,
+                None,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+                Format::Depth32F,
+            )?);
+        }
+        RenderTarget::write_array(
+            &self.gl,
+            0,
+            0,
+            width,
+            height,
+            Some(&vec4(0.0, 0.0, 0.0, 0.0)),
+            Some(1.0),
+            self.geometry_pass_texture.as_ref(),
+            self.geometry_pass_depth_texture.as_ref(),
+            GEOMETRY_LAYERS,
+            &|channel| channel,
+            0,
+            render_scene,
+        )
+    }
+
+    /// Lights the surfaces of the last geometry pass, drawing over
+    /// everything where there is a surface. Their depth is written too,
+    /// for the forward pass. An environment adds its light to the
+    /// ambient light, and may be drawn where there is no surface.
+    pub fn light_pass(
+        &self,
+        camera: &Camera,
+        lights: &Lights,
+        environment: Option<&Environment>,
+    ) -> Result<(), Error> {
+        let (texture, depth_texture) = match (
+            &self.geometry_pass_texture,
+            &self.geometry_pass_depth_texture,
+        ) {
+            (Some(texture), Some(depth_texture)) => (texture, depth_texture),
+            _ => return Ok(()),
+        };
+        let view_projection_inverse = (camera.get_projection() * camera.get_view())
+            .invert()
+            .unwrap();
+        state::blend(&self.gl, state::BlendType::None);
+
+        // The background goes behind everything, so it keeps the depth
+        // of the cleared screen.
+        if let Some(environment) = environment.filter(|environment| environment.show_background) {
+            state::depth_write(&self.gl, false);
+            state::depth_test(&self.gl, state::DepthTestType::Always);
+            let program = self.background_effect.program();
+            program.use_texture(&environment.background, "background")?;
+            program.add_uniform_mat4("viewProjectionInverse", &view_projection_inverse)?;
+            program.add_uniform_vec3("eyePosition", camera.position())?;
+            program.add_uniform_float("environment_intensity", &environment.intensity)?;
+            self.background_effect.apply();
+        }
+        state::depth_write(&self.gl, true);
+        state::depth_test(&self.gl, state::DepthTestType::Always);
+
+        // Ambient light and emission come first, replacing what is there.
+        let ambient_light = lights.ambient.color * lights.ambient.intensity;
+        match environment {
+            Some(environment) => {
+                let program = self.environment_light_effect.program();
+                program.use_texture(texture, "gbuffer")?;
+                program.use_texture(depth_texture, "depthMap")?;
+                program.add_uniform_mat4("viewProjectionInverse", &view_projection_inverse)?;
+                program.add_uniform_vec3("eyePosition", camera.position())?;
+                program.add_uniform_vec3("ambient_light", &ambient_light)?;
+                program.add_uniform_float("environment_intensity", &environment.intensity)?;
+                for (i, coefficient) in environment.irradiance.iter().enumerate() {
+                    program.add_uniform_vec3(&format!("irradiance_{}", i), coefficient)?;
+                }
+                program.use_texture(&environment.specular, "specular_map")?;
+                self.environment_light_effect.apply();
+            }
+            None => {
+                let program = self.ambient_light_effect.program();
+                program.use_texture(texture, "gbuffer")?;
+                program.use_texture(depth_texture, "depthMap")?;
+                program.add_uniform_vec3("ambient_light", &ambient_light)?;
+                self.ambient_light_effect.apply();
+            }
+        }
+        state::depth_write(&self.gl, false);
+        state::depth_test(&self.gl, state::DepthTestType::None);
+        state::blend(&self.gl, state::BlendType::OneOne);
+
+        for light in lights.directional.iter() {
+            let program = self.directional_light_effect.program();
+            program.use_texture(texture, "gbuffer")?;
+            program.use_texture(depth_texture, "depthMap")?;
+            program.add_uniform_mat4("viewProjectionInverse", &view_projection_inverse)?;
+            program.add_uniform_vec3("eyePosition", camera.position())?;
+            program.add_uniform_vec3("light_color", &(light.color * light.intensity))?;
+            program.add_uniform_vec3("light_direction", &light.world_direction(camera))?;
+            self.directional_light_effect.apply();
+        }
+        for light in lights.point.iter() {
+            let program = self.point_light_effect.program();
+            program.use_texture(texture, "gbuffer")?;
+            program.use_texture(depth_texture, "depthMap")?;
+            program.add_uniform_mat4("viewProjectionInverse", &view_projection_inverse)?;
+            program.add_uniform_vec3("eyePosition", camera.position())?;
+            program.add_uniform_vec3("light_color", &(light.color * light.intensity))?;
+            program.add_uniform_vec3("light_position", &light.position)?;
+            program.add_uniform_vec3("attenuation", &light.attenuation)?;
+            self.point_light_effect.apply();
+        }
+        Ok(())
+    }
+
+    /// Blends the surfaces drawn by `render_scene` over the lit image,
+    /// behind the surfaces of the geometry pass. They are drawn in the
+    /// order they come in, so they should be sorted back to front.
+    pub fn forward_pass(&self, render_scene: &dyn Fn()) {
+        state::depth_write(&self.gl, false);
+        state::depth_test(&self.gl, state::DepthTestType::LessOrEqual);
+        state::cull(&self.gl, state::CullType::None);
+        state::blend(&self.gl, state::BlendType::SrcAlphaOneMinusSrcAlpha);
+        render_scene();
+    }
+}
+
+/// Fragment shader source with `write_surface`, which fragment shaders
+/// of the geometry pass are appended to.
+pub fn material_shader(fragment_shader: &str) -> String {
+    format!(
+        "{}\n{}",
+        include_str!("shaders/material.frag"),
+        fragment_shader
+    )
+}
+
+/// Sets the uniforms of `material_shader` for a material.
+pub fn use_material(program: &program::Program, material: &Material) {
+    // Illumination model 0 is a constant color, and 1 has no highlights.
+    let unlit = if material.illumination == 0 { 1.0 } else { 0.0 };
+    let specular = material.specular_color;
+    let specular_intensity = if material.illumination == 1 {
+        0.0
+    } else {
+        specular.x.max(specular.y).max(specular.z)
+    };
+    program
+        .add_uniform_float("diffuse_intensity", &material.diffuse_intensity)
+        .unwrap();
+    program
+        .add_uniform_float("specular_intensity", &specular_intensity)
+        .unwrap();
+    program
+        .add_uniform_float("specular_power", &material.specular_power)
+        .unwrap();
+    program
+        .add_uniform_vec3("ambient_color", &material.ambient_color)
+        .unwrap();
+    program
+        .add_uniform_vec3("emissive_color", &material.emissive_color)
+        .unwrap();
+    program
+        .add_uniform_float("opacity", &material.opacity)
+        .unwrap();
+    program.add_uniform_float("unlit", &unlit).unwrap();
+}
+
+/// Sets the uniforms of `material_shader` that `write_pbr_surface` uses,
+/// and the metallic and roughness values, for a metallic-roughness
+/// material.
+pub fn use_pbr_material(program: &program::Program, material: &Material, pbr: &PbrMaterial) {
+    let unlit = if material.illumination == 0 { 1.0 } else { 0.0 };
+    program
+        .add_uniform_vec3("emissive_color", &material.emissive_color)
+        .unwrap();
+    program
+        .add_uniform_float("opacity", &material.opacity)
+        .unwrap();
+    program.add_uniform_float("unlit", &unlit).unwrap();
+    program
+        .add_uniform_float("metallic", &pbr.metallic)
+        .unwrap();
+    program
+        .add_uniform_float("roughness", &pbr.roughness)
+        .unwrap();
+}
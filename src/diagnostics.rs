@@ -0,0 +1,125 @@
+use super::{Model, Msg, ShouldRender};
+use yew::{html, Html};
+
+/// How loading a file went.
+pub enum FileStatus {
+    Loading,
+    Loaded,
+    /// Waiting for another file, such as the buffer of a glTF file.
+    Waiting(String),
+    Failed(String),
+}
+
+/// What happened while loading a file, to show to the user.
+pub struct FileDiagnostics {
+    pub name: String,
+    pub status: FileStatus,
+    pub warnings: Vec<String>,
+}
+
+/// Diagnostics of all loaded files, in the order they were loaded.
+pub struct Diagnostics {
+    pub files: Vec<FileDiagnostics>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics { files: vec![] }
+    }
+
+    /// Starts over for a file, which may have been loaded before.
+    pub fn start(&mut self, name: &str) {
+        self.dismiss(name);
+        self.files.push(FileDiagnostics {
+            name: name.to_string(),
+            status: FileStatus::Loading,
+            warnings: vec![],
+        });
+    }
+
+    pub fn set_status(&mut self, name: &str, status: FileStatus) {
+        if let Some(file) = self.get_mut(name) {
+            file.status = status;
+        }
+    }
+
+    pub fn warn(&mut self, name: &str, warning: String) {
+        if let Some(file) = self.get_mut(name) {
+            file.warnings.push(warning);
+        }
+    }
+
+    pub fn dismiss(&mut self, name: &str) {
+        self.files.retain(|file| file.name != name);
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut FileDiagnostics> {
+        self.files.iter_mut().find(|file| file.name == name)
+    }
+}
+
+pub fn update(model: &mut Model, msg: &Msg) -> ShouldRender {
+    match msg {
+        Msg::DismissDiagnostics(name) => {
+            model.diagnostics.dismiss(name);
+            return true;
+        }
+        Msg::DismissAllDiagnostics => {
+            model.diagnostics.files.clear();
+            return true;
+        }
+        _ => {}
+    };
+    false
+}
+
+pub fn view(model: &Model) -> Html {
+    if model.diagnostics.files.is_empty() {
+        return html! {};
+    }
+    let dismiss_all_cb = model.link.callback(|_| Msg::DismissAllDiagnostics);
+    html! {
+        <div>
+            <table>
+            { for model.diagnostics.files.iter().map(|file| view_file(model, file)) }
+            </table>
+            <button onclick=dismiss_all_cb>
+                        { "Dismiss all" }
+            </button>
+        </div>
+    }
+}
+
+fn view_file(model: &Model, file: &FileDiagnostics) -> Html {
+    let file_name = file.name.clone();
+    let dismiss_cb = model
+        .link
+        .callback(move |_| Msg::DismissDiagnostics(file_name.clone()));
+    let (color, status) = match &file.status {
+        FileStatus::Loading => ("black", "Loading".to_string()),
+        FileStatus::Loaded if file.warnings.is_empty() => ("green", "Loaded".to_string()),
+        FileStatus::Loaded => ("darkorange", "Loaded with warnings".to_string()),
+        FileStatus::Waiting(resource) => ("black", format!("Waiting for {}", resource)),
+        FileStatus::Failed(error) => ("red", error.clone()),
+    };
+    html! {
+        <tr>
+            <td style="vertical-align: top">
+            { &file.name }
+            </td>
+            <td style="vertical-align: top">
+            <span style=format!("color: {}", color)>{ status }</span>
+            { if file.warnings.is_empty() { html! {} } else { html! {
+                <ul>
+                { for file.warnings.iter().map(|warning| html! { <li>{ warning }</li> }) }
+                </ul>
+            } } }
+            </td>
+            <td style="vertical-align: top">
+            <button onclick=dismiss_cb>
+                        { "Dismiss" }
+            </button>
+            </td>
+        </tr>
+    }
+}
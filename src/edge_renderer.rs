@@ -0,0 +1,107 @@
+use crate::deferred_renderer::{use_material, MaterialPrograms, Pass};
+use crate::io::Material;
+use crate::render_buffers::EdgeData;
+use crate::MeshContainer;
+use three_d::*;
+
+/// How the faces of a mesh are shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisplayMode {
+    /// Faces lit with their materials.
+    Shaded,
+    /// Edges of every face, front or back, and nothing else.
+    Wireframe,
+    /// Faces lit with their materials, with their edges over them.
+    ShadedWireframe,
+    /// Edges of the faces that can be seen, over plain white faces.
+    HiddenLine,
+}
+
+impl DisplayMode {
+    pub const ALL: [DisplayMode; 4] = [
+        DisplayMode::Shaded,
+        DisplayMode::Wireframe,
+        DisplayMode::ShadedWireframe,
+        DisplayMode::HiddenLine,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DisplayMode::Shaded => "Shaded",
+            DisplayMode::Wireframe => "Wireframe",
+            DisplayMode::ShadedWireframe => "Shaded + wireframe",
+            DisplayMode::HiddenLine => "Hidden line",
+        }
+    }
+
+    /// The mode after this one, going round all of them.
+    pub fn next(&self) -> DisplayMode {
+        let index = DisplayMode::ALL
+            .iter()
+            .position(|mode| mode == self)
+            .unwrap();
+        DisplayMode::ALL[(index + 1) % DisplayMode::ALL.len()]
+    }
+
+    /// Are the faces drawn with their materials?
+    pub fn shows_surfaces(&self) -> bool {
+        matches!(self, DisplayMode::Shaded | DisplayMode::ShadedWireframe)
+    }
+
+    /// Are the edges drawn, with the edge renderer?
+    pub fn shows_edges(&self) -> bool {
+        !matches!(self, DisplayMode::Shaded)
+    }
+}
+
+/// Renders the edges of faces, from the barycentric coordinates of
+/// their corners, into the layers of the geometry pass. They are unlit,
+/// so they keep their color under any light.
+pub struct EdgeRenderer {
+    shader: MaterialPrograms,
+    material: Material,
+}
+
+impl EdgeRenderer {
+    pub fn new(gl: &Gl) -> EdgeRenderer {
+        let mut material = Material::new();
+        material.illumination = 0;
+        EdgeRenderer {
+            shader: MaterialPrograms::new(
+                gl,
+                include_str!("shaders/edges.vert"),
+                include_str!("shaders/edges.frag"),
+            ),
+            material,
+        }
+    }
+
+    pub fn render(&self, camera: &camera::Camera, mesh: &MeshContainer, edges: &EdgeData) {
+        let (edge_color, fill, depth_offset) = match mesh.display_mode {
+            DisplayMode::Shaded => return,
+            DisplayMode::Wireframe => (vec3(0.9, 0.9, 0.9), 0.0, 0.0),
+            // Edges are drawn at the depth of the faces they border, so
+            // they are moved in front of them.
+            DisplayMode::ShadedWireframe => (vec3(0.0, 0.0, 0.0), 0.0, 2e-5),
+            DisplayMode::HiddenLine => (vec3(0.0, 0.0, 0.0), 1.0, 0.0),
+        };
+        let program = self.shader.program(Pass::Geometry, camera);
+        use_material(program, &self.material);
+        program.add_uniform_vec3("edge_color", &edge_color).unwrap();
+        program
+            .add_uniform_vec3("fill_color", &vec3(1.0, 1.0, 1.0))
+            .unwrap();
+        program.add_uniform_float("fill", &fill).unwrap();
+        program
+            .add_uniform_float("depth_offset", &depth_offset)
+            .unwrap();
+        program
+            .add_uniform_mat4("modelMatrix", &mesh.model_matrix)
+            .unwrap();
+        program.use_uniform_block(camera.matrix_buffer(), "Camera");
+        program
+            .use_attribute_vec3_float(&edges.position_buffer, "position")
+            .unwrap();
+        program.draw_arrays(edges.num_corners as u32);
+    }
+}
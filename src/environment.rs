@@ -0,0 +1,473 @@
+//! Light from all around the scene, taken from an equirectangular HDR
+//! image. The image is prefiltered when it is loaded: into spherical
+//! harmonics for diffuse light, and into blurred copies of itself for
+//! reflections of rougher and rougher surfaces. It can also be drawn
+//! behind the scene.
+
+use super::{Model, Msg, ShouldRender};
+use crate::diagnostics::FileStatus;
+use image::hdr::HdrDecoder;
+use log::warn;
+use std::f32::consts::PI;
+use std::io::Cursor;
+use three_d::core::Error;
+use three_d::*;
+use yew::services::reader::File;
+use yew::{html, ChangeData, Html, InputData};
+
+/// Roughness levels of the reflections, evenly spaced from a mirror to
+/// fully rough, stacked from top to bottom in one texture.
+pub const SPECULAR_LEVELS: usize = 5;
+const SPECULAR_WIDTH: usize = 128;
+const SPECULAR_HEIGHT: usize = 64;
+/// Widest the background is kept.
+const BACKGROUND_WIDTH: usize = 1024;
+/// Size that diffuse light is computed from. It changes slowly with
+/// direction, so a small image is enough.
+const IRRADIANCE_WIDTH: usize = 64;
+/// Texels that add less than this to a reflection, relative to the
+/// middle of its lobe, are left out.
+const LOBE_CUTOFF: f32 = 1e-3;
+/// Brightest color that the RGBM encoding of the textures keeps.
+pub const RGBM_RANGE: f32 = 16.0;
+
+/// Radiance from every direction, as an equirectangular image. Its top
+/// row is straight up (+y), and its middle column looks along +x.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvironmentMap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3>,
+}
+
+impl EnvironmentMap {
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> EnvironmentMap {
+        assert_eq!(pixels.len(), width * height);
+        EnvironmentMap {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Reads a Radiance HDR image.
+    pub fn from_hdr(data: &[u8]) -> image::ImageResult<EnvironmentMap> {
+        let decoder = HdrDecoder::new(Cursor::new(data))?;
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()?
+            .iter()
+            .map(|pixel| vec3(pixel[0], pixel[1], pixel[2]))
+            .collect();
+        Ok(EnvironmentMap::new(
+            metadata.width as usize,
+            metadata.height as usize,
+            pixels,
+        ))
+    }
+
+    /// Direction that texture coordinates look in.
+    pub fn direction(u: f32, v: f32) -> Vec3 {
+        let phi = (u - 0.5) * 2.0 * PI;
+        let theta = v * PI;
+        vec3(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        )
+    }
+
+    /// Direction of the middle of a texel.
+    fn texel_direction(&self, x: usize, y: usize) -> Vec3 {
+        EnvironmentMap::direction(
+            (x as f32 + 0.5) / self.width as f32,
+            (y as f32 + 0.5) / self.height as f32,
+        )
+    }
+
+    /// Solid angle covered by a texel of a row. Texels near the poles
+    /// cover less.
+    fn solid_angle(&self, y: usize) -> f32 {
+        let theta = (y as f32 + 0.5) / self.height as f32 * PI;
+        (2.0 * PI / self.width as f32) * (PI / self.height as f32) * theta.sin()
+    }
+
+    /// Radiance in a direction, from the nearest texel.
+    pub fn sample(&self, direction: Vec3) -> Vec3 {
+        let direction = direction.normalize();
+        let u = direction.z.atan2(direction.x) / (2.0 * PI) + 0.5;
+        let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.pixels[y * self.width + x]
+    }
+
+    /// Averages the texels that fall into each texel of an image of
+    /// another size. Texels are repeated where it is larger.
+    pub fn resized(&self, width: usize, height: usize) -> EnvironmentMap {
+        let width = width.max(1);
+        let height = height.max(1);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let y0 = y * self.height / height;
+            let y1 = ((y + 1) * self.height / height).max(y0 + 1);
+            for x in 0..width {
+                let x0 = x * self.width / width;
+                let x1 = ((x + 1) * self.width / width).max(x0 + 1);
+                let mut sum = vec3(0.0, 0.0, 0.0);
+                for row in y0..y1 {
+                    for column in x0..x1 {
+                        sum += self.pixels[row * self.width + column];
+                    }
+                }
+                pixels.push(sum / ((y1 - y0) * (x1 - x0)) as f32);
+            }
+        }
+        EnvironmentMap::new(width, height, pixels)
+    }
+
+    /// Diffuse light as nine spherical harmonics coefficients, in the
+    /// order of `sh_basis`. They are divided by pi, so that a
+    /// white surface lit by an environment of one color reflects that
+    /// color, as it does for the other lights.
+    pub fn irradiance(&self) -> [Vec3; 9] {
+        let map = self.resized(IRRADIANCE_WIDTH, IRRADIANCE_WIDTH / 2);
+        let mut coefficients = [vec3(0.0, 0.0, 0.0); 9];
+        for y in 0..map.height {
+            let solid_angle = map.solid_angle(y);
+            for x in 0..map.width {
+                let radiance = map.pixels[y * map.width + x] * solid_angle;
+                let basis = sh_basis(map.texel_direction(x, y));
+                for (coefficient, value) in coefficients.iter_mut().zip(basis.iter()) {
+                    *coefficient += radiance * *value;
+                }
+            }
+        }
+        // Convolution with the cosine lobe of diffuse surfaces, which
+        // only keeps the lowest frequencies.
+        for (i, coefficient) in coefficients.iter_mut().enumerate() {
+            let band = match i {
+                0 => PI,
+                1..=3 => 2.0 * PI / 3.0,
+                _ => PI / 4.0,
+            };
+            *coefficient *= band / PI;
+        }
+        coefficients
+    }
+
+    /// The light a surface with `roughness` reflects in each direction,
+    /// as an image of `width` by `height`. Texels are weighted by a lobe
+    /// around the reflected direction, which widens with roughness.
+    pub fn prefiltered(&self, roughness: f32, width: usize, height: usize) -> EnvironmentMap {
+        let alpha = roughness * roughness;
+        if alpha < 1e-3 {
+            return self.resized(width, height);
+        }
+        let power = (2.0 / (alpha * alpha) - 2.0).max(1.0);
+        // Angle at which the lobe drops below the cutoff.
+        let cutoff = (LOBE_CUTOFF.ln() / power).exp().acos();
+        // Wide lobes blur away the details, so they are taken from a
+        // smaller image with a few texels across the lobe.
+        let source_width = ((2.0 * PI / (cutoff / 4.0)) as usize)
+            .next_power_of_two()
+            .max(16)
+            .min(self.width);
+        let source = self.resized(source_width, source_width / 2);
+        let directions: Vec<Vec3> = (0..source.height)
+            .flat_map(|y| (0..source.width).map(move |x| (x, y)))
+            .map(|(x, y)| source.texel_direction(x, y))
+            .collect();
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let theta = (y as f32 + 0.5) / height as f32 * PI;
+            let first_row = (((theta - cutoff) / PI * source.height as f32)
+                .floor()
+                .max(0.0)) as usize;
+            let last_row =
+                (((theta + cutoff) / PI * source.height as f32).ceil() as usize).min(source.height);
+            // Longitudes that the lobe reaches, unless it covers a pole.
+            let half_columns = if cutoff < theta && cutoff < PI - theta {
+                let half_angle = (cutoff.sin() / theta.sin()).min(1.0).asin();
+                (half_angle / (2.0 * PI) * source.width as f32).ceil() as usize + 1
+            } else {
+                source.width
+            };
+            for x in 0..width {
+                let u = (x as f32 + 0.5) / width as f32;
+                let reflected = EnvironmentMap::direction(u, theta / PI);
+                let center = (u * source.width as f32) as usize;
+                let columns: Vec<usize> = if 2 * half_columns + 1 >= source.width {
+                    (0..source.width).collect()
+                } else {
+                    (0..=2 * half_columns)
+                        .map(|offset| {
+                            (center + source.width + offset - half_columns) % source.width
+                        })
+                        .collect()
+                };
+                let mut sum = vec3(0.0, 0.0, 0.0);
+                let mut total = 0.0;
+                for row in first_row..last_row {
+                    let solid_angle = source.solid_angle(row);
+                    for column in columns.iter() {
+                        let index = row * source.width + column;
+                        let cosine = directions[index].dot(reflected);
+                        if cosine <= 0.0 {
+                            continue;
+                        }
+                        let weight = cosine.powf(power) * solid_angle;
+                        sum += source.pixels[index] * weight;
+                        total += weight;
+                    }
+                }
+                pixels.push(if total > 0.0 {
+                    sum / total
+                } else {
+                    source.sample(reflected)
+                });
+            }
+        }
+        EnvironmentMap::new(width, height, pixels)
+    }
+}
+
+/// The nine real spherical harmonics up to the second band, in a
+/// direction, in the order of the coefficients of
+/// `EnvironmentMap::irradiance` and environment_light.frag.
+pub fn sh_basis(n: Vec3) -> [f32; 9] {
+    [
+        0.282_095,
+        0.488_603 * n.y,
+        0.488_603 * n.z,
+        0.488_603 * n.x,
+        1.092_548 * n.x * n.y,
+        1.092_548 * n.y * n.z,
+        0.315_392 * (3.0 * n.z * n.z - 1.0),
+        1.092_548 * n.x * n.z,
+        0.546_274 * (n.x * n.x - n.y * n.y),
+    ]
+}
+
+/// Stores a color brighter than one in eight bits per channel, with a
+/// multiplier in alpha, as `decode_rgbm` of environment.frag reads it.
+pub fn encode_rgbm(color: Vec3) -> [u8; 4] {
+    let largest = color.x.max(color.y).max(color.z) / RGBM_RANGE;
+    let multiplier = (largest.min(1.0) * 255.0).ceil() / 255.0;
+    if multiplier <= 0.0 {
+        return [0, 0, 0, 0];
+    }
+    let scale = 255.0 / (multiplier * RGBM_RANGE);
+    let channel = |value: f32| (value * scale).round().clamp(0.0, 255.0) as u8;
+    [
+        channel(color.x),
+        channel(color.y),
+        channel(color.z),
+        (multiplier * 255.0).round() as u8,
+    ]
+}
+
+fn rgbm_bytes(map: &EnvironmentMap, bytes: &mut Vec<u8>) {
+    for pixel in map.pixels.iter() {
+        bytes.extend_from_slice(&encode_rgbm(*pixel));
+    }
+}
+
+/// Reflections of each roughness level, stacked from the mirror at the
+/// top to the roughest at the bottom, as RGBM.
+pub fn specular_atlas(map: &EnvironmentMap) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(SPECULAR_WIDTH * SPECULAR_HEIGHT * SPECULAR_LEVELS * 4);
+    for level in 0..SPECULAR_LEVELS {
+        let roughness = level as f32 / (SPECULAR_LEVELS - 1) as f32;
+        rgbm_bytes(
+            &map.prefiltered(roughness, SPECULAR_WIDTH, SPECULAR_HEIGHT),
+            &mut bytes,
+        );
+    }
+    bytes
+}
+
+/// An environment that lights the scene, ready to be drawn with.
+pub struct Environment {
+    /// Name of the file it was loaded from.
+    pub name: String,
+    /// Multiplies the light of the environment.
+    pub intensity: f32,
+    /// Draw the environment behind the scene, instead of the background
+    /// color?
+    pub show_background: bool,
+    /// Spherical harmonics coefficients of the diffuse light.
+    pub irradiance: [Vec3; 9],
+    /// Reflections of each roughness level, from `specular_atlas`.
+    pub specular: Texture2D,
+    pub background: Texture2D,
+}
+
+impl Environment {
+    pub fn new(gl: &Gl, name: &str, map: &EnvironmentMap) -> Result<Environment, Error> {
+        let create_texture = |width: usize, height: usize, bytes: &[u8]| {
+            // Longitudes wrap around, latitudes end at the poles.
+            Texture2D::new_with_u8(
+                gl,
+                Interpolation::Linear,
+                Interpolation::Linear,
+                None,
+                Wrapping::Repeat,
+                Wrapping::ClampToEdge,
+                width as u32,
+                height as u32,
+                bytes,
+            )
+        };
+        let specular = create_texture(
+            SPECULAR_WIDTH,
+            SPECULAR_HEIGHT * SPECULAR_LEVELS,
+            &specular_atlas(map),
+        )?;
+        let background_map = map.resized(
+            BACKGROUND_WIDTH.min(map.width),
+            (BACKGROUND_WIDTH / 2).min(map.height),
+        );
+        let mut background_bytes = vec![];
+        rgbm_bytes(&background_map, &mut background_bytes);
+        let background = create_texture(
+            background_map.width,
+            background_map.height,
+            &background_bytes,
+        )?;
+        Ok(Environment {
+            name: name.to_string(),
+            intensity: 1.0,
+            show_background: true,
+            irradiance: map.irradiance(),
+            specular,
+            background,
+        })
+    }
+}
+
+fn load_environment(model: &mut Model, name: &str, data: &[u8]) -> Result<Environment, String> {
+    let map = EnvironmentMap::from_hdr(data).map_err(|e| e.to_string())?;
+    let gl = model.gl.as_ref().expect("GL Context not initialized!");
+    Environment::new(gl, name, &map).map_err(|e| format!("{:?}", e))
+}
+
+pub fn update(model: &mut Model, msg: &Msg) -> ShouldRender {
+    match msg {
+        Msg::EnvironmentFiles(files) => {
+            // Only one environment is kept, so only the last file counts.
+            if let Some(file) = files.last() {
+                model.diagnostics.start(&file.name());
+                let callback = model.link.callback(Msg::EnvironmentLoaded);
+                let task = model.reader.read_file(file.clone(), callback).unwrap();
+                model.tasks.push(task);
+                return true;
+            }
+        }
+        Msg::EnvironmentLoaded(file) => {
+            match load_environment(model, &file.name, &file.content) {
+                Ok(environment) => {
+                    // A new environment keeps the settings of the last.
+                    let environment = match &model.environment {
+                        Some(old) => Environment {
+                            intensity: old.intensity,
+                            show_background: old.show_background,
+                            ..environment
+                        },
+                        None => environment,
+                    };
+                    model.environment = Some(environment);
+                    model.diagnostics.set_status(&file.name, FileStatus::Loaded);
+                }
+                Err(e) => {
+                    warn!(
+                        "Could not load {} as an environment due to {}",
+                        file.name, e
+                    );
+                    model
+                        .diagnostics
+                        .set_status(&file.name, FileStatus::Failed(e));
+                }
+            }
+            return true;
+        }
+        Msg::EnvironmentIntensityChanged(intensity) => {
+            if let Some(environment) = model.environment.as_mut() {
+                environment.intensity = *intensity;
+            }
+        }
+        Msg::ToggleEnvironmentBackground => {
+            if let Some(environment) = model.environment.as_mut() {
+                environment.show_background = !environment.show_background;
+                return true;
+            }
+        }
+        Msg::RemoveEnvironment => {
+            model.environment = None;
+            return true;
+        }
+        _ => {}
+    };
+    false
+}
+
+pub fn view(model: &Model) -> Html {
+    let file_cb = model.link.callback(|value| {
+        let mut result = Vec::new();
+        if let ChangeData::Files(files) = value {
+            let files = js_sys::try_iter(&files)
+                .unwrap()
+                .unwrap()
+                .map(|v| File::from(v.unwrap()));
+            result.extend(files);
+        }
+        Msg::EnvironmentFiles(result)
+    });
+    html! {
+        <table>
+            <tr>
+            <td>
+            { "Environment:" }
+            </td>
+            <td>
+            <input id="load_environment" type="file" accept=".hdr" onchange=file_cb />
+            </td>
+            </tr>
+            { view_settings(model) }
+        </table>
+    }
+}
+
+/// A row to change the loaded environment, if there is one.
+fn view_settings(model: &Model) -> Html {
+    let environment = match &model.environment {
+        Some(environment) => environment,
+        None => return html! {},
+    };
+    let intensity = environment.intensity;
+    let intensity_cb = model.link.callback(move |event: InputData| {
+        let intensity = event.value.parse::<f32>().unwrap_or(intensity);
+        Msg::EnvironmentIntensityChanged(intensity.max(0.0))
+    });
+    let background_cb = model.link.callback(|_| Msg::ToggleEnvironmentBackground);
+    let remove_cb = model.link.callback(|_| Msg::RemoveEnvironment);
+    html! {
+        <tr>
+        <td>
+        { &environment.name }
+        </td>
+        <td>
+        { "intensity " }
+        <input type="number" min="0" step="0.1" style="width:4em" value={intensity.to_string()} oninput=intensity_cb />
+        </td>
+        <td>
+        <input type="checkbox" checked={environment.show_background} onclick=background_cb />
+        { "show as background" }
+        </td>
+        <td>
+        <button onclick=remove_cb>{ "Remove" }</button>
+        </td>
+        </tr>
+    }
+}
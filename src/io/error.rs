@@ -0,0 +1,196 @@
+//! Module for the error type of all IO operations
+
+use std::{fmt, io::Error as ioError};
+
+//------------------------------------------------------------------------------
+
+/// What went wrong while reading or writing a file
+pub enum ErrorKind {
+    /// Reading or writing failed, the source error says why.
+    AccessFile,
+    /// None of the formats can load the file.
+    UnknownFormat,
+    /// Another file is needed first, such as the buffer of a glTF file.
+    MissingResource(String),
+    LineParse,
+    InvalidMeshIndices,
+    InvalidVertex,
+    InvalidProperty,
+    InvalidType(String),
+    InvalidVertexType,
+    InvalidFaceType,
+    InvalidVertexDimensionDefinition,
+    PropertyLineLocation,
+    FaceStructure,
+    LoadStartNotFound,
+    LoadFormatNotFound,
+    LoadHeaderInvalid,
+    LoadVertexCountIncorrect,
+    LoadFaceCountIncorrect,
+    ColorArrayIncorrectLength,
+    /// A material property for a material that wasn't defined.
+    NoMaterial,
+    UnsupportedDimension,
+    InvalidUri(String),
+    InvalidBuffer(usize),
+    InvalidPrimitive(usize),
+    /// Errors of the libraries that parse some of the formats.
+    Parse(String),
+}
+
+/// Error type for all IO operations. Besides what went wrong, it says
+/// where, as precisely as the format allows: the line of text formats,
+/// and the byte offset and element of binary ones.
+pub struct Error {
+    pub kind: ErrorKind,
+    pub file: Option<String>,
+    /// Line number, starting at 1.
+    pub line: Option<usize>,
+    /// Offset from the start of the file, in bytes.
+    pub offset: Option<u64>,
+    /// Name and index of the element being read, such as face 12.
+    pub element: Option<(&'static str, usize)>,
+    source: Option<ioError>,
+}
+
+/// Result type for all IO operations
+pub type IoResult<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Error {
+        Error {
+            kind,
+            file: None,
+            line: None,
+            offset: None,
+            element: None,
+            source: None,
+        }
+    }
+
+    /// An error on a line of a text format.
+    pub fn on_line(kind: ErrorKind, line: usize) -> Error {
+        Error::new(kind).at_line(line)
+    }
+
+    /// Wraps the error of a library that parses a format.
+    pub fn parse<E: fmt::Debug>(error: E) -> Error {
+        Error::new(ErrorKind::Parse(format!("{:?}", error)))
+    }
+
+    /// Names the file, unless it's named already.
+    pub fn in_file(mut self, file: &str) -> Error {
+        self.file.get_or_insert_with(|| file.to_string());
+        self
+    }
+
+    /// Sets the line, unless it's known already.
+    pub fn at_line(mut self, line: usize) -> Error {
+        self.line.get_or_insert(line);
+        self
+    }
+
+    /// Sets the byte offset, unless it's known already.
+    pub fn at_offset(mut self, offset: u64) -> Error {
+        self.offset.get_or_insert(offset);
+        self
+    }
+
+    /// Sets the element, unless it's known already.
+    pub fn at_element(mut self, element: &'static str, index: usize) -> Error {
+        self.element.get_or_insert((element, index));
+        self
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::AccessFile => write!(f, "Unable to access file"),
+            Self::UnknownFormat => write!(f, "Unknown file format"),
+            Self::MissingResource(x) => write!(f, "Missing resource {}", x),
+            Self::LineParse => write!(f, "Unable to parse line"),
+            Self::InvalidMeshIndices => write!(f, "File contains invalid mesh indices"),
+            Self::InvalidVertex => write!(f, "Invalid vertex definition"),
+            Self::InvalidProperty => write!(f, "Invalid property"),
+            Self::InvalidType(x) => write!(f, "Invalid type in header '{}'", x),
+            Self::InvalidVertexType => write!(f, "Invalid vertex type in header"),
+            Self::InvalidFaceType => write!(f, "Invalid face type in header"),
+            Self::InvalidVertexDimensionDefinition => {
+                write!(f, "Invalid order / definition of vertex dimension order")
+            }
+            Self::PropertyLineLocation => write!(f, "Found property line at unexpected location"),
+            Self::FaceStructure => write!(
+                f,
+                "Invalid face structure, expecting one texture coordinate per corner"
+            ),
+            Self::LoadStartNotFound => write!(f, "Start of .ply header not found"),
+            Self::LoadFormatNotFound => write!(f, "Format of .ply missing or not supported"),
+            Self::LoadHeaderInvalid => write!(f, "Header seems to be invalid"),
+            Self::LoadVertexCountIncorrect => write!(f, "Vertex count in header is incorrect"),
+            Self::LoadFaceCountIncorrect => write!(f, "Face count in header is incorrect"),
+            Self::ColorArrayIncorrectLength => {
+                write!(f, "The provided color array has an incorrect length")
+            }
+            Self::NoMaterial => write!(f, "Material property before newmtl"),
+            Self::UnsupportedDimension => write!(f, "Only 3D vertices are supported"),
+            Self::InvalidUri(x) => write!(f, "Unsupported URI {}", x),
+            Self::InvalidBuffer(x) => write!(f, "Buffer {} is too short", x),
+            Self::InvalidPrimitive(x) => {
+                write!(f, "Attributes of primitive {} have different lengths", x)
+            }
+            Self::Parse(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(file) = &self.file {
+            write!(f, " in {}", file)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, " on line {}", line)?;
+        }
+        if let Some((element, index)) = self.element {
+            write!(f, " at {} {}", element, index)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error::new(kind)
+    }
+}
+
+impl From<ioError> for Error {
+    fn from(error: ioError) -> Self {
+        Error {
+            source: Some(error),
+            ..Error::new(ErrorKind::AccessFile)
+        }
+    }
+}
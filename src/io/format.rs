@@ -0,0 +1,609 @@
+//! Module for choosing how to load a file
+
+use rust_3d::*;
+use std::collections::HashMap;
+use std::path::Path;
+use three_d::core::types::InnerSpace;
+
+use super::error::{Error, ErrorKind, IoResult};
+use super::*;
+
+//------------------------------------------------------------------------------
+
+/// A file format that files can be loaded from.
+pub trait MeshFormat {
+    /// Name of the format, for messages.
+    fn name(&self) -> &'static str;
+
+    /// Lower case file extensions of the format, without the dot.
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Does the content look like a file of this format? Formats that
+    /// can't tell, such as XYZ files which are only numbers, leave it
+    /// to the extension.
+    fn detect(&self, _data: &[u8]) -> bool {
+        false
+    }
+
+    /// Loads a file. `resources` holds the files loaded so far, for
+    /// formats that refer to other files.
+    fn load(
+        &self,
+        data: &[u8],
+        name: &str,
+        resources: &HashMap<String, Vec<u8>>,
+    ) -> IoResult<Scene<SceneMesh3D>>;
+}
+
+/// All formats that files can be loaded from.
+pub struct FormatRegistry {
+    formats: Vec<Box<dyn MeshFormat>>,
+}
+
+impl FormatRegistry {
+    /// A registry without any formats.
+    pub fn empty() -> FormatRegistry {
+        FormatRegistry { formats: vec![] }
+    }
+
+    /// A registry with all formats that are built in. Formats with a
+    /// signature come first, since they are the most reliable to detect.
+    pub fn new() -> FormatRegistry {
+        let mut registry = FormatRegistry::empty();
+        registry.register(Box::new(PlyMeshFormat));
+        registry.register(Box::new(GltfMeshFormat));
+        for format in IMAGE_FORMATS.iter() {
+            registry.register(Box::new(format.clone()));
+        }
+        registry.register(Box::new(StlMeshFormat));
+        registry.register(Box::new(OffMeshFormat));
+        registry.register(Box::new(ObjMeshFormat));
+        registry.register(Box::new(MtlFormat));
+        registry.register(Box::new(XyzMeshFormat));
+        registry.register(Box::new(BufferFormat));
+        registry
+    }
+
+    pub fn register(&mut self, format: Box<dyn MeshFormat>) {
+        self.formats.push(format);
+    }
+
+    /// Finds the format of a file by its content, so that files with
+    /// a wrong or no extension load too. The extension is only used
+    /// when the content could be more than one format, or none of the
+    /// formats recognizes it.
+    pub fn find(&self, name: &str, data: &[u8]) -> Option<&dyn MeshFormat> {
+        let by_extension = self.find_by_extension(name);
+        if let Some(format) = by_extension {
+            if format.detect(data) {
+                return Some(format);
+            }
+        }
+        self.formats
+            .iter()
+            .find(|format| format.detect(data))
+            .map(|format| format.as_ref())
+            .or(by_extension)
+    }
+
+    fn find_by_extension(&self, name: &str) -> Option<&dyn MeshFormat> {
+        let extension = Path::new(name)
+            .extension()?
+            .to_string_lossy()
+            .to_lowercase();
+        self.formats
+            .iter()
+            .find(|format| format.extensions().contains(&&extension[..]))
+            .map(|format| format.as_ref())
+    }
+
+    /// Loads a file with the format it's found to have.
+    pub fn load(
+        &self,
+        name: &str,
+        data: &[u8],
+        resources: &HashMap<String, Vec<u8>>,
+    ) -> IoResult<Scene<SceneMesh3D>> {
+        let format = self.find(name, data).ok_or(ErrorKind::UnknownFormat)?;
+        format
+            .load(data, name, resources)
+            .map_err(|e| e.in_file(name))
+    }
+
+    /// The extensions of all formats, as in the accept attribute of
+    /// file inputs.
+    pub fn accept(&self) -> String {
+        let mut extensions: Vec<String> = vec![];
+        for format in self.formats.iter() {
+            for extension in format.extensions().iter() {
+                let extension = format!(".{}", extension);
+                if !extensions.contains(&extension) {
+                    extensions.push(extension);
+                }
+            }
+        }
+        extensions.join(", ")
+    }
+}
+
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//------------------------------------------------------------------------------
+
+/// How much of a file to look at to tell whether it's text of a format.
+const SNIFF_LENGTH: usize = 4096;
+
+/// The start of a file in whole lines, if it is text.
+fn sniff_text(data: &[u8]) -> Option<&str> {
+    let mut data = data;
+    if data.len() > SNIFF_LENGTH {
+        let end = data[..SNIFF_LENGTH].iter().rposition(|c| *c == b'\n')?;
+        data = &data[..end];
+    }
+    std::str::from_utf8(data).ok()
+}
+
+/// Is the text made of lines that start with the keywords of a format,
+/// with at least one line starting with `required`? A few other lines
+/// are allowed, since formats get extended.
+fn has_keyword_lines(text: &str, keywords: &[&str], required: &str) -> bool {
+    let mut known = 0;
+    let mut unknown = 0;
+    let mut has_required = false;
+    for line in text.lines() {
+        match line.split_whitespace().next() {
+            None => {}
+            Some(word) if word.starts_with('#') => {}
+            Some(word) if keywords.contains(&word) => {
+                known += 1;
+                has_required = has_required || word == required;
+            }
+            Some(_) => unknown += 1,
+        }
+    }
+    has_required && unknown * 4 <= known
+}
+
+const OBJ_KEYWORDS: [&str; 12] = [
+    "v", "vt", "vn", "vp", "f", "l", "p", "o", "g", "s", "usemtl", "mtllib",
+];
+
+const MTL_KEYWORDS: [&str; 35] = [
+    "newmtl",
+    "Ka",
+    "Kd",
+    "Ks",
+    "Ke",
+    "Ns",
+    "Ni",
+    "d",
+    "Tr",
+    "Tf",
+    "illum",
+    "sharpness",
+    "map_Ka",
+    "map_Kd",
+    "map_Ks",
+    "map_Ke",
+    "map_Ns",
+    "map_d",
+    "map_bump",
+    "map_Bump",
+    "bump",
+    "disp",
+    "decal",
+    "refl",
+    "norm",
+    // Extensions for physically based materials.
+    "Pr",
+    "Pm",
+    "Ps",
+    "Pc",
+    "Pcr",
+    "aniso",
+    "anisor",
+    "map_Pr",
+    "map_Pm",
+    "map_Ps",
+];
+
+//------------------------------------------------------------------------------
+
+struct PlyMeshFormat;
+
+impl MeshFormat for PlyMeshFormat {
+    fn name(&self) -> &'static str {
+        "PLY"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["ply"]
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        data.starts_with(b"ply\n") || data.starts_with(b"ply\r\n")
+    }
+
+    fn load(
+        &self,
+        data: &[u8],
+        name: &str,
+        _resources: &HashMap<String, Vec<u8>>,
+    ) -> IoResult<Scene<SceneMesh3D>> {
+        let mut mesh = SceneMesh3D::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        load_ply(
+            &mut &data[..],
+            &mut mesh,
+            &mut material,
+            &mut material_info,
+            name,
+        )?;
+        let mut scene = Scene::new();
+        scene.materials.insert(name.to_string(), material);
+        scene.add_mesh(name, mesh, material_info);
+        Ok(scene)
+    }
+}
+
+struct ObjMeshFormat;
+
+impl MeshFormat for ObjMeshFormat {
+    fn name(&self) -> &'static str {
+        "OBJ"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["obj"]
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        match sniff_text(data) {
+            Some(text) => has_keyword_lines(text, &OBJ_KEYWORDS[..], "v"),
+            None => false,
+        }
+    }
+
+    fn load(
+        &self,
+        data: &[u8],
+        name: &str,
+        _resources: &HashMap<String, Vec<u8>>,
+    ) -> IoResult<Scene<SceneMesh3D>> {
+        let mut mesh = SceneMesh3D::default();
+        let mut material_info = MaterialInfo::new();
+        load_obj_mesh(&mut &data[..], &mut mesh, &mut material_info)?;
+        let mut scene = Scene::new();
+        scene.add_mesh(name, mesh, material_info);
+        Ok(scene)
+    }
+}
+
+struct OffMeshFormat;
+
+impl MeshFormat for OffMeshFormat {
+    fn name(&self) -> &'static str {
+        "OFF"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["off"]
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        // Skip comments and empty lines before the header.
+        let header = data.split(|c| *c == b'\n').take(64).find(|line| {
+            let line = line.trim_ascii_start();
+            !line.is_empty() && !line.starts_with(b"#")
+        });
+        matches!(header, Some(line) if is_off_header(line))
+    }
+
+    fn load(
+        &self,
+        data: &[u8],
+        name: &str,
+        _resources: &HashMap<String, Vec<u8>>,
+    ) -> IoResult<Scene<SceneMesh3D>> {
+        let mut mesh = SceneMesh3D::default();
+        let mut material_info = MaterialInfo::new();
+        load_off_mesh(&mut &data[..], &mut mesh, &mut material_info)?;
+        let mut scene = Scene::new();
+        scene.add_mesh(name, mesh, material_info);
+        Ok(scene)
+    }
+}
+
+struct StlMeshFormat;
+
+impl MeshFormat for StlMeshFormat {
+    fn name(&self) -> &'static str {
+        "STL"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["stl"]
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        // Binary files have an 80 byte header, the number of facets and
+        // 50 bytes per facet.
+        if data.len() >= 84 {
+            let mut count = [0u8; 4];
+            count.copy_from_slice(&data[80..84]);
+            // In u64, as the size of a large count doesn't fit in the
+            // usize of wasm32.
+            let size = (u32::from_le_bytes(count) as u64)
+                .checked_mul(50)
+                .and_then(|facets| facets.checked_add(84));
+            if size == Some(data.len() as u64) {
+                return true;
+            }
+        }
+        match sniff_text(data) {
+            Some(text) => text.trim_start().starts_with("solid") && text.contains("facet"),
+            None => false,
+        }
+    }
+
+    fn load(
+        &self,
+        data: &[u8],
+        name: &str,
+        _resources: &HashMap<String, Vec<u8>>,
+    ) -> IoResult<Scene<SceneMesh3D>> {
+        let mut mesh = SceneMesh3D::default();
+        let mut material_info = MaterialInfo::new();
+        let mut facet_normals = vec![];
+        rust_3d::io::load_stl_mesh_unique(
+            &mut &data[..],
+            rust_3d::io::StlFormat::Auto,
+            &mut mesh,
+            &mut facet_normals,
+        )
+        .map_err(from_stl_error)?;
+        // Normals are per facet, so give each wedge the normal of its facet.
+        for (fid, normal) in facet_normals.iter().enumerate() {
+            let mut normal = three_d::vec3(normal.x as f32, normal.y as f32, normal.z as f32);
+            if normal.magnitude2() == 0.0 {
+                // Many files leave the normals out.
+                let [a, b, c] = mesh
+                    .face_vertices(FId { val: fid })
+                    .map_err(|e| Error::parse(e).at_element("facet", fid))?;
+                let a = three_d::vec3(a.x as f32, a.y as f32, a.z as f32);
+                let b = three_d::vec3(b.x as f32, b.y as f32, b.z as f32);
+                let c = three_d::vec3(c.x as f32, c.y as f32, c.z as f32);
+                normal = (b - a).cross(c - a);
+            }
+            let normal = normal.normalize();
+            material_info.normals.extend_from_slice(&[normal; 3]);
+        }
+        let mut scene = Scene::new();
+        scene.add_mesh(name, mesh, material_info);
+        Ok(scene)
+    }
+}
+
+/// Keeps the line of errors of the STL reader of rust-3d.
+fn from_stl_error(error: rust_3d::io::StlError) -> Error {
+    match error {
+        rust_3d::io::StlError::LineParse(i_line) => Error::on_line(ErrorKind::LineParse, i_line),
+        error => Error::parse(error),
+    }
+}
+
+struct XyzMeshFormat;
+
+impl MeshFormat for XyzMeshFormat {
+    fn name(&self) -> &'static str {
+        "XYZ"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["xyz"]
+    }
+
+    fn load(
+        &self,
+        data: &[u8],
+        name: &str,
+        _resources: &HashMap<String, Vec<u8>>,
+    ) -> IoResult<Scene<SceneMesh3D>> {
+        let mut points = PointCloud3D::<Point3D>::new();
+        load_xyz_points(&mut &data[..], &mut points)?;
+        let mut scene = Scene::new();
+        scene.point_clouds.push(ScenePointCloud {
+            name: name.to_string(),
+            points,
+            colors: vec![],
+            normals: vec![],
+        });
+        Ok(scene)
+    }
+}
+
+struct GltfMeshFormat;
+
+impl MeshFormat for GltfMeshFormat {
+    fn name(&self) -> &'static str {
+        "glTF"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["gltf", "glb"]
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        if data.starts_with(b"glTF") {
+            return true;
+        }
+        // The JSON of .gltf files always has an asset property.
+        match sniff_text(data) {
+            Some(text) => text.trim_start().starts_with('{') && text.contains("\"asset\""),
+            None => false,
+        }
+    }
+
+    fn load(
+        &self,
+        data: &[u8],
+        name: &str,
+        resources: &HashMap<String, Vec<u8>>,
+    ) -> IoResult<Scene<SceneMesh3D>> {
+        load_gltf::<SceneMesh3D, Point3D>(data, name, resources)
+    }
+}
+
+/// Binary buffers, which glTF files refer to.
+struct BufferFormat;
+
+impl MeshFormat for BufferFormat {
+    fn name(&self) -> &'static str {
+        "buffer"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["bin"]
+    }
+
+    fn load(
+        &self,
+        data: &[u8],
+        name: &str,
+        _resources: &HashMap<String, Vec<u8>>,
+    ) -> IoResult<Scene<SceneMesh3D>> {
+        let mut scene = Scene::new();
+        scene.resources.insert(name.to_string(), data.to_vec());
+        Ok(scene)
+    }
+}
+
+struct MtlFormat;
+
+impl MeshFormat for MtlFormat {
+    fn name(&self) -> &'static str {
+        "MTL"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["mtl"]
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        match sniff_text(data) {
+            Some(text) => has_keyword_lines(text, &MTL_KEYWORDS[..], "newmtl"),
+            None => false,
+        }
+    }
+
+    fn load(
+        &self,
+        data: &[u8],
+        _name: &str,
+        _resources: &HashMap<String, Vec<u8>>,
+    ) -> IoResult<Scene<SceneMesh3D>> {
+        let mut scene = Scene::new();
+        scene.materials = load_mtl(&mut &data[..])?;
+        Ok(scene)
+    }
+}
+
+/// Images, which are used as textures. The file is kept as a resource
+/// so that it can be exported as is.
+#[derive(Clone)]
+struct ImageFileFormat {
+    name: &'static str,
+    extensions: &'static [&'static str],
+    format: image::ImageFormat,
+}
+
+const IMAGE_FORMATS: [ImageFileFormat; 11] = [
+    ImageFileFormat {
+        name: "PNG",
+        extensions: &["png"],
+        format: image::ImageFormat::Png,
+    },
+    ImageFileFormat {
+        name: "JPEG",
+        extensions: &["jpeg", "jpg"],
+        format: image::ImageFormat::Jpeg,
+    },
+    ImageFileFormat {
+        name: "GIF",
+        extensions: &["gif"],
+        format: image::ImageFormat::Gif,
+    },
+    ImageFileFormat {
+        name: "WebP",
+        extensions: &["webp"],
+        format: image::ImageFormat::WebP,
+    },
+    ImageFileFormat {
+        name: "PNM",
+        extensions: &["pnm"],
+        format: image::ImageFormat::Pnm,
+    },
+    ImageFileFormat {
+        name: "TIFF",
+        extensions: &["tif", "tiff"],
+        format: image::ImageFormat::Tiff,
+    },
+    ImageFileFormat {
+        name: "TGA",
+        extensions: &["tga"],
+        format: image::ImageFormat::Tga,
+    },
+    ImageFileFormat {
+        name: "DDS",
+        extensions: &["dds"],
+        format: image::ImageFormat::Dds,
+    },
+    ImageFileFormat {
+        name: "BMP",
+        extensions: &["bmp"],
+        format: image::ImageFormat::Bmp,
+    },
+    ImageFileFormat {
+        name: "ICO",
+        extensions: &["ico"],
+        format: image::ImageFormat::Ico,
+    },
+    ImageFileFormat {
+        name: "HDR",
+        extensions: &["hdr"],
+        format: image::ImageFormat::Hdr,
+    },
+];
+
+impl MeshFormat for ImageFileFormat {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+
+    fn detect(&self, data: &[u8]) -> bool {
+        matches!(image::guess_format(data), Ok(format) if format == self.format)
+    }
+
+    fn load(
+        &self,
+        data: &[u8],
+        name: &str,
+        _resources: &HashMap<String, Vec<u8>>,
+    ) -> IoResult<Scene<SceneMesh3D>> {
+        let image = image::load_from_memory_with_format(data, self.format)
+            .map_err(|e| Error::new(ErrorKind::Parse(e.to_string())))?;
+        let mut scene = Scene::new();
+        scene.images.insert(name.to_string(), image);
+        scene.resources.insert(name.to_string(), data.to_vec());
+        Ok(scene)
+    }
+}
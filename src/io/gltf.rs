@@ -0,0 +1,552 @@
+//! Module for IO of the glTF 2.0 file format
+
+use log::*;
+use rust_3d::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+use ::gltf::{accessor::Accessor, buffer, image, json, mesh::Mode, scene::Node, Document, Gltf};
+
+use super::{
+    error::{Error, ErrorKind, IoResult},
+    Material, MaterialInfo, MaterialSurface, PbrMaterial, Scene, SceneMesh, TextureMap, IDENTITY,
+};
+
+//------------------------------------------------------------------------------
+
+/// Loads a scene from a .gltf or .glb file. External buffers are looked
+/// up in `resources` by their file name. External images are not needed
+/// here, they're referred to by their file name. Each mesh in the scene
+/// holds all of its primitives, and each primitive is a surface named
+/// after its material.
+pub fn load_gltf<EM, P>(
+    data: &[u8],
+    name: &str,
+    resources: &HashMap<String, Vec<u8>>,
+) -> IoResult<Scene<EM>>
+where
+    EM: IsFaceEditableMesh<P, Face3> + IsVertexEditableMesh<P, Face3> + Default,
+    P: IsBuildable3D + Clone,
+{
+    let parse_error =
+        |e: ::gltf::Error| Error::new(ErrorKind::Parse(format!("Unable to parse glTF: {}", e)));
+    let Gltf { document, blob } = Gltf::from_slice_without_validation(data).map_err(parse_error)?;
+    // The gltf crate panics on some invalid files while validating or
+    // reading them, so check those parts first.
+    let json = document.into_json();
+    check_json(&json)?;
+    let gltf = Gltf {
+        document: Document::from_json(json).map_err(parse_error)?,
+        blob,
+    };
+
+    let mut buffers = vec![];
+    for buffer in gltf.buffers() {
+        let data = match buffer.source() {
+            buffer::Source::Bin => gltf
+                .blob
+                .clone()
+                .ok_or(ErrorKind::InvalidBuffer(buffer.index()))?,
+            buffer::Source::Uri(uri) => resolve_uri(uri, resources)?,
+        };
+        if data.len() < buffer.length() {
+            return Err(ErrorKind::InvalidBuffer(buffer.index()).into());
+        }
+        buffers.push(data);
+    }
+    check_accessors(&gltf, &buffers)?;
+    check_primitives(&gltf)?;
+
+    let mut scene = Scene::new();
+
+    let mut image_names = vec![];
+    for image in gltf.images() {
+        let embedded_name = format!("{}#image{}", name, image.index());
+        let embedded_data = match image.source() {
+            image::Source::View { view, .. } => {
+                let data = &buffers[view.buffer().index()];
+                let end = view.offset() + view.length();
+                if end > data.len() {
+                    return Err(ErrorKind::InvalidBuffer(view.buffer().index()).into());
+                }
+                Some(data[view.offset()..end].to_vec())
+            }
+            image::Source::Uri { uri, .. } => {
+                if uri.starts_with("data:") {
+                    Some(resolve_uri(uri, resources)?)
+                } else {
+                    // Loaded from its own file.
+                    image_names.push(file_name(uri));
+                    None
+                }
+            }
+        };
+        if let Some(data) = embedded_data {
+            match ::image::load_from_memory(&data[..]) {
+                Ok(decoded) => {
+                    scene.images.insert(embedded_name.clone(), decoded);
+                }
+                Err(e) => scene.warnings.push(format!(
+                    "Could not load {} due to error {}",
+                    embedded_name, e
+                )),
+            }
+            scene.resources.insert(embedded_name.clone(), data);
+            image_names.push(embedded_name);
+        }
+    }
+
+    let image_name = |texture: ::gltf::Texture| &image_names[texture.source().index()];
+    for material in gltf.materials() {
+        let pbr = material.pbr_metallic_roughness();
+        let base_color = pbr.base_color_factor();
+        let mut converted = Material::new();
+        converted.diffuse_color = three_d::vec3(base_color[0], base_color[1], base_color[2]);
+        // Opaque materials ignore the alpha of their base color.
+        if material.alpha_mode() == json::material::AlphaMode::Opaque {
+            converted.texture_alpha = false;
+        } else {
+            converted.opacity = base_color[3];
+        }
+        converted.texture_name = pbr
+            .base_color_texture()
+            .map(|info| image_name(info.texture()).clone());
+        let emissive = material.emissive_factor();
+        converted.emissive_color = three_d::vec3(emissive[0], emissive[1], emissive[2]);
+        converted.emissive_map = material
+            .emissive_texture()
+            .map(|info| TextureMap::new(image_name(info.texture())));
+        if let Some(normal) = material.normal_texture() {
+            let mut normal_map = TextureMap::new(image_name(normal.texture()));
+            normal_map.options.bump_multiplier = normal.scale();
+            converted.normal_map = Some(normal_map);
+        }
+        let mut converted_pbr = PbrMaterial::new();
+        converted_pbr.metallic = pbr.metallic_factor();
+        converted_pbr.roughness = pbr.roughness_factor();
+        // Roughness is in the green channel, and metallic in the blue one.
+        if let Some(info) = pbr.metallic_roughness_texture() {
+            let mut roughness_map = TextureMap::new(image_name(info.texture()));
+            roughness_map.options.channel = 1;
+            let mut metallic_map = roughness_map.clone();
+            metallic_map.options.channel = 2;
+            converted_pbr.roughness_map = Some(roughness_map);
+            converted_pbr.metallic_map = Some(metallic_map);
+        }
+        if let Some(occlusion) = material.occlusion_texture() {
+            converted_pbr.occlusion_map = Some(TextureMap::new(image_name(occlusion.texture())));
+            converted_pbr.occlusion_strength = occlusion.strength();
+        }
+        converted.pbr = Some(converted_pbr);
+        scene
+            .materials
+            .insert(material_name(name, material.index()), converted);
+    }
+    scene
+        .materials
+        .insert(material_name(name, None), Material::new());
+
+    let mut loader = MeshLoader {
+        name,
+        buffers: &buffers,
+        n_nodes: gltf.nodes().len(),
+        meshes: vec![],
+    };
+    match gltf.default_scene().or_else(|| gltf.scenes().next()) {
+        Some(gltf_scene) => {
+            for node in gltf_scene.nodes() {
+                loader.add_node::<P>(&node, &IDENTITY, 0)?;
+            }
+        }
+        None => {
+            // Without a scene, show every node that isn't a child of another.
+            let mut is_child = vec![false; gltf.nodes().len()];
+            for node in gltf.nodes() {
+                for child in node.children() {
+                    is_child[child.index()] = true;
+                }
+            }
+            for node in gltf.nodes() {
+                if !is_child[node.index()] {
+                    loader.add_node::<P>(&node, &IDENTITY, 0)?;
+                }
+            }
+        }
+    }
+    scene.meshes = loader.meshes;
+    Ok(scene)
+}
+
+//------------------------------------------------------------------------------
+
+struct MeshLoader<'a, EM> {
+    name: &'a str,
+    buffers: &'a [Vec<u8>],
+    n_nodes: usize,
+    meshes: Vec<SceneMesh<EM>>,
+}
+
+impl<'a, EM> MeshLoader<'a, EM> {
+    fn add_node<P>(&mut self, node: &Node, parent: &[[f32; 4]; 4], depth: usize) -> IoResult<()>
+    where
+        EM: IsFaceEditableMesh<P, Face3> + IsVertexEditableMesh<P, Face3> + Default,
+        P: IsBuildable3D + Clone,
+    {
+        // Deeper than there are nodes, some node is its own ancestor.
+        if depth > self.n_nodes {
+            return Err(
+                ErrorKind::Parse(format!("Node {} is its own ancestor", node.index())).into(),
+            );
+        }
+        let transform = multiply(parent, &node.transform().matrix());
+        if let Some(gltf_mesh) = node.mesh() {
+            let label = node
+                .name()
+                .or_else(|| gltf_mesh.name())
+                .map(|label| label.to_string())
+                .unwrap_or_else(|| format!("node {}", node.index()));
+            let mut mesh_name = format!("{}/{}", self.name, label);
+            if self.meshes.iter().any(|m| m.name == mesh_name) {
+                mesh_name = format!("{} ({})", mesh_name, node.index());
+            }
+            let mut mesh = EM::default();
+            let mut material_info = MaterialInfo::new();
+            self.add_primitives(&gltf_mesh, &mut mesh, &mut material_info)?;
+            self.meshes.push(SceneMesh {
+                name: mesh_name,
+                mesh,
+                material_info,
+                transform,
+            });
+        }
+        for child in node.children() {
+            self.add_node::<P>(&child, &transform, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Adds all primitives of a mesh to a single mesh. Attributes that
+    /// only some of the primitives have are filled with defaults for
+    /// the others, or dropped if they can't be defaulted.
+    fn add_primitives<P>(
+        &self,
+        gltf_mesh: &::gltf::Mesh,
+        mesh: &mut EM,
+        material_info: &mut MaterialInfo,
+    ) -> IoResult<()>
+    where
+        EM: IsFaceEditableMesh<P, Face3> + IsVertexEditableMesh<P, Face3>,
+        P: IsBuildable3D + Clone,
+    {
+        let buffers = self.buffers;
+        let mut all_normals = true;
+        let mut any_uvs = false;
+        let mut any_colors = false;
+        let mut skipped = 0;
+        for primitive in gltf_mesh.primitives() {
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|d| &d[..]));
+            let positions = match reader.read_positions() {
+                Some(positions) => positions,
+                None => continue,
+            };
+            let base = mesh.num_vertices();
+            for p in positions {
+                mesh.add_vertex(P::new(p[0] as f64, p[1] as f64, p[2] as f64));
+            }
+            let n_vertices = mesh.num_vertices() - base;
+
+            match reader.read_normals() {
+                Some(normals) => material_info
+                    .normals
+                    .extend(normals.map(|n| three_d::vec3(n[0], n[1], n[2]))),
+                None => all_normals = false,
+            }
+            match reader.read_tex_coords(0) {
+                Some(tex_coords) => {
+                    any_uvs = true;
+                    // glTF has its origin at the top left of the image.
+                    for uv in tex_coords.into_f32() {
+                        material_info.uv.push_d(Point3D::new(
+                            uv[0] as f64,
+                            1.0 - uv[1] as f64,
+                            0.0,
+                        ));
+                    }
+                }
+                None => {
+                    for _ in 0..n_vertices {
+                        material_info.uv.push_d(Point3D::new(0.0, 0.0, 0.0));
+                    }
+                }
+            }
+            match reader.read_colors(0) {
+                Some(colors) => {
+                    any_colors = true;
+                    material_info.colors.extend(
+                        colors
+                            .into_rgb_f32()
+                            .map(|c| three_d::vec3(c[0], c[1], c[2])),
+                    );
+                }
+                None => {
+                    material_info
+                        .colors
+                        .extend((0..n_vertices).map(|_| three_d::vec3(1.0, 1.0, 1.0)));
+                }
+            }
+            if material_info.normals.len() != mesh.num_vertices()
+                || material_info.uv.len() != mesh.num_vertices()
+                || material_info.colors.len() != mesh.num_vertices()
+            {
+                return Err(ErrorKind::InvalidPrimitive(primitive.index()).into());
+            }
+
+            let indices: Vec<usize> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+                None => (0..n_vertices).collect(),
+            };
+            let triangles = match primitive.mode() {
+                Mode::Triangles => indices
+                    .chunks_exact(3)
+                    .map(|t| [t[0], t[1], t[2]])
+                    .collect(),
+                Mode::TriangleStrip => (2..indices.len())
+                    .map(|i| {
+                        if i % 2 == 0 {
+                            [indices[i - 2], indices[i - 1], indices[i]]
+                        } else {
+                            [indices[i - 1], indices[i - 2], indices[i]]
+                        }
+                    })
+                    .collect(),
+                Mode::TriangleFan => (2..indices.len())
+                    .map(|i| [indices[0], indices[i - 1], indices[i]])
+                    .collect(),
+                mode => {
+                    material_info
+                        .warnings
+                        .push(format!("Skipped a primitive with mode {:?}", mode));
+                    vec![]
+                }
+            };
+
+            let surface_name = material_name(self.name, primitive.material().index());
+            let surface = material_info
+                .surfaces
+                .entry(surface_name)
+                .or_insert_with(MaterialSurface::new);
+            for [a, b, c] in triangles {
+                let face = Face3 {
+                    a: VId { val: base + a },
+                    b: VId { val: base + b },
+                    c: VId { val: base + c },
+                };
+                match mesh.try_add_connection(face.a, face.b, face.c) {
+                    Ok(_) => {
+                        surface.faces.insert(face);
+                    }
+                    Err(_) => {
+                        info!(
+                            "Warning, face {},{},{} could not be added.",
+                            face.a.val, face.b.val, face.c.val
+                        );
+                        skipped += 1;
+                    }
+                }
+            }
+        }
+        if skipped > 0 {
+            material_info
+                .warnings
+                .push(format!("{} faces could not be added", skipped));
+        }
+        if !all_normals {
+            material_info.normals.clear();
+        }
+        if !any_uvs {
+            material_info.uv = PointCloud3D::new();
+        }
+        if !any_colors {
+            material_info.colors.clear();
+        }
+        Ok(())
+    }
+}
+
+//------------------------------------------------------------------------------
+
+/// Name of a material within the materials of all loaded files.
+fn material_name(name: &str, index: Option<usize>) -> String {
+    match index {
+        Some(index) => format!("{}#material{}", name, index),
+        None => format!("{}#default", name),
+    }
+}
+
+fn file_name(uri: &str) -> String {
+    Path::new(uri)
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_else(|| uri.to_string())
+}
+
+fn resolve_uri(uri: &str, resources: &HashMap<String, Vec<u8>>) -> IoResult<Vec<u8>> {
+    if uri.starts_with("data:") {
+        let (header, data) = uri
+            .find(',')
+            .map(|i| (&uri[..i], &uri[i + 1..]))
+            .ok_or_else(|| ErrorKind::InvalidUri(uri.to_string()))?;
+        if !header.ends_with(";base64") {
+            return Err(ErrorKind::InvalidUri(header.to_string()).into());
+        }
+        return base64::decode(data).map_err(|_| ErrorKind::InvalidUri(header.to_string()).into());
+    }
+    let name = file_name(uri);
+    resources
+        .get(&name)
+        .cloned()
+        .ok_or_else(|| ErrorKind::MissingResource(name).into())
+}
+
+/// Checks what validating the document would panic on: primitives whose
+/// positions refer to missing accessors, and images without a source.
+fn check_json(json: &json::Root) -> IoResult<()> {
+    for mesh in json.meshes.iter() {
+        for (i, primitive) in mesh.primitives.iter().enumerate() {
+            if primitive
+                .attributes
+                .values()
+                .any(|accessor| accessor.value() >= json.accessors.len())
+            {
+                return Err(ErrorKind::InvalidPrimitive(i).into());
+            }
+        }
+    }
+    for image in json.images.iter() {
+        let has_source = match image.buffer_view {
+            Some(_) => image.mime_type.is_some(),
+            None => image.uri.is_some(),
+        };
+        if !has_source {
+            return Err(ErrorKind::Parse("Image without a source".to_string()).into());
+        }
+    }
+    Ok(())
+}
+
+/// Checks that all accessors and buffer views fit into their buffers,
+/// as reading them would panic otherwise.
+fn check_accessors(gltf: &Gltf, buffers: &[Vec<u8>]) -> IoResult<()> {
+    let fits = |view: &buffer::View, offset: usize, count: usize, size: usize| {
+        let buffer_length = buffers[view.buffer().index()].len();
+        let stride = view.stride().unwrap_or(size);
+        let end = count
+            .checked_sub(1)
+            .and_then(|n| n.checked_mul(stride))
+            .and_then(|n| n.checked_add(offset))
+            .and_then(|n| n.checked_add(size));
+        let view_end = view.offset().checked_add(view.length());
+        stride >= size
+            && matches!(view_end, Some(view_end) if view_end <= buffer_length)
+            && matches!(end, Some(end) if end <= view.length())
+    };
+    for accessor in gltf.accessors() {
+        let valid = accessor.count() > 0
+            && match accessor.view() {
+                Some(view) => fits(&view, accessor.offset(), accessor.count(), accessor.size()),
+                None => true,
+            }
+            && match accessor.sparse() {
+                Some(sparse) => {
+                    let indices = sparse.indices();
+                    let values = sparse.values();
+                    fits(
+                        &indices.view(),
+                        indices.offset() as usize,
+                        sparse.count() as usize,
+                        indices.index_type().size(),
+                    ) && fits(
+                        &values.view(),
+                        values.offset() as usize,
+                        sparse.count() as usize,
+                        accessor.size(),
+                    )
+                }
+                None => true,
+            };
+        if !valid {
+            return Err(invalid_accessor(&accessor));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the attributes and indices that are read have types the
+/// gltf crate can read them as, as it panics otherwise.
+fn check_primitives(gltf: &Gltf) -> IoResult<()> {
+    use ::gltf::{
+        accessor::{DataType, Dimensions},
+        Semantic,
+    };
+    for mesh in gltf.meshes() {
+        for primitive in mesh.primitives() {
+            for (semantic, accessor) in primitive.attributes() {
+                let valid = match semantic {
+                    Semantic::Positions | Semantic::Normals => matches!(
+                        (accessor.data_type(), accessor.dimensions()),
+                        (DataType::F32, Dimensions::Vec3)
+                    ),
+                    Semantic::TexCoords(_) => matches!(
+                        (accessor.data_type(), accessor.dimensions()),
+                        (DataType::U8, Dimensions::Vec2)
+                            | (DataType::U16, Dimensions::Vec2)
+                            | (DataType::F32, Dimensions::Vec2)
+                    ),
+                    Semantic::Colors(_) => matches!(
+                        (accessor.data_type(), accessor.dimensions()),
+                        (DataType::U8, Dimensions::Vec3)
+                            | (DataType::U16, Dimensions::Vec3)
+                            | (DataType::F32, Dimensions::Vec3)
+                            | (DataType::U8, Dimensions::Vec4)
+                            | (DataType::U16, Dimensions::Vec4)
+                            | (DataType::F32, Dimensions::Vec4)
+                    ),
+                    _ => true,
+                };
+                if !valid {
+                    return Err(invalid_accessor(&accessor));
+                }
+            }
+            if let Some(accessor) = primitive.indices() {
+                let valid = matches!(
+                    (accessor.data_type(), accessor.dimensions()),
+                    (DataType::U8, Dimensions::Scalar)
+                        | (DataType::U16, Dimensions::Scalar)
+                        | (DataType::U32, Dimensions::Scalar)
+                );
+                if !valid {
+                    return Err(invalid_accessor(&accessor));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn invalid_accessor(accessor: &Accessor) -> Error {
+    Error::new(ErrorKind::Parse(format!(
+        "Accessor {} is invalid or does not fit into its buffer",
+        accessor.index()
+    )))
+}
+
+/// Product of two column-major matrices.
+fn multiply(a: &[[f32; 4]; 4], b: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut result = [[0.0; 4]; 4];
+    for (column, b_column) in result.iter_mut().zip(b.iter()) {
+        for (row, value) in column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b_column[k]).sum();
+        }
+    }
+    result
+}
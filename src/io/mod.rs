@@ -1,9 +1,7 @@
 use rust_3d::{Face3, Point3D, PointCloud3D};
 use std::collections::{HashMap, HashSet};
 
-/// We consider the specular and ambient
-/// colors to be properties of the scene
-/// lights, not the individual meshes.
+/// Appearance of a surface, with the properties of the MTL format.
 #[derive(Clone, Debug)]
 pub struct Material {
     /// Diffuse color
@@ -12,11 +10,55 @@ pub struct Material {
     /// Diffuse intensity
     pub diffuse_intensity: f32,
 
-    /// Specular intensity
-    pub specular_intensity: f32,
+    /// Ambient color. It scales the ambient light reflected by the
+    /// diffuse color, so white leaves the diffuse color as it is.
+    pub ambient_color: three_d::Vec3,
+
+    /// Specular color
+    pub specular_color: three_d::Vec3,
+
+    /// Specular exponent. Higher values give smaller highlights.
+    pub specular_power: f32,
+
+    /// Color the surface emits, regardless of the lights.
+    pub emissive_color: three_d::Vec3,
+
+    /// Opacity, from 0 for invisible to 1 for opaque.
+    pub opacity: f32,
+
+    /// Illumination model of MTL files. 0 is unlit, 1 has no specular
+    /// highlights, and 2 and above are fully lit.
+    pub illumination: u32,
 
     /// Name of texture, if there is one.
     pub texture_name: Option<String>,
+
+    /// Options of the texture.
+    pub texture_options: TextureOptions,
+
+    /// Does the alpha channel of the texture scale the opacity?
+    pub texture_alpha: bool,
+
+    /// Texture scaling the specular color.
+    pub specular_map: Option<TextureMap>,
+
+    /// Texture scaling the opacity.
+    pub opacity_map: Option<TextureMap>,
+
+    /// Height texture to perturb the normals with.
+    pub bump_map: Option<TextureMap>,
+
+    /// Texture with normals in tangent space.
+    pub normal_map: Option<TextureMap>,
+
+    /// Texture scaling the emissive color.
+    pub emissive_map: Option<TextureMap>,
+
+    /// Metallic-roughness properties, for materials that have them. The
+    /// base color is the diffuse color and texture, and the emissive
+    /// color and map are shared. Such materials ignore the ambient and
+    /// specular properties.
+    pub pbr: Option<PbrMaterial>,
 }
 
 impl Material {
@@ -24,8 +66,139 @@ impl Material {
         Material {
             diffuse_color: three_d::Vec3::new(0.8, 0.8, 0.8),
             diffuse_intensity: 0.5,
-            specular_intensity: 0.5,
+            ambient_color: three_d::Vec3::new(1.0, 1.0, 1.0),
+            specular_color: three_d::Vec3::new(0.5, 0.5, 0.5),
+            specular_power: 5.0,
+            emissive_color: three_d::Vec3::new(0.0, 0.0, 0.0),
+            opacity: 1.0,
+            illumination: 2,
             texture_name: None,
+            texture_options: TextureOptions::new(),
+            texture_alpha: true,
+            specular_map: None,
+            opacity_map: None,
+            bump_map: None,
+            normal_map: None,
+            emissive_map: None,
+            pbr: None,
+        }
+    }
+
+    /// Names of all textures the material uses.
+    pub fn texture_names(&self) -> Vec<&str> {
+        let maps = [
+            &self.specular_map,
+            &self.opacity_map,
+            &self.bump_map,
+            &self.normal_map,
+            &self.emissive_map,
+        ];
+        let pbr_maps = self
+            .pbr
+            .iter()
+            .flat_map(|pbr| vec![&pbr.metallic_map, &pbr.roughness_map, &pbr.occlusion_map]);
+        self.texture_name
+            .iter()
+            .map(|name| &name[..])
+            .chain(
+                maps.iter()
+                    .cloned()
+                    .chain(pbr_maps)
+                    .filter_map(|map| map.as_ref())
+                    .map(|map| &map.name[..]),
+            )
+            .collect()
+    }
+}
+
+/// Physically based properties of a material, as in glTF.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PbrMaterial {
+    /// How metallic the surface is, from 0 for a dielectric to 1 for a
+    /// metal.
+    pub metallic: f32,
+
+    /// Roughness, from 0 for a mirror to 1 for a fully rough surface.
+    pub roughness: f32,
+
+    /// How much the occlusion map darkens the ambient light, from 0 to 1.
+    pub occlusion_strength: f32,
+
+    /// Texture scaling the metallic value.
+    pub metallic_map: Option<TextureMap>,
+
+    /// Texture scaling the roughness.
+    pub roughness_map: Option<TextureMap>,
+
+    /// Texture with the ambient light that reaches the surface.
+    pub occlusion_map: Option<TextureMap>,
+}
+
+impl PbrMaterial {
+    pub fn new() -> PbrMaterial {
+        PbrMaterial {
+            metallic: 0.0,
+            roughness: 1.0,
+            occlusion_strength: 1.0,
+            metallic_map: None,
+            roughness_map: None,
+            occlusion_map: None,
+        }
+    }
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How a texture is placed on a surface.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureOptions {
+    /// Added to the texture coordinates, after scaling.
+    pub offset: three_d::Vec3,
+
+    /// Multiplies the texture coordinates.
+    pub scale: three_d::Vec3,
+
+    /// Multiplies the heights of a bump map.
+    pub bump_multiplier: f32,
+
+    /// Channel that textures of single values are read from: 0, 1, 2
+    /// and 3 for red, green, blue and alpha.
+    pub channel: usize,
+}
+
+impl TextureOptions {
+    pub fn new() -> TextureOptions {
+        TextureOptions {
+            offset: three_d::Vec3::new(0.0, 0.0, 0.0),
+            scale: three_d::Vec3::new(1.0, 1.0, 1.0),
+            bump_multiplier: 1.0,
+            channel: 0,
+        }
+    }
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A texture of a material other than the diffuse one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureMap {
+    pub name: String,
+    pub options: TextureOptions,
+}
+
+impl TextureMap {
+    pub fn new(name: &str) -> TextureMap {
+        TextureMap {
+            name: name.to_string(),
+            options: TextureOptions::new(),
         }
     }
 }
@@ -51,26 +224,51 @@ impl MaterialSurface {
     }
 }
 
+/// A named part of a mesh, such as an object or group of an OBJ file.
+pub struct MeshPart {
+    pub name: String,
+    /// Indices of the faces in the part.
+    pub faces: Vec<usize>,
+}
+
 pub struct MaterialInfo {
     /// uvw
     pub uv: PointCloud3D<Point3D>,
+    /// Colors, if the file provides them. Either one per vertex, or
+    /// one per wedge (three per face, in face order).
+    pub colors: Vec<three_d::Vec3>,
+    /// Normals, if the file provides them. Either one per vertex, or
+    /// one per wedge (three per face, in face order).
+    pub normals: Vec<three_d::Vec3>,
     /// mtl:uv
     pub surfaces: HashMap<String, MaterialSurface>,
     /// material files containing information about
     /// surfaces on this mesh.
     pub material_libs: HashSet<String>,
+    /// Problems that didn't stop the file from loading, such as faces
+    /// that had to be left out.
+    pub warnings: Vec<String>,
+    /// Parts the faces belong to, if the file has any. Every face is in
+    /// exactly one part then.
+    pub parts: Vec<MeshPart>,
 }
 
 impl MaterialInfo {
     pub fn new() -> MaterialInfo {
         MaterialInfo {
             uv: PointCloud3D::<Point3D>::new(),
+            colors: vec![],
+            normals: vec![],
             surfaces: HashMap::new(),
             material_libs: HashSet::new(),
+            warnings: vec![],
+            parts: vec![],
         }
     }
 }
 
+mod error;
+pub use self::error::*;
 mod ply;
 pub use self::ply::*;
 
@@ -83,6 +281,22 @@ pub use self::mtl::*;
 mod off;
 pub use self::off::*;
 
+mod xyz;
+pub use self::xyz::*;
+
+mod stl;
+pub use self::stl::*;
+
+mod triangulate;
+pub use self::triangulate::*;
+
+mod scene;
+pub use self::scene::*;
+mod gltf;
+pub use self::gltf::*;
+mod format;
+pub use self::format::*;
+
 mod utils;
 
 mod byte_reader;
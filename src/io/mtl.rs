@@ -1,16 +1,16 @@
 //! Module for IO of the mtl file format
 
+use super::error::{Error, ErrorKind, IoResult};
 use super::utils::*;
-use super::Material;
+use super::{Material, PbrMaterial, TextureMap, TextureOptions};
 use rust_3d::*;
 use std::collections::HashMap;
-use std::fmt;
-use std::io::{BufRead, Error as ioError};
+use std::io::{BufRead, Write};
 
 //------------------------------------------------------------------------------
 
 /// Loads materials from a .mtl file
-pub fn load_mtl<R>(read: &mut R) -> MtlResult<HashMap<String, Material>>
+pub fn load_mtl<R>(read: &mut R) -> IoResult<HashMap<String, Material>>
 where
     R: BufRead,
 {
@@ -18,98 +18,270 @@ where
     let mut i_line = 0;
     let mut mtl_name: Option<String> = None;
     let mut result: HashMap<String, Material> = HashMap::new();
-    while let Ok(line) = fetch_line(read, &mut line_buffer) {
+    // "d" takes precedence over "Tr", wherever they are.
+    let mut has_dissolve = false;
+    while let Some(line) = fetch_line(read, &mut line_buffer)? {
         i_line += 1;
-        if line.starts_with(b"newmtl ") {
-            // skip "newmtl"
-            let mut words = to_words_skip_empty(line);
-            words.next().ok_or(MtlError::LineParse(i_line))?;
-            if let Some(next_word) = words.next().and_then(|w| from_ascii(w)) {
+        let mut words = to_words_skip_empty(line);
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        if keyword == b"newmtl" {
+            if let Some(next_word) = words.next().and_then(|w| from_ascii::<String>(w)) {
+                result.insert(next_word.clone(), Material::new());
                 mtl_name = Some(next_word);
-                result.insert(mtl_name.clone().unwrap(), Material::new());
+                has_dissolve = false;
             }
-        } else if line.starts_with(b"map_Kd ") {
-            let mut words = to_words_skip_empty(line);
-            // skip "map_Kd"
-            words.next().ok_or(MtlError::LineParse(i_line))?;
-            if let Some(next_word) = words.next().and_then(|w| from_ascii(w)) {
-                if let Some(mtl_name) = mtl_name.clone() {
-                    if let Some(mtl) = result.get_mut(&mtl_name) {
-                        mtl.texture_name = Some(next_word);
-                    } else {
-                        return Err(MtlError::NoMaterialError(i_line));
-                    }
-                }
+            continue;
+        }
+        // Statements before the first material have nothing to apply to.
+        let mtl = match &mtl_name {
+            Some(mtl_name) => result
+                .get_mut(mtl_name)
+                .ok_or(Error::on_line(ErrorKind::NoMaterial, i_line))?,
+            None => continue,
+        };
+        let words: Vec<&[u8]> = words.collect();
+        match keyword {
+            b"d" => has_dissolve = true,
+            b"Tr" if has_dissolve => continue,
+            _ => {}
+        }
+        parse_statement(mtl, keyword, &words[..])
+            .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
+    }
+    Ok(result)
+}
+
+/// Applies a statement to a material. None if the statement can't be
+/// parsed. Unknown statements are ignored.
+fn parse_statement(mtl: &mut Material, keyword: &[u8], words: &[&[u8]]) -> Option<()> {
+    match keyword {
+        b"Kd" | b"Ka" | b"Ks" | b"Ke" => {
+            // Spectral curves aren't supported, so those colors are left
+            // as they are.
+            if words.first() == Some(&&b"spectral"[..]) {
+                return Some(());
             }
-        } else if line.starts_with(b"Ns ") {
-            let mut words = to_words_skip_empty(line);
-            // skip "map_Kd"
-            words.next().ok_or(MtlError::LineParse(i_line))?;
-            if let Some(next_word) = words.next().and_then(|w| from_ascii(w)) {
-                if let Some(mtl_name) = mtl_name.clone() {
-                    if let Some(mtl) = result.get_mut(&mtl_name) {
-                        mtl.specular_intensity = next_word;
-                    } else {
-                        return Err(MtlError::NoMaterialError(i_line));
-                    }
-                }
+            let color = parse_color(words)?;
+            match keyword {
+                b"Kd" => mtl.diffuse_color = color,
+                b"Ka" => mtl.ambient_color = color,
+                b"Ks" => mtl.specular_color = color,
+                _ => mtl.emissive_color = color,
             }
-        } else if line.starts_with(b"Kd ") {
-            let mut words = to_words_skip_empty(line);
-            // skip "Kd"
-            words.next().ok_or(MtlError::LineParse(i_line))?;
-            if let Some(mtl_name) = mtl_name.clone() {
-                if let Some(mtl) = result.get_mut(&mtl_name) {
-                    let mut first_word = words.next().ok_or(MtlError::LineParse(i_line))?;
-                    // Skip "=" in some OBJ files
-                    if first_word == b"=" {
-                        first_word = words.next().ok_or(MtlError::LineParse(i_line))?;
-                    }
-                    let x = from_ascii(first_word).ok_or(MtlError::LineParse(i_line))?;
+        }
+        b"Ns" => mtl.specular_power = from_ascii(words.first()?)?,
+        b"d" => {
+            // "-halo" makes the edges more opaque than the middle, which
+            // isn't supported.
+            let value = words.iter().find(|w| **w != b"-halo")?;
+            mtl.opacity = from_ascii(value)?;
+        }
+        b"Tr" => {
+            // Some exporters write the opacity rather than the
+            // transparency, so fully transparent is taken to mean that.
+            let transparency: f32 = from_ascii(words.first()?)?;
+            mtl.opacity = if transparency >= 1.0 {
+                1.0
+            } else {
+                1.0 - transparency
+            };
+        }
+        b"illum" => mtl.illumination = from_ascii(words.first()?)?,
+        b"map_Kd" => {
+            let map = parse_texture_map(words)?;
+            mtl.texture_name = Some(map.name);
+            mtl.texture_options = map.options;
+        }
+        b"map_Ks" => mtl.specular_map = Some(parse_texture_map(words)?),
+        b"map_d" => mtl.opacity_map = Some(parse_texture_map(words)?),
+        b"map_Bump" | b"map_bump" | b"bump" => mtl.bump_map = Some(parse_texture_map(words)?),
+        b"norm" => mtl.normal_map = Some(parse_texture_map(words)?),
+        b"map_Ke" => mtl.emissive_map = Some(parse_texture_map(words)?),
+        // Extensions for physically based materials.
+        b"Pr" => pbr(mtl).roughness = from_ascii(words.first()?)?,
+        b"Pm" => pbr(mtl).metallic = from_ascii(words.first()?)?,
+        b"map_Pr" => pbr(mtl).roughness_map = Some(parse_texture_map(words)?),
+        b"map_Pm" => pbr(mtl).metallic_map = Some(parse_texture_map(words)?),
+        _ => {}
+    }
+    Some(())
+}
 
-                    let y = words
-                        .next()
-                        .and_then(|w| from_ascii(w))
-                        .ok_or(MtlError::LineParse(i_line))?;
+/// Physically based properties of a material, which are added by the
+/// first statement that sets one.
+fn pbr(mtl: &mut Material) -> &mut PbrMaterial {
+    mtl.pbr.get_or_insert_with(PbrMaterial::new)
+}
 
-                    let z = words
-                        .next()
-                        .and_then(|w| from_ascii(w))
-                        .ok_or(MtlError::LineParse(i_line))?;
-                    mtl.diffuse_color = three_d::Vec3::new(x, y, z);
-                } else {
-                    return Err(MtlError::NoMaterialError(i_line));
+/// Parses "r g b", or "r" for grey. Colors given as "xyz" are taken to
+/// be rgb.
+fn parse_color(words: &[&[u8]]) -> Option<three_d::Vec3> {
+    let values = words
+        .iter()
+        // Skip "=" in some OBJ files
+        .skip_while(|w| **w == b"=" || **w == b"xyz")
+        .map(|w| from_ascii(w))
+        .collect::<Option<Vec<f32>>>()?;
+    match values[..] {
+        [grey] => Some(three_d::Vec3::new(grey, grey, grey)),
+        [r, g, b] => Some(three_d::Vec3::new(r, g, b)),
+        _ => None,
+    }
+}
+
+/// Parses the options and the file name of a texture statement. Options
+/// other than the offset, scale, bump multiplier and channel are skipped.
+fn parse_texture_map(words: &[&[u8]]) -> Option<TextureMap> {
+    let mut options = TextureOptions::new();
+    let mut i = 0;
+    while let Some(option) = words.get(i).filter(|w| w.starts_with(b"-")) {
+        i += 1;
+        match *option {
+            b"-o" | b"-s" | b"-t" => {
+                // Up to three numbers, for u, v and w.
+                let mut values = vec![];
+                while let Some(value) = words.get(i).filter(|_| values.len() < 3) {
+                    match from_ascii::<f32>(value) {
+                        Some(value) => values.push(value),
+                        None => break,
+                    }
+                    i += 1;
+                }
+                let (first, default) = match values.first() {
+                    Some(first) => (*first, if *option == b"-s" { 1.0 } else { 0.0 }),
+                    None => return None,
+                };
+                let vector = three_d::Vec3::new(
+                    first,
+                    values.get(1).cloned().unwrap_or(default),
+                    values.get(2).cloned().unwrap_or(default),
+                );
+                match *option {
+                    b"-o" => options.offset = vector,
+                    b"-s" => options.scale = vector,
+                    _ => {}
                 }
             }
+            b"-bm" => {
+                options.bump_multiplier = from_ascii(words.get(i)?)?;
+                i += 1;
+            }
+            b"-imfchan" => {
+                // Luminance and depth are read from the red channel.
+                options.channel = match *words.get(i)? {
+                    b"r" | b"l" | b"z" => 0,
+                    b"g" => 1,
+                    b"b" => 2,
+                    b"m" => 3,
+                    _ => return None,
+                };
+                i += 1;
+            }
+            b"-mm" => i += 2,
+            b"-blendu" | b"-blendv" | b"-boost" | b"-texres" | b"-clamp" | b"-type" | b"-cc" => {
+                i += 1
+            }
+            _ => return None,
         }
-
-        i_line += 1;
     }
-    Ok(result)
+    // File names may contain spaces.
+    let names = words
+        .get(i..)
+        .filter(|names| !names.is_empty())?
+        .iter()
+        .map(|w| from_ascii::<String>(w))
+        .collect::<Option<Vec<String>>>()?;
+    Some(TextureMap {
+        name: names.join(" "),
+        options,
+    })
 }
 
-/// Error type for .mtl file operations
-pub enum MtlError {
-    AccessFile,
-    LineParse(usize),
-    NoMaterialError(usize),
-}
-
-impl fmt::Debug for MtlError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::AccessFile => write!(f, "Unable to access file"),
-            Self::LineParse(x) => write!(f, "Unable to parse line {}", x),
-            Self::NoMaterialError(x) => write!(f, "Line {} occurs before newmtl.", x),
+/// Saves materials in the .mtl file format
+pub fn save_mtl<W>(write: &mut W, materials: &[(String, Material)]) -> IoResult<()>
+where
+    W: Write,
+{
+    writeln!(write, "# Created by web-geo-viewer")?;
+    for (name, material) in materials.iter() {
+        writeln!(write, "newmtl {}", name)?;
+        write_color(write, "Ka", &material.ambient_color)?;
+        write_color(write, "Kd", &material.diffuse_color)?;
+        write_color(write, "Ks", &material.specular_color)?;
+        write_color(write, "Ke", &material.emissive_color)?;
+        writeln!(write, "Ns {}", material.specular_power)?;
+        writeln!(write, "d {}", material.opacity)?;
+        writeln!(write, "illum {}", material.illumination)?;
+        if let Some(texture_name) = &material.texture_name {
+            let map = TextureMap {
+                name: texture_name.clone(),
+                options: material.texture_options.clone(),
+            };
+            write_texture_map(write, "map_Kd", &map)?;
+        }
+        let maps = [
+            ("map_Ks", &material.specular_map),
+            ("map_d", &material.opacity_map),
+            ("map_Bump", &material.bump_map),
+            ("norm", &material.normal_map),
+            ("map_Ke", &material.emissive_map),
+        ];
+        for (keyword, map) in maps.iter() {
+            if let Some(map) = map {
+                write_texture_map(write, keyword, map)?;
+            }
+        }
+        if let Some(pbr) = &material.pbr {
+            writeln!(write, "Pr {}", pbr.roughness)?;
+            writeln!(write, "Pm {}", pbr.metallic)?;
+            let maps = [
+                ("map_Pr", &pbr.roughness_map),
+                ("map_Pm", &pbr.metallic_map),
+            ];
+            for (keyword, map) in maps.iter() {
+                if let Some(map) = map {
+                    write_texture_map(write, keyword, map)?;
+                }
+            }
         }
     }
+    Ok(())
 }
 
-/// Result type for .obj file operations
-pub type MtlResult<T> = std::result::Result<T, MtlError>;
+fn write_color<W>(write: &mut W, keyword: &str, color: &three_d::Vec3) -> IoResult<()>
+where
+    W: Write,
+{
+    writeln!(write, "{} {} {} {}", keyword, color.x, color.y, color.z)?;
+    Ok(())
+}
 
-impl From<ioError> for MtlError {
-    fn from(_error: ioError) -> Self {
-        MtlError::AccessFile
+/// Writes a texture statement, with the options that aren't the
+/// defaults.
+fn write_texture_map<W>(write: &mut W, keyword: &str, map: &TextureMap) -> IoResult<()>
+where
+    W: Write,
+{
+    let defaults = TextureOptions::new();
+    write!(write, "{}", keyword)?;
+    let options = &map.options;
+    if options.offset != defaults.offset {
+        let o = options.offset;
+        write!(write, " -o {} {} {}", o.x, o.y, o.z)?;
+    }
+    if options.scale != defaults.scale {
+        let s = options.scale;
+        write!(write, " -s {} {} {}", s.x, s.y, s.z)?;
+    }
+    if options.bump_multiplier != defaults.bump_multiplier {
+        write!(write, " -bm {}", options.bump_multiplier)?;
+    }
+    if options.channel != defaults.channel {
+        write!(write, " -imfchan {}", ["r", "g", "b", "m"][options.channel])?;
     }
+    writeln!(write, " {}", map.name)?;
+    Ok(())
 }
@@ -24,14 +24,16 @@ OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
 use log::*;
 use rust_3d::*;
+use three_d::core::types::InnerSpace;
 
-use std::{
-    fmt,
-    io::{BufRead, Error as ioError},
-};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
 
-use super::utils::*;
-use super::{MaterialInfo, MaterialSurface};
+use super::{
+    error::{Error, ErrorKind, IoResult},
+    utils::*,
+};
+use super::{triangulate::*, MaterialInfo, MaterialSurface, MeshPart};
 
 //------------------------------------------------------------------------------
 
@@ -43,7 +45,7 @@ pub fn load_obj_mesh<EM, P, R>(
     read: &mut R,
     mesh: &mut EM,
     material_info: &mut MaterialInfo,
-) -> ObjResult<()>
+) -> IoResult<()>
 where
     EM: IsFaceEditableMesh<P, Face3> + IsVertexEditableMesh<P, Face3>,
     P: IsBuildable3D + Clone,
@@ -55,34 +57,50 @@ where
     material_info
         .surfaces
         .insert(mtl_name.clone(), MaterialSurface::new());
+    let mut uvs = PointCloud3D::<Point3D>::new();
+    let mut normals: Vec<three_d::Vec3> = vec![];
+    let mut polygons: Vec<(Vec<Corner>, String, Option<String>)> = vec![];
+    let mut object: Option<String> = None;
+    let mut group: Option<String> = None;
 
-    while let Ok(line) = fetch_line(read, &mut line_buffer) {
+    while let Some(line) = fetch_line(read, &mut line_buffer)? {
         i_line += 1;
         if line.starts_with(b"usemtl ") {
             let mut words = to_words_skip_empty(line);
 
             // skip "usemtl"
-            words.next().ok_or(ObjError::LineParse(i_line))?;
+            words
+                .next()
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
             mtl_name = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(ObjError::LineParse(i_line))?;
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
             material_info
                 .surfaces
                 .insert(mtl_name.clone(), MaterialSurface::new());
         }
 
+        if line.starts_with(b"o ") {
+            object = part_statement_name(line);
+            group = None;
+        } else if line.starts_with(b"g ") || line == b"g" {
+            group = part_statement_name(line);
+        }
+
         if line.starts_with(b"mtllib ") {
             let mut words = to_words_skip_empty(line);
 
             // skip "v"
-            words.next().ok_or(ObjError::LineParse(i_line))?;
+            words
+                .next()
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
             let lib_name: String = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(ObjError::LineParse(i_line))?;
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
             material_info.material_libs.insert(lib_name.clone());
         }
 
@@ -90,179 +108,211 @@ where
             let mut words = to_words_skip_empty(line);
 
             // skip "v"
-            words.next().ok_or(ObjError::LineParse(i_line))?;
+            words
+                .next()
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
             let x = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(ObjError::LineParse(i_line))?;
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
             let y = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(ObjError::LineParse(i_line))?;
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
             let z = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(ObjError::LineParse(i_line))?;
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
             mesh.add_vertex(P::new(x, y, z));
         } else if line.starts_with(b"vt ") {
             let mut words = to_words_skip_empty(line);
 
             // skip "vt"
-            words.next().ok_or(ObjError::LineParse(i_line))?;
+            words
+                .next()
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
             let x = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(ObjError::LineParse(i_line))?;
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
-            let y = words
-                .next()
-                .and_then(|w| from_ascii(w))
-                .or(Some(0f64))
-                .unwrap();
-            let z = words
-                .next()
-                .and_then(|w| from_ascii(w))
-                .or(Some(0f64))
-                .unwrap();
+            let y = words.next().and_then(from_ascii).unwrap_or(0f64);
+            let z = words.next().and_then(from_ascii).unwrap_or(0f64);
 
-            material_info.uv.push_d(Point3D::new(x, y, z));
-        } else if line.starts_with(b"f ") {
+            uvs.push_d(Point3D::new(x, y, z));
+        } else if line.starts_with(b"vn ") {
             let mut words = to_words_skip_empty(line);
 
-            // skip "f"
-            words.next().ok_or(ObjError::LineParse(i_line))?;
-
-            let mut tmp = words.next().ok_or(ObjError::LineParse(i_line))?;
-            let tmp_str = until_bytes(tmp, b'/');
-            let a: usize = from_ascii(tmp_str).ok_or(ObjError::LineParse(i_line))?;
-            let mut maybe_at: Option<usize> = None;
-            if tmp_str.len() + 1 < tmp.len() {
-                maybe_at = from_ascii(until_bytes(&tmp[(tmp_str.len() + 1)..], b'/')).or(None);
-            }
+            // skip "vn"
+            words
+                .next()
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
-            tmp = words.next().ok_or(ObjError::LineParse(i_line))?;
-            let tmp_str = until_bytes(tmp, b'/');
-            let b: usize = from_ascii(tmp_str).ok_or(ObjError::LineParse(i_line))?;
-            let mut maybe_bt: Option<usize> = None;
-            if tmp_str.len() + 1 < tmp.len() {
-                maybe_bt = from_ascii(until_bytes(&tmp[(tmp_str.len() + 1)..], b'/')).or(None);
-            }
+            let x: f32 = words
+                .next()
+                .and_then(from_ascii)
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
-            tmp = words.next().ok_or(ObjError::LineParse(i_line))?;
-            let tmp_str = until_bytes(tmp, b'/');
-            let c: usize = from_ascii(tmp_str).ok_or(ObjError::LineParse(i_line))?;
-            let mut maybe_ct: Option<usize> = None;
-            if tmp_str.len() + 1 < tmp.len() {
-                maybe_ct = from_ascii(until_bytes(&tmp[(tmp_str.len() + 1)..], b'/')).or(None);
-            }
+            let y: f32 = words
+                .next()
+                .and_then(from_ascii)
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
-            let mut maybe_d: Option<usize> = None;
-            let mut maybe_dt: Option<usize> = None;
-            match words.next() {
-                Some(tmp) => {
-                    let tmp_str = until_bytes(tmp, b'/');
-                    if tmp_str.len() > 0 {
-                        maybe_d = from_ascii(until_bytes(&tmp_str, b'/')).or(None);
-                    }
-                    if tmp_str.len() + 1 < tmp.len() {
-                        maybe_dt =
-                            from_ascii(until_bytes(&tmp[(tmp_str.len() + 1)..], b'/')).or(None);
-                    }
-                    // The second face of a quad.
-                    if let Some(d) = maybe_d {
-                        let face = Face3 {
-                            a: VId { val: a - 1 },
-                            b: VId { val: c - 1 },
-                            c: VId { val: d - 1 },
-                        };
-                        material_info
-                            .surfaces
-                            .get_mut(&mtl_name)
-                            .unwrap()
-                            .faces
-                            .insert(face);
-                    }
-                    if let (Some(at), Some(ct), Some(d), Some(dt)) =
-                        (maybe_at, maybe_ct, maybe_d, maybe_dt)
-                    {
-                        material_info
-                            .surfaces
-                            .get_mut(&mtl_name)
-                            .unwrap()
-                            .uvs
-                            .insert(
-                                Face3 {
-                                    a: VId { val: a - 1 },
-                                    b: VId { val: c - 1 },
-                                    c: VId { val: d - 1 },
-                                },
-                                Face3 {
-                                    a: VId { val: at - 1 },
-                                    b: VId { val: ct - 1 },
-                                    c: VId { val: dt - 1 },
-                                },
-                            );
-                    }
-                }
-                None => {}
-            };
+            let z: f32 = words
+                .next()
+                .and_then(from_ascii)
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
-            // This triangle should be created for either a triangle or quad face.
-            material_info
-                .surfaces
-                .get_mut(&mtl_name)
-                .unwrap()
-                .faces
-                .insert(Face3 {
-                    a: VId { val: a - 1 },
-                    b: VId { val: b - 1 },
-                    c: VId { val: c - 1 },
-                });
-            if let (Some(at), Some(bt), Some(ct)) = (maybe_at, maybe_bt, maybe_ct) {
-                material_info
-                    .surfaces
-                    .get_mut(&mtl_name)
-                    .unwrap()
-                    .uvs
-                    .insert(
-                        Face3 {
-                            a: VId { val: a - 1 },
-                            b: VId { val: b - 1 },
-                            c: VId { val: c - 1 },
-                        },
-                        Face3 {
-                            a: VId { val: at - 1 },
-                            b: VId { val: bt - 1 },
-                            c: VId { val: ct - 1 },
-                        },
-                    );
+            normals.push(three_d::Vec3::new(x, y, z));
+        } else if line.starts_with(b"f ") {
+            let mut words = to_words_skip_empty(line);
+
+            // skip "f"
+            words
+                .next()
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
+
+            // Negative indices refer back from the elements read so far.
+            let counts = [mesh.num_vertices(), uvs.len(), normals.len()];
+            let mut corners = vec![];
+            for word in words {
+                corners.push(
+                    parse_corner(word, counts)
+                        .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?,
+                );
             }
-            // obj indexing starts at 1
-            if let Some(_next) = words.next() {
-                return Err(ObjError::NotTriangularMesh(i_line));
+            if corners.len() < 3 {
+                return Err(Error::on_line(ErrorKind::LineParse, i_line));
             }
+            polygons.push((corners, mtl_name.clone(), part_name(&object, &group)));
         }
     }
+
     // Add all faces after vertices have already been added. This is
     // in case there are multiple groups of vertices and faces; in that
     // case it would be possible for the face to come before the vertex
-    // it references in the file.
-    for surface in &material_info.surfaces {
-        for face in &surface.1.faces {
-            match mesh.try_add_connection(face.a, face.b, face.c).or(Err(
-                ObjError::InvalidMeshIndices(face.a.val, face.b.val, face.c.val),
-            )) {
-                Ok(_) => {}
+    // it references in the file. Faces are added in file order so that
+    // per-wedge attributes can be matched up with them.
+    let has_parts = polygons.iter().any(|(_, _, part)| part.is_some());
+    let mut part_indices: HashMap<String, usize> = HashMap::new();
+    let mut added = vec![];
+    let mut skipped = 0;
+    for (corners, surface_name, part) in polygons {
+        let vids: Vec<usize> = corners.iter().map(|corner| corner.v).collect();
+        let triangles = match triangulate_mesh_polygon(mesh, &vids[..]) {
+            Some(triangles) => triangles,
+            None => {
+                info!("Warning, face {:?} could not be added.", vids);
+                skipped += 1;
+                continue;
+            }
+        };
+        for [a, b, c] in triangles {
+            let triangle = [corners[a], corners[b], corners[c]];
+            let face = match corners_to_face(&triangle, |corner| Some(corner.v)) {
+                Some(face) => face,
+                None => continue,
+            };
+            match mesh.try_add_connection(face.a, face.b, face.c) {
+                Ok(fid) => {
+                    if has_parts {
+                        let part = part.clone().unwrap_or_else(|| "default".to_string());
+                        let parts = &mut material_info.parts;
+                        let index = *part_indices.entry(part.clone()).or_insert_with(|| {
+                            parts.push(MeshPart {
+                                name: part,
+                                faces: vec![],
+                            });
+                            parts.len() - 1
+                        });
+                        parts[index].faces.push(fid.val);
+                    }
+                    let surface = material_info
+                        .surfaces
+                        .entry(surface_name.clone())
+                        .or_insert_with(MaterialSurface::new);
+                    surface.faces.insert(face.clone());
+                    if let Some(uv_face) = corners_to_face(&triangle, |corner| corner.vt) {
+                        surface.uvs.insert(face, uv_face);
+                    }
+                    added.push(triangle);
+                }
                 Err(_) => {
                     info!(
                         "Warning, face {},{},{} could not be added.",
                         face.a.val, face.b.val, face.c.val
                     );
+                    skipped += 1;
+                }
+            }
+        }
+    }
+    if skipped > 0 {
+        material_info
+            .warnings
+            .push(format!("{} faces could not be added", skipped));
+    }
+
+    // Texture coordinates and normals can only be used per vertex when
+    // every corner references them by the same index as its vertex.
+    // Otherwise, they are stored per wedge.
+    let differs = |index: Option<usize>, corner: &Corner| matches!(index, Some(i) if i != corner.v);
+    let per_wedge = added.iter().flatten().any(|corner| {
+        (uvs.len() > 0 && differs(corner.vt, corner))
+            || (!normals.is_empty() && differs(corner.vn, corner))
+    });
+    if per_wedge {
+        for triangle in added.iter() {
+            let face_normal = face_normal(mesh, triangle);
+            for corner in triangle.iter() {
+                if uvs.len() > 0 {
+                    let uv = corner
+                        .vt
+                        .filter(|vt| *vt < uvs.len())
+                        .map(|vt| uvs.get_d(vt))
+                        .unwrap_or_else(|| Point3D::new(0.0, 0.0, 0.0));
+                    material_info.uv.push_d(uv);
+                }
+                if !normals.is_empty() {
+                    let normal = corner
+                        .vn
+                        .and_then(|vn| normals.get(vn))
+                        .cloned()
+                        .unwrap_or(face_normal);
+                    material_info.normals.push(normal);
+                }
+            }
+        }
+    } else {
+        material_info.uv = uvs;
+        if !normals.is_empty() {
+            material_info.normals = vec![three_d::Vec3::new(0.0, 0.0, 0.0); mesh.num_vertices()];
+            let mut has_normal = vec![false; mesh.num_vertices()];
+            for corner in added.iter().flatten() {
+                if let Some(normal) = corner.vn.and_then(|vn| normals.get(vn)) {
+                    material_info.normals[corner.v] = *normal;
+                    has_normal[corner.v] = true;
+                }
+            }
+            // Vertices that no face gives a normal get the average of
+            // the normals of their faces, rather than none at all.
+            let mut computed = vec![three_d::Vec3::new(0.0, 0.0, 0.0); mesh.num_vertices()];
+            for triangle in added.iter() {
+                if triangle.iter().any(|corner| !has_normal[corner.v]) {
+                    let face_normal = face_normal(mesh, triangle);
+                    for corner in triangle.iter() {
+                        computed[corner.v] += face_normal;
+                    }
+                }
+            }
+            for (v, normal) in computed.iter().enumerate() {
+                if !has_normal[v] && normal.magnitude2() > 0.0 {
+                    material_info.normals[v] = normal.normalize();
                 }
             }
         }
@@ -270,8 +320,31 @@ where
     Ok(())
 }
 
+/// Name given by an "o" or "g" statement, with names of multiple groups
+/// joined by spaces
+fn part_statement_name(line: &[u8]) -> Option<String> {
+    let names: Vec<String> = to_words_skip_empty(line)
+        .skip(1)
+        .filter_map(from_ascii::<String>)
+        .collect();
+    if names.is_empty() {
+        None
+    } else {
+        Some(names.join(" "))
+    }
+}
+
+/// Name of the part of the current object and group
+fn part_name(object: &Option<String>, group: &Option<String>) -> Option<String> {
+    match (object, group) {
+        (Some(object), Some(group)) => Some(format!("{}/{}", object, group)),
+        (Some(name), None) | (None, Some(name)) => Some(name.clone()),
+        (None, None) => None,
+    }
+}
+
 /// Loads IsPushable<Is3D> from the .obj file format
-pub fn load_obj_points<IP, P, R>(read: &mut R, ip: &mut IP) -> ObjResult<()>
+pub fn load_obj_points<IP, P, R>(read: &mut R, ip: &mut IP) -> IoResult<()>
 where
     IP: IsPushable<P>,
     P: IsBuildable3D,
@@ -280,29 +353,31 @@ where
     let mut line_buffer = Vec::new();
     let mut i_line = 0;
 
-    while let Ok(line) = fetch_line(read, &mut line_buffer) {
+    while let Some(line) = fetch_line(read, &mut line_buffer)? {
         i_line += 1;
 
         if line.starts_with(b"v ") {
             let mut words = to_words_skip_empty(line);
 
             // skip "v"
-            words.next().ok_or(ObjError::LineParse(i_line))?;
+            words
+                .next()
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
             let x = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(ObjError::LineParse(i_line))?;
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
             let y = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(ObjError::LineParse(i_line))?;
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
             let z = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(ObjError::LineParse(i_line))?;
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
             ip.push(P::new(x, y, z));
         }
@@ -313,36 +388,162 @@ where
 
 //------------------------------------------------------------------------------
 
-/// Error type for .obj file operations
-pub enum ObjError {
-    AccessFile,
-    InvalidMeshIndices(usize, usize, usize),
-    LineParse(usize),
-    NotTriangularMesh(usize),
+/// Zero-based vertex, texture coordinate and normal indices of a face
+/// corner.
+#[derive(Copy, Clone)]
+struct Corner {
+    v: usize,
+    vt: Option<usize>,
+    vn: Option<usize>,
 }
 
-/// Result type for .obj file operations
-pub type ObjResult<T> = std::result::Result<T, ObjError>;
+/// Parses a face corner of the form v, v/vt, v//vn or v/vt/vn. `counts`
+/// are the numbers of vertices, texture coordinates and normals read so
+/// far, which negative indices are relative to.
+fn parse_corner(word: &[u8], counts: [usize; 3]) -> Option<Corner> {
+    let mut parts = word.split(|c| *c == b'/');
+    // Texture coordinates and normals may be left out, but if they're
+    // there they must be valid.
+    let optional_index = |part: Option<&[u8]>, count| match part {
+        None | Some(b"") => Some(None),
+        Some(part) => to_index(part, count).map(Some),
+    };
+    let v = parts.next().and_then(|part| to_index(part, counts[0]))?;
+    let vt = optional_index(parts.next(), counts[1])?;
+    let vn = optional_index(parts.next(), counts[2])?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(Corner { v, vt, vn })
+}
 
-impl fmt::Debug for ObjError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::AccessFile => write!(f, "Unable to access file"),
-            Self::LineParse(x) => write!(f, "Unable to parse line {}", x),
-            Self::InvalidMeshIndices(x, y, z) => {
-                write!(f, "File contains invalid mesh indices: {}, {}, {}", x, y, z)
-            }
-            Self::NotTriangularMesh(x) => write!(
-                f,
-                "File contains face with more than 3 sets of indices on line {}",
-                x
-            ),
-        }
+/// Converts an obj index to a zero-based one. obj indexing starts at 1,
+/// and -1 is the last element read before the face.
+fn to_index(part: &[u8], count: usize) -> Option<usize> {
+    let index: isize = from_ascii(part)?;
+    if index > 0 {
+        Some(index as usize - 1)
+    } else if index < 0 {
+        count.checked_sub(index.unsigned_abs())
+    } else {
+        None
+    }
+}
+
+fn corners_to_face<F>(triangle: &[Corner; 3], index: F) -> Option<Face3>
+where
+    F: Fn(&Corner) -> Option<usize>,
+{
+    Some(Face3 {
+        a: VId {
+            val: index(&triangle[0])?,
+        },
+        b: VId {
+            val: index(&triangle[1])?,
+        },
+        c: VId {
+            val: index(&triangle[2])?,
+        },
+    })
+}
+
+/// Geometric normal of a triangle, used for corners that don't
+/// reference a normal.
+fn face_normal<EM, P>(mesh: &EM, triangle: &[Corner; 3]) -> three_d::Vec3
+where
+    EM: IsVertexEditableMesh<P, Face3>,
+    P: IsBuildable3D + Clone,
+{
+    let point = |corner: &Corner| {
+        mesh.vertex(VId { val: corner.v })
+            .map(|p| three_d::vec3(p.x() as f32, p.y() as f32, p.z() as f32))
+            .unwrap_or_else(|_| three_d::vec3(0.0, 0.0, 0.0))
+    };
+    let p0 = point(&triangle[0]);
+    let normal = (point(&triangle[1]) - p0).cross(point(&triangle[2]) - p0);
+    if normal.magnitude2() > 0.0 {
+        normal.normalize()
+    } else {
+        normal
     }
 }
 
-impl From<ioError> for ObjError {
-    fn from(_error: ioError) -> Self {
-        ObjError::AccessFile
+//------------------------------------------------------------------------------
+
+/// Faces of a mesh that share a material, for saving to .obj files.
+pub struct ObjSurface<'a> {
+    /// Material of the faces, as named in the material library.
+    pub material_name: Option<&'a str>,
+    /// Vertex indices, three per face.
+    pub indices: &'a [u32],
+}
+
+/// A mesh to save to .obj files. All buffers are flat arrays with a
+/// stride of 3 and hold one entry per vertex.
+pub struct ObjMeshData<'a> {
+    pub positions: &'a [f32],
+    pub normals: Option<&'a [f32]>,
+    /// Texture coordinates. Only the first two of each are written.
+    pub uvs: Option<&'a [f32]>,
+    /// Written after the positions, as many tools do.
+    pub colors: Option<&'a [f32]>,
+    pub surfaces: Vec<ObjSurface<'a>>,
+}
+
+/// Saves a mesh in the .obj file format, referring to the given
+/// material library if there is one
+pub fn save_obj<W>(write: &mut W, mtl_lib: Option<&str>, data: &ObjMeshData) -> IoResult<()>
+where
+    W: Write,
+{
+    writeln!(write, "# Created by web-geo-viewer")?;
+    if let Some(mtl_lib) = mtl_lib {
+        writeln!(write, "mtllib {}", mtl_lib)?;
     }
+    let num_vertices = data.positions.len() / 3;
+    for i in 0..num_vertices {
+        let p = &data.positions[3 * i..3 * i + 3];
+        match data.colors {
+            Some(colors) => {
+                let c = &colors[3 * i..3 * i + 3];
+                writeln!(
+                    write,
+                    "v {} {} {} {} {} {}",
+                    p[0], p[1], p[2], c[0], c[1], c[2]
+                )?;
+            }
+            None => writeln!(write, "v {} {} {}", p[0], p[1], p[2])?,
+        }
+    }
+    if let Some(uvs) = data.uvs {
+        for i in 0..num_vertices {
+            writeln!(write, "vt {} {}", uvs[3 * i], uvs[3 * i + 1])?;
+        }
+    }
+    if let Some(normals) = data.normals {
+        for i in 0..num_vertices {
+            let n = &normals[3 * i..3 * i + 3];
+            writeln!(write, "vn {} {} {}", n[0], n[1], n[2])?;
+        }
+    }
+    for surface in data.surfaces.iter() {
+        if let Some(material_name) = surface.material_name {
+            writeln!(write, "usemtl {}", material_name)?;
+        }
+        for face in surface.indices.chunks_exact(3) {
+            write!(write, "f")?;
+            for index in face.iter() {
+                // .obj indices start at 1.
+                let index = index + 1;
+                match (data.uvs.is_some(), data.normals.is_some()) {
+                    (true, true) => write!(write, " {}/{}/{}", index, index, index)?,
+                    (true, false) => write!(write, " {}/{}", index, index)?,
+                    (false, true) => write!(write, " {}//{}", index, index)?,
+                    (false, false) => write!(write, " {}", index)?,
+                }
+            }
+            writeln!(write)?;
+        }
+    }
+    Ok(())
 }
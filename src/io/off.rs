@@ -24,17 +24,26 @@ OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
 use rust_3d::*;
 
-use std::{
-    fmt,
-    io::{BufRead, Error as ioError},
-};
+use std::io::BufRead;
 
-use super::utils::*;
+use super::{
+    error::{Error, ErrorKind, IoResult},
+    triangulate::*,
+    utils::*,
+    MaterialInfo,
+};
 
 //------------------------------------------------------------------------------
 
-/// Loads an IsMesh3D from the off file format
-pub fn load_off_mesh<EM, P, R>(read: &mut R, mesh: &mut EM) -> OffResult<()>
+/// Loads an IsMesh3D from the off file format. Vertex normals, vertex
+/// colors and texture coordinates of the COFF, NOFF and STOFF variants
+/// go into `material_info`, with one entry per vertex. If any face has
+/// a color, the colors are per wedge instead, three per face.
+pub fn load_off_mesh<EM, P, R>(
+    read: &mut R,
+    mesh: &mut EM,
+    material_info: &mut MaterialInfo,
+) -> IoResult<()>
 where
     EM: IsFaceEditableMesh<P, Face3> + IsVertexEditableMesh<P, Face3>,
     P: IsBuildable3D + Clone,
@@ -43,34 +52,39 @@ where
     let mut line_buffer = Vec::new();
     let mut i_line = 0;
 
-    let mut off_seen = false;
+    let mut header = OffHeader::new();
     let mut counts = None;
+    let mut vertex_colors = vec![];
+    // One entry per triangle, for faces that have a color.
+    let mut face_colors: Vec<Option<three_d::Vec3>> = vec![];
+    let mut n_polygons = 0;
+    let mut skipped = 0;
 
-    while let Ok(line) = fetch_line(read, &mut line_buffer) {
+    while let Some(line) = fetch_line(read, &mut line_buffer)? {
         i_line += 1;
 
-        if !off_seen && line.starts_with(b"OFF") {
-            off_seen = true;
-            continue;
-        }
-
         if line.is_empty() || line.starts_with(b"#") {
             continue;
         }
 
+        let line = match header.read(line, i_line)? {
+            Some(rest) => rest,
+            None => continue,
+        };
+
         if counts.is_none() {
             let mut words = to_words_skip_empty(line);
-            let n_vertices = words
+            let n_vertices: usize = words
                 .next()
-                .and_then(|word| from_ascii(word))
-                .ok_or(OffError::LineParse(i_line))?;
-            let n_faces = words
+                .and_then(from_ascii)
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
+            let n_faces: usize = words
                 .next()
-                .and_then(|word| from_ascii(word))
-                .ok_or(OffError::LineParse(i_line))?;
+                .and_then(from_ascii)
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
 
-            mesh.reserve_vertices(n_vertices);
-            mesh.reserve_faces(n_faces);
+            mesh.reserve_vertices(n_vertices.min(MAX_RESERVE));
+            mesh.reserve_faces(n_faces.min(MAX_RESERVE));
 
             counts = Some([n_vertices, n_faces]);
             continue;
@@ -78,56 +92,105 @@ where
 
         // safe since checked above
         if mesh.num_vertices() < counts.unwrap()[0] {
-            let mut words = to_words_skip_empty(line);
-
-            let x = words
-                .next()
-                .and_then(|word| from_ascii(word))
-                .ok_or(OffError::LineParse(i_line))?;
-
-            let y = words
-                .next()
-                .and_then(|word| from_ascii(word))
-                .ok_or(OffError::LineParse(i_line))?;
-
-            let z = words
-                .next()
-                .and_then(|word| from_ascii(word))
-                .ok_or(OffError::LineParse(i_line))?;
-
-            mesh.add_vertex(P::new(x, y, z));
+            let vertex = header.read_vertex::<P>(line, i_line)?;
+            mesh.add_vertex(vertex.position);
+            if let Some(normal) = vertex.normal {
+                material_info.normals.push(normal);
+            }
+            if let Some(color) = vertex.color {
+                vertex_colors.push(color);
+            }
+            if let Some(uv) = vertex.uv {
+                material_info.uv.push(uv);
+            }
         } else {
             let mut words = to_words_skip_empty(line);
 
-            let count_face = words.next().ok_or(OffError::LineParse(i_line))?;
-
-            if count_face == b"3" {
-                let a = words
-                    .next()
-                    .and_then(|word| from_ascii(word))
-                    .ok_or(OffError::LineParse(i_line))?;
+            let count_face: usize = words
+                .next()
+                .and_then(from_ascii)
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
+
+            let mut vids = vec![];
+            for _ in 0..count_face {
+                vids.push(
+                    words
+                        .next()
+                        .and_then(from_ascii)
+                        .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?,
+                );
+            }
 
-                let b = words
-                    .next()
-                    .and_then(|word| from_ascii(word))
-                    .ok_or(OffError::LineParse(i_line))?;
+            // The rest of the line may be a color. A single value is an
+            // index into a color map, which we don't have.
+            let rest = words
+                .map(|word| from_ascii(word).ok_or(Error::on_line(ErrorKind::LineParse, i_line)))
+                .collect::<IoResult<Vec<f32>>>()?;
+            let face_color = if rest.len() >= 3 {
+                Some(to_color(&rest[..]))
+            } else {
+                None
+            };
+
+            let triangles = triangulate_mesh_polygon(mesh, &vids[..])
+                .ok_or(Error::on_line(ErrorKind::InvalidMeshIndices, i_line))?;
+            // Triangles with a vertex twice are left out.
+            for [a, b, c] in triangles {
+                match mesh.try_add_connection(
+                    VId { val: vids[a] },
+                    VId { val: vids[b] },
+                    VId { val: vids[c] },
+                ) {
+                    Ok(_) => face_colors.push(face_color),
+                    Err(_) => skipped += 1,
+                }
+            }
+            n_polygons += 1;
+        }
+    }
 
-                let c = words
-                    .next()
-                    .and_then(|word| from_ascii(word))
-                    .ok_or(OffError::LineParse(i_line))?;
+    // Files that end early are damaged, even if what's there is valid.
+    match counts {
+        None => return Err(Error::on_line(ErrorKind::LoadHeaderInvalid, i_line)),
+        Some([n_vertices, _]) if mesh.num_vertices() < n_vertices => {
+            return Err(Error::on_line(ErrorKind::LoadVertexCountIncorrect, i_line))
+        }
+        Some([_, n_faces]) if n_polygons < n_faces => {
+            return Err(Error::on_line(ErrorKind::LoadFaceCountIncorrect, i_line))
+        }
+        _ => {}
+    }
+    if skipped > 0 {
+        material_info
+            .warnings
+            .push(format!("{} faces could not be added", skipped));
+    }
 
-                mesh.try_add_connection(VId { val: a }, VId { val: b }, VId { val: c })
-                    .or(Err(OffError::InvalidMeshIndices(i_line)))?;
+    if face_colors.iter().any(|color| color.is_some()) {
+        // Faces without a color of their own use the vertex colors, or
+        // the default material color.
+        let default_color = super::Material::new().diffuse_color;
+        for (fid, face_color) in face_colors.iter().enumerate() {
+            let face = mesh
+                .face_vertex_ids(FId { val: fid })
+                .or(Err(Error::on_line(ErrorKind::InvalidMeshIndices, i_line)))?;
+            for vid in [face.a, face.b, face.c].iter() {
+                let color = match face_color {
+                    Some(color) => *color,
+                    None => vertex_colors.get(vid.val).cloned().unwrap_or(default_color),
+                };
+                material_info.colors.push(color);
             }
         }
+    } else {
+        material_info.colors = vertex_colors;
     }
 
     Ok(())
 }
 
 /// Loads IsPushable<Is3D> from the .off file format
-pub fn load_off_points<IP, P, R>(read: &mut R, ip: &mut IP) -> OffResult<()>
+pub fn load_off_points<IP, P, R>(read: &mut R, ip: &mut IP) -> IoResult<()>
 where
     IP: IsPushable<P>,
     P: IsBuildable3D,
@@ -136,90 +199,216 @@ where
     let mut line_buffer = Vec::new();
     let mut i_line = 0;
 
-    let mut off_seen = false;
-    let mut n_vertices = None;
+    let mut header = OffHeader::new();
+    let mut n_vertices: Option<usize> = None;
     let mut n_added = 0;
 
-    while let Ok(line) = fetch_line(read, &mut line_buffer) {
+    while let Some(line) = fetch_line(read, &mut line_buffer)? {
         i_line += 1;
 
-        if !off_seen && line.starts_with(b"OFF") {
-            off_seen = true;
-            continue;
-        }
-
         if line.is_empty() || line.starts_with(b"#") {
             continue;
         }
 
+        let line = match header.read(line, i_line)? {
+            Some(rest) => rest,
+            None => continue,
+        };
+
         if n_vertices.is_none() {
             let mut words = to_words_skip_empty(line);
             n_vertices = Some(
                 words
                     .next()
-                    .and_then(|word| from_ascii(word))
-                    .ok_or(OffError::LineParse(i_line))?,
+                    .and_then(from_ascii)
+                    .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?,
             );
-            ip.reserve(n_vertices.unwrap());
+            ip.reserve(n_vertices.unwrap().min(MAX_RESERVE));
 
             continue;
         }
 
         // safe since checked above
         if n_added < n_vertices.unwrap() {
-            let mut words = to_words_skip_empty(line);
-
-            let x = words
-                .next()
-                .and_then(|word| from_ascii(word))
-                .ok_or(OffError::LineParse(i_line))?;
-
-            let y = words
-                .next()
-                .and_then(|word| from_ascii(word))
-                .ok_or(OffError::LineParse(i_line))?;
-
-            let z = words
-                .next()
-                .and_then(|word| from_ascii(word))
-                .ok_or(OffError::LineParse(i_line))?;
-
-            ip.push(P::new(x, y, z));
+            ip.push(header.read_vertex::<P>(line, i_line)?.position);
             n_added += 1;
         } else {
             break;
         }
     }
 
-    Ok(())
+    match n_vertices {
+        None => Err(Error::on_line(ErrorKind::LoadHeaderInvalid, i_line)),
+        Some(n_vertices) if n_added < n_vertices => {
+            Err(Error::on_line(ErrorKind::LoadVertexCountIncorrect, i_line))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Does the line start with the header keyword of an .off file, such
+/// as OFF or STCNOFF? Files may leave the keyword out, so this can't
+/// tell for all of them.
+pub fn is_off_header(line: &[u8]) -> bool {
+    match to_words_skip_empty(line).next() {
+        Some(keyword) if keyword.ends_with(b"OFF") => OffHeader::new().read(line, 1).is_ok(),
+        _ => false,
+    }
 }
 
 //------------------------------------------------------------------------------
 
-/// Error type for .off file operations
-pub enum OffError {
-    AccessFile,
-    InvalidMeshIndices(usize),
-    LineParse(usize),
+/// What the header keyword of an .off file, such as STCNOFF, says
+/// about the vertices.
+struct OffHeader {
+    /// Has the keyword been read? It's optional.
+    seen: bool,
+    /// nOFF files give the dimension on the next line.
+    dimension_pending: bool,
+    texcoords: bool,
+    colors: bool,
+    normals: bool,
+    /// 4OFF files have homogeneous coordinates.
+    homogeneous: bool,
 }
 
-/// Result type for .off file operations
-pub type OffResult<T> = std::result::Result<T, OffError>;
+struct OffVertex<P> {
+    position: P,
+    normal: Option<three_d::Vec3>,
+    color: Option<three_d::Vec3>,
+    uv: Option<Point3D>,
+}
 
-impl fmt::Debug for OffError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::AccessFile => write!(f, "Unable to access file"),
-            Self::LineParse(x) => write!(f, "Unable to parse line {}", x),
-            Self::InvalidMeshIndices(x) => {
-                write!(f, "File contains invalid mesh indices on line {}", x)
+impl OffHeader {
+    fn new() -> OffHeader {
+        OffHeader {
+            seen: false,
+            dimension_pending: false,
+            texcoords: false,
+            colors: false,
+            normals: false,
+            homogeneous: false,
+        }
+    }
+
+    /// Reads the header keyword and dimension, if this line holds
+    /// them. Returns the part of the line that is left to read, if any.
+    fn read<'a>(&mut self, line: &'a [u8], i_line: usize) -> IoResult<Option<&'a [u8]>> {
+        if self.dimension_pending {
+            let dimension: usize = to_words_skip_empty(line)
+                .next()
+                .and_then(from_ascii)
+                .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
+            if dimension != 3 {
+                return Err(Error::on_line(ErrorKind::UnsupportedDimension, i_line));
             }
+            self.dimension_pending = false;
+            return Ok(None);
+        }
+        if self.seen {
+            return Ok(Some(line));
+        }
+        self.seen = true;
+        let start = line
+            .iter()
+            .position(|c| !c.is_ascii_whitespace())
+            .unwrap_or(0);
+        let end = line[start..]
+            .iter()
+            .position(|c| c.is_ascii_whitespace())
+            .map(|end| start + end)
+            .unwrap_or_else(|| line.len());
+        let keyword = &line[start..end];
+        if !keyword.ends_with(b"OFF") {
+            // Files without a keyword start with the counts.
+            return Ok(Some(line));
+        }
+        let mut prefix = &keyword[..keyword.len() - 3];
+        if prefix.starts_with(b"ST") {
+            self.texcoords = true;
+            prefix = &prefix[2..];
+        }
+        for c in prefix.iter() {
+            match c {
+                b'C' => self.colors = true,
+                b'N' => self.normals = true,
+                b'4' => self.homogeneous = true,
+                b'n' => self.dimension_pending = true,
+                _ => return Err(Error::on_line(ErrorKind::LineParse, i_line)),
+            }
+        }
+        // Some files put the counts on the same line as the keyword.
+        let rest = &line[end..];
+        if to_words_skip_empty(rest).next().is_some() {
+            if self.dimension_pending {
+                // The dimension comes first.
+                return Err(Error::on_line(ErrorKind::LineParse, i_line));
+            }
+            Ok(Some(rest))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn read_vertex<P>(&self, line: &[u8], i_line: usize) -> IoResult<OffVertex<P>>
+    where
+        P: IsBuildable3D,
+    {
+        let values = to_words_skip_empty(line)
+            .map(|word| from_ascii(word).ok_or(Error::on_line(ErrorKind::LineParse, i_line)))
+            .collect::<IoResult<Vec<f64>>>()?;
+        let n_position = if self.homogeneous { 4 } else { 3 };
+        let n_normal = if self.normals { 3 } else { 0 };
+        let n_uv = if self.texcoords { 2 } else { 0 };
+        if values.len() < n_position + n_normal + n_uv {
+            return Err(Error::on_line(ErrorKind::LineParse, i_line));
+        }
+        let n_color = values.len() - n_position - n_normal - n_uv;
+        if self.colors && n_color != 3 && n_color != 4 {
+            return Err(Error::on_line(ErrorKind::LineParse, i_line));
+        }
+
+        let w = if self.homogeneous { values[3] } else { 1.0 };
+        if w == 0.0 {
+            return Err(Error::on_line(ErrorKind::LineParse, i_line));
+        }
+        let position = P::new(values[0] / w, values[1] / w, values[2] / w);
+        let mut rest = &values[n_position..];
+        let mut normal = None;
+        if self.normals {
+            normal = Some(three_d::vec3(
+                rest[0] as f32,
+                rest[1] as f32,
+                rest[2] as f32,
+            ));
+            rest = &rest[3..];
+        }
+        let mut color = None;
+        if self.colors {
+            let components: Vec<f32> = rest[..n_color].iter().map(|c| *c as f32).collect();
+            color = Some(to_color(&components[..]));
+            rest = &rest[n_color..];
+        }
+        let mut uv = None;
+        if self.texcoords {
+            uv = Some(Point3D::new(rest[0], rest[1], 0.0));
         }
+        Ok(OffVertex {
+            position,
+            normal,
+            color,
+            uv,
+        })
     }
 }
 
-impl From<ioError> for OffError {
-    fn from(_error: ioError) -> Self {
-        OffError::AccessFile
+/// Colors are given either as floats between 0 and 1, or as integers
+/// between 0 and 255. Alpha is ignored.
+fn to_color(components: &[f32]) -> three_d::Vec3 {
+    let rgb = &components[..3];
+    if rgb.iter().any(|c| *c > 1.0) {
+        three_d::vec3(rgb[0] / 255.0, rgb[1] / 255.0, rgb[2] / 255.0)
+    } else {
+        three_d::vec3(rgb[0], rgb[1], rgb[2])
     }
 }
@@ -23,9 +23,8 @@ OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 //! Module for load functions of the ply file format
 
 use rust_3d::*;
-use std::collections::HashMap;
 
-use crate::io::{MaterialInfo, MaterialSurface};
+use crate::io::{triangulate_mesh_polygon, MaterialInfo, MaterialSurface};
 use crate::Material;
 use core::convert::TryFrom;
 
@@ -33,7 +32,11 @@ use log::*;
 use std::io::{BufRead, Read};
 use std::str;
 
-use super::super::{byte_reader::*, utils::*};
+use super::super::{
+    byte_reader::*,
+    error::{Error, ErrorKind, IoResult},
+    utils::*,
+};
 
 use super::{types::*, utils::*};
 
@@ -45,27 +48,30 @@ pub fn load_ply<EM, P, R>(
     mesh: &mut EM,
     material: &mut Material,
     material_info: &mut MaterialInfo,
-    name: &String,
-) -> PlyResult<()>
+    name: &str,
+) -> IoResult<()>
 where
     EM: IsFaceEditableMesh<P, Face3> + IsVertexEditableMesh<P, Face3>,
     P: IsBuildable3D + Clone,
     R: BufRead,
 {
+    let mut read = ByteCounter::new(read);
     let mut line_buffer = Vec::new();
     let mut i_line = 0;
     let surface = MaterialSurface::new();
-    material_info.surfaces.insert(name.clone(), surface);
+    material_info.surfaces.insert(name.to_string(), surface);
 
-    let header = load_header(read, &mut line_buffer, &mut i_line)?;
+    // The header is read line by line, so its errors are on the last line read.
+    let header =
+        load_header(&mut read, &mut line_buffer, &mut i_line).map_err(|e| e.at_line(i_line))?;
     material.texture_name = header.texture_name.clone();
 
-    mesh.reserve_vertices(header.n_vertices);
-    mesh.reserve_faces(header.n_faces);
+    mesh.reserve_vertices(header.n_vertices.min(MAX_RESERVE));
+    mesh.reserve_faces(header.n_faces.min(MAX_RESERVE));
 
-    let result = match header.format {
+    let skipped = match header.format {
         Format::Ascii => load_ascii(
-            read,
+            &mut read,
             mesh,
             &header,
             &mut line_buffer,
@@ -74,20 +80,25 @@ where
             name,
         ),
         Format::LittleEndian => {
-            load_binary::<LittleReader, _, _, _>(read, mesh, &header, material_info, name)
+            load_binary::<LittleReader, _, _, _>(&mut read, mesh, &header, material_info, name)
         }
         Format::BigEndian => {
-            load_binary::<BigReader, _, _, _>(read, mesh, &header, material_info, name)
+            load_binary::<BigReader, _, _, _>(&mut read, mesh, &header, material_info, name)
         }
-    };
-    result
+    }?;
+    if skipped > 0 {
+        material_info
+            .warnings
+            .push(format!("{} faces could not be added", skipped));
+    }
+    Ok(())
 }
 
 //------------------------------------------------------------------------------
 //------------------------------------------------------------------------------
 //------------------------------------------------------------------------------
 
-fn load_header<R>(read: &mut R, line_buffer: &mut Vec<u8>, i_line: &mut usize) -> PlyResult<Header>
+fn load_header<R>(read: &mut R, line_buffer: &mut Vec<u8>, i_line: &mut usize) -> IoResult<Header>
 where
     R: BufRead,
 {
@@ -107,12 +118,12 @@ where
     let mut vertex_before = BytesWords::default();
     let mut vertex_between_first_snd = BytesWords::default();
     let mut vertex_between_snd_third = BytesWords::default();
-    let mut after = BytesWords::default();
+    let mut after = vec![];
 
     let mut face_types = vec![];
     let mut texture_name = None;
 
-    while let Ok(line) = fetch_line(read, line_buffer) {
+    while let Some(line) = fetch_line(read, line_buffer)? {
         *i_line += 1;
 
         if line.starts_with(b"comment") {
@@ -145,7 +156,7 @@ where
                 ply_found = true;
                 continue;
             }
-            return Err(PlyError::LoadStartNotFound);
+            return Err(ErrorKind::LoadStartNotFound.into());
         }
 
         if opt_format.is_none() {
@@ -153,7 +164,7 @@ where
                 b"format ascii 1.0" => Format::Ascii,
                 b"format binary_little_endian 1.0" => Format::LittleEndian,
                 b"format binary_big_endian 1.0" => Format::BigEndian,
-                _ => return Err(PlyError::LoadFormatNotFound),
+                _ => return Err(ErrorKind::LoadFormatNotFound.into()),
             });
             continue;
         }
@@ -167,7 +178,7 @@ where
                         words
                             .nth(2)
                             .and_then(|w| from_ascii(w))
-                            .ok_or(PlyError::LineParse(*i_line))?,
+                            .ok_or(ErrorKind::LineParse)?,
                     );
                     continue;
                 }
@@ -184,7 +195,7 @@ where
                         words
                             .nth(2)
                             .and_then(|w| from_ascii(w))
-                            .ok_or(PlyError::LineParse(*i_line))?,
+                            .ok_or(ErrorKind::LineParse)?,
                     );
                     continue;
                 }
@@ -198,9 +209,12 @@ where
                     let mut words = to_words_skip_empty(line);
                     skip_n(&mut words, 1); // skip "property"
 
-                    let t =
-                        Type::try_from(words.next().ok_or(PlyError::InvalidProperty(*i_line))?)?;
-                    let id = words.next().ok_or(PlyError::InvalidProperty(*i_line))?;
+                    let t = Type::try_from(words.next().ok_or(ErrorKind::InvalidProperty)?)?;
+                    let id = words.next().ok_or(ErrorKind::InvalidProperty)?;
+                    let is_coordinate = id == b"x" || id == b"y" || id == b"z";
+                    if is_coordinate && i_vertex_order == vertex_order.len() {
+                        return Err(ErrorKind::InvalidVertexDimensionDefinition.into());
+                    }
                     if id == b"x" {
                         opt_fst_type = Some(VertexType::try_from(t)?);
                         n_types_found += 1;
@@ -227,8 +241,10 @@ where
                             vertex_between_snd_third.bytes += t.size_bytes();
                             vertex_between_snd_third.words += 1;
                         } else {
-                            after.bytes += t.size_bytes();
-                            after.words += 1;
+                            after.push(VertexProperty {
+                                attribute: VertexAttribute::from(id),
+                                t,
+                            });
                         }
                     }
                 }
@@ -237,14 +253,14 @@ where
                         let mut words = to_words_skip_empty(line);
                         skip_n(&mut words, 2); // skip "property" and "list"
                         let t_count = FaceType::try_from(Type::try_from(
-                            words.next().ok_or(PlyError::InvalidProperty(*i_line))?,
+                            words.next().ok_or(ErrorKind::InvalidProperty)?,
                         )?)?;
                         let t_index = FaceType::try_from(Type::try_from(
-                            words.next().ok_or(PlyError::InvalidProperty(*i_line))?,
+                            words.next().ok_or(ErrorKind::InvalidProperty)?,
                         )?)?;
                         let name: String =
-                            str::from_utf8(words.next().ok_or(PlyError::InvalidProperty(*i_line))?)
-                                .unwrap()
+                            str::from_utf8(words.next().ok_or(ErrorKind::InvalidProperty)?)
+                                .or(Err(ErrorKind::InvalidProperty))?
                                 .to_string();
 
                         let face_format = FaceFormat {
@@ -255,24 +271,16 @@ where
                         face_types.push(face_format);
                     }
                 }
-                _ => return Err(PlyError::PropertyLineLocation(*i_line)),
+                _ => return Err(ErrorKind::PropertyLineLocation.into()),
             }
 
             continue;
         }
 
         if line == b"end_header" && ply_found {
-            if let (
-                Some(format),
-                Some(n_vertices),
-                Some(n_faces),
-                Some(x_type),
-                Some(y_type),
-                Some(z_type),
-            ) = (
+            if let (Some(format), Some(n_vertices), Some(x_type), Some(y_type), Some(z_type)) = (
                 opt_format,
                 opt_n_vertices,
-                opt_n_faces,
                 opt_fst_type,
                 opt_snd_type,
                 opt_third_type,
@@ -280,7 +288,8 @@ where
                 return Ok(Header {
                     format,
                     n_vertices,
-                    n_faces,
+                    // Point clouds may not have a face element at all.
+                    n_faces: opt_n_faces.unwrap_or(0),
                     vertex_format: VertexFormat {
                         order: VertexOrder::try_from(vertex_order)?,
                         first: x_type,
@@ -297,142 +306,203 @@ where
             }
         }
 
-        return Err(PlyError::LoadHeaderInvalid);
+        return Err(ErrorKind::LoadHeaderInvalid.into());
     }
 
-    Err(PlyError::LoadHeaderInvalid)
+    Err(ErrorKind::LoadHeaderInvalid.into())
 }
 
 //------------------------------------------------------------------------------
 
 fn load_binary<BR, EM, P, R>(
-    read: &mut R,
+    read: &mut ByteCounter<R>,
     mesh: &mut EM,
     header: &Header,
     material_info: &mut MaterialInfo,
-    name: &String,
-) -> PlyResult<()>
+    name: &str,
+) -> IoResult<usize>
 where
     EM: IsFaceEditableMesh<P, Face3> + IsVertexEditableMesh<P, Face3>,
     P: IsBuildable3D + Clone,
     R: Read,
     BR: IsByteReader,
 {
-    for _ in 0..header.n_vertices {
-        skip_bytes(read, header.vertex_format.before.bytes)?;
+    for i_vertex in 0..header.n_vertices {
+        let offset = read.offset;
+        let vertex = load_binary_vertex::<BR, _, _>(read, header, material_info)
+            .map_err(|e| e.at_element("vertex", i_vertex).at_offset(offset))?;
+        mesh.add_vertex(vertex);
+    }
 
-        let first = read_vertex_type::<BR, _>(read, header.vertex_format.first)?;
+    let mut skipped = 0;
+    for i_face in 0..header.n_faces {
+        let offset = read.offset;
+        skipped += load_binary_face::<BR, _, _, _>(read, mesh, header, material_info, name)
+            .map_err(|e| e.at_element("face", i_face).at_offset(offset))?;
+    }
+    Ok(skipped)
+}
 
-        skip_bytes(read, header.vertex_format.between_first_snd.bytes)?;
+fn load_binary_vertex<BR, P, R>(
+    read: &mut R,
+    header: &Header,
+    material_info: &mut MaterialInfo,
+) -> IoResult<P>
+where
+    P: IsBuildable3D,
+    R: Read,
+    BR: IsByteReader,
+{
+    skip_bytes(read, header.vertex_format.before.bytes)?;
+
+    let first = read_vertex_type::<BR, _>(read, header.vertex_format.first)?;
 
-        let snd = read_vertex_type::<BR, _>(read, header.vertex_format.snd)?;
+    skip_bytes(read, header.vertex_format.between_first_snd.bytes)?;
 
-        skip_bytes(read, header.vertex_format.between_snd_third.bytes)?;
+    let snd = read_vertex_type::<BR, _>(read, header.vertex_format.snd)?;
 
-        let third = read_vertex_type::<BR, _>(read, header.vertex_format.third)?;
+    skip_bytes(read, header.vertex_format.between_snd_third.bytes)?;
 
-        skip_bytes(read, header.vertex_format.after.bytes)?;
+    let third = read_vertex_type::<BR, _>(read, header.vertex_format.third)?;
 
-        mesh.add_vertex(point_with_order(
-            first,
-            snd,
-            third,
-            header.vertex_format.order,
-        ));
+    let mut color = three_d::Vec3::new(1.0, 1.0, 1.0);
+    let mut normal = three_d::Vec3::new(0.0, 0.0, 0.0);
+    for property in header.vertex_format.after.iter() {
+        let value = read_type::<BR, _>(read, property.t)?;
+        add_color_channel(&mut color, property, value);
+        add_normal_component(&mut normal, property, value);
+    }
+    if header.vertex_format.has_color() {
+        material_info.colors.push(color);
+    }
+    if header.vertex_format.has_normal() {
+        material_info.normals.push(normal);
     }
 
-    let mut idx_to_face: HashMap<usize, Face3> = HashMap::new();
-    let mut idx_to_uvs: HashMap<usize, Face3> = HashMap::new();
-    for i in 0..header.n_faces {
-        for format in header.face_format.iter() {
-            let element_count = read_face_type::<BR, _>(read, format.count)?;
-            if format.name == "vertex_indices" {
-                if element_count != 3 {
-                    return Err(PlyError::FaceStructure);
-                }
+    Ok(point_with_order(
+        first,
+        snd,
+        third,
+        header.vertex_format.order,
+    ))
+}
 
-                let a = read_face_type::<BR, _>(read, format.index)?;
-                let b = read_face_type::<BR, _>(read, format.index)?;
-                let c = read_face_type::<BR, _>(read, format.index)?;
-                let face = Face3 {
-                    a: VId { val: a },
-                    b: VId { val: b },
-                    c: VId { val: c },
-                };
-
-                idx_to_face.insert(i, face.clone());
-                material_info
-                    .surfaces
-                    .get_mut(name)
-                    .unwrap()
-                    .faces
-                    .insert(face);
-                mesh.try_add_connection(
-                    VId { val: a as usize },
-                    VId { val: b as usize },
-                    VId { val: c as usize },
-                )
-                .or(Err(PlyError::InvalidMeshIndices(None)))?;
-            } else if format.name == "texcoord" {
-                // There's no great standard for how to save a PLY with face
-                // UVs, but Meshlab's export is what we're targetting here.
-                if element_count != 6 {
-                    return Err(PlyError::FaceStructure);
-                }
-                let mut indices = vec![];
-                for _ in 0..3 {
-                    indices.push(material_info.uv.len());
-                    if format.index == FaceType::Float {
-                        let x = read_vertex_type::<BR, _>(read, VertexType::Float)?;
-                        let y = read_vertex_type::<BR, _>(read, VertexType::Float)?;
-                        let z = 1.0f64;
-                        material_info.uv.push_d(Point3D::new(x, y, z));
-                    } else if format.index == FaceType::Double {
-                        let x = read_vertex_type::<BR, _>(read, VertexType::Double)?;
-                        let y = read_vertex_type::<BR, _>(read, VertexType::Double)?;
-                        let z = 1.0f64;
-                        material_info.uv.push_d(Point3D::new(x, y, z));
-                    } else {
-                        return Err(PlyError::FaceStructure);
-                    }
+fn load_binary_face<BR, EM, P, R>(
+    read: &mut R,
+    mesh: &mut EM,
+    header: &Header,
+    material_info: &mut MaterialInfo,
+    name: &str,
+) -> IoResult<usize>
+where
+    EM: IsFaceEditableMesh<P, Face3> + IsVertexEditableMesh<P, Face3>,
+    P: IsBuildable3D + Clone,
+    R: Read,
+    BR: IsByteReader,
+{
+    let mut vids = vec![];
+    let mut texcoords = vec![];
+    for format in header.face_format.iter() {
+        let element_count = read_face_type::<BR, _>(read, format.count)?;
+        if format.name == "vertex_indices" {
+            for _ in 0..element_count {
+                vids.push(read_face_type::<BR, _>(read, format.index)?);
+            }
+        } else if format.name == "texcoord" {
+            // There's no great standard for how to save a PLY with face
+            // UVs, but Meshlab's export is what we're targetting here.
+            for _ in 0..element_count {
+                if format.index == FaceType::Float {
+                    texcoords.push(read_vertex_type::<BR, _>(read, VertexType::Float)?);
+                } else if format.index == FaceType::Double {
+                    texcoords.push(read_vertex_type::<BR, _>(read, VertexType::Double)?);
+                } else {
+                    return Err(ErrorKind::FaceStructure.into());
                 }
-                idx_to_face.insert(
-                    i,
-                    Face3 {
-                        a: VId { val: indices[0] },
-                        b: VId { val: indices[1] },
-                        c: VId { val: indices[2] },
-                    },
-                );
-            } else {
-                // Skip unknown fields
-                for _ in 0..element_count {
-                    if format.index == FaceType::Double {
-                        let _ = read_vertex_type::<BR, _>(read, VertexType::Double)?;
-                    } else if format.index == FaceType::Float {
-                        let _ = read_vertex_type::<BR, _>(read, VertexType::Float)?;
-                    } else {
-                        let _ = read_face_type::<BR, _>(read, format.index)?;
-                    }
+            }
+        } else {
+            // Skip unknown fields
+            for _ in 0..element_count {
+                if format.index == FaceType::Double {
+                    let _ = read_vertex_type::<BR, _>(read, VertexType::Double)?;
+                } else if format.index == FaceType::Float {
+                    let _ = read_vertex_type::<BR, _>(read, VertexType::Float)?;
+                } else {
+                    let _ = read_face_type::<BR, _>(read, format.index)?;
                 }
             }
-            // If we've read both a face and a uv, add a link
-            match idx_to_face.get(&i) {
-                Some(face) => match idx_to_uvs.get(&i) {
-                    Some(uv_face) => {
-                        let surface = material_info.surfaces.get_mut(name).unwrap();
-                        surface.uvs.insert(face.clone(), uv_face.clone());
-                        // Remove it to save memory
-                        idx_to_face.remove(&i);
-                        idx_to_uvs.remove(&i);
-                    }
-                    None => {}
-                },
-                None => {}
+        }
+    }
+    add_polygon(mesh, &vids[..], &texcoords[..], material_info, name)
+}
+
+//------------------------------------------------------------------------------
+
+/// Triangulates a face and adds it to the mesh. If there are texture
+/// coordinates, there must be one pair of them per corner, and they're
+/// added per wedge. Returns how many triangles could not be added, such
+/// as those with a vertex twice.
+fn add_polygon<EM, P>(
+    mesh: &mut EM,
+    vids: &[usize],
+    texcoords: &[f64],
+    material_info: &mut MaterialInfo,
+    name: &str,
+) -> IoResult<usize>
+where
+    EM: IsFaceEditableMesh<P, Face3> + IsVertexEditableMesh<P, Face3>,
+    P: IsBuildable3D + Clone,
+{
+    if !texcoords.is_empty() && texcoords.len() != 2 * vids.len() {
+        return Err(ErrorKind::FaceStructure.into());
+    }
+    let triangles = triangulate_mesh_polygon(mesh, vids).ok_or(ErrorKind::InvalidMeshIndices)?;
+    let mut skipped = 0;
+    for corners in triangles {
+        let face = Face3 {
+            a: VId {
+                val: vids[corners[0]],
+            },
+            b: VId {
+                val: vids[corners[1]],
+            },
+            c: VId {
+                val: vids[corners[2]],
+            },
+        };
+        if mesh.try_add_connection(face.a, face.b, face.c).is_err() {
+            skipped += 1;
+            continue;
+        }
+        let surface = material_info
+            .surfaces
+            .entry(name.to_string())
+            .or_insert_with(MaterialSurface::new);
+        if !texcoords.is_empty() {
+            let uv_base_idx = material_info.uv.len();
+            for corner in corners.iter() {
+                material_info.uv.push_d(Point3D::new(
+                    texcoords[2 * corner],
+                    texcoords[2 * corner + 1],
+                    1.0,
+                ));
             }
+            surface.uvs.insert(
+                face.clone(),
+                Face3 {
+                    a: VId { val: uv_base_idx },
+                    b: VId {
+                        val: uv_base_idx + 1,
+                    },
+                    c: VId {
+                        val: uv_base_idx + 2,
+                    },
+                },
+            );
         }
+        surface.faces.insert(face);
     }
-    Ok(())
+    Ok(skipped)
 }
 
 //------------------------------------------------------------------------------
@@ -444,16 +514,18 @@ fn load_ascii<EM, P, R>(
     line_buffer: &mut Vec<u8>,
     i_line: &mut usize,
     material_info: &mut MaterialInfo,
-    name: &String,
-) -> PlyResult<()>
+    name: &str,
+) -> IoResult<usize>
 where
     EM: IsFaceEditableMesh<P, Face3> + IsVertexEditableMesh<P, Face3>,
     P: IsBuildable3D + Clone,
     R: BufRead,
 {
-    let mut idx_to_face: HashMap<usize, Face3> = HashMap::new();
-    let mut idx_to_uvs: HashMap<usize, Face3> = HashMap::new();
-    while let Ok(line) = fetch_line(read, line_buffer) {
+    let has_color = header.vertex_format.has_color();
+    let has_normal = header.vertex_format.has_normal();
+    let mut n_faces_read = 0;
+    let mut skipped = 0;
+    while let Some(line) = fetch_line(read, line_buffer)? {
         *i_line += 1;
 
         if header.n_vertices > mesh.num_vertices() {
@@ -464,23 +536,40 @@ where
             let first = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(PlyError::InvalidVertex(*i_line))?;
+                .ok_or(Error::on_line(ErrorKind::InvalidVertex, *i_line))?;
 
             skip_n(&mut words, header.vertex_format.between_first_snd.words);
 
             let snd = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(PlyError::InvalidVertex(*i_line))?;
+                .ok_or(Error::on_line(ErrorKind::InvalidVertex, *i_line))?;
 
             skip_n(&mut words, header.vertex_format.between_snd_third.words);
 
             let third = words
                 .next()
                 .and_then(|w| from_ascii(w))
-                .ok_or(PlyError::InvalidVertex(*i_line))?;
-
-            // no need to skip 'after' since we're done with this line anyway
+                .ok_or(Error::on_line(ErrorKind::InvalidVertex, *i_line))?;
+
+            if has_color || has_normal {
+                let mut color = three_d::Vec3::new(1.0, 1.0, 1.0);
+                let mut normal = three_d::Vec3::new(0.0, 0.0, 0.0);
+                for property in header.vertex_format.after.iter() {
+                    let value = words
+                        .next()
+                        .and_then(from_ascii)
+                        .ok_or(Error::on_line(ErrorKind::InvalidVertex, *i_line))?;
+                    add_color_channel(&mut color, property, value);
+                    add_normal_component(&mut normal, property, value);
+                }
+                if has_color {
+                    material_info.colors.push(color);
+                }
+                if has_normal {
+                    material_info.normals.push(normal);
+                }
+            }
 
             mesh.add_vertex(point_with_order(
                 first,
@@ -492,109 +581,44 @@ where
             continue;
         }
 
-        if header.n_faces > mesh.num_faces() {
+        if header.n_faces > n_faces_read {
             let mut words = to_words_skip_empty(&line);
+            let mut vids = vec![];
+            let mut texcoords = vec![];
             for format in header.face_format.iter() {
-                if format.name == "vertex_indices" {
-                    // We expect 3 elements. Otherwise we don't know
-                    // how to handle these vertices.
-                    match words.next() {
-                        Some(word) => {
-                            if word != b"3" {
-                                return Err(PlyError::LineParse(*i_line));
-                            }
-                        }
-                        None => {
-                            return Err(PlyError::LineParse(*i_line));
-                        }
-                    }
-                    let maybe_a = words.next();
-                    let maybe_b = words.next();
-                    let maybe_c = words.next();
-                    if let (Some(a_bytes), Some(b_bytes), Some(c_bytes)) =
-                        (maybe_a, maybe_b, maybe_c)
-                    {
-                        if let (Some(a), Some(b), Some(c)) = (
-                            from_ascii(a_bytes),
-                            from_ascii(b_bytes),
-                            from_ascii(c_bytes),
-                        ) {
-                            let face = Face3 {
-                                a: VId { val: a },
-                                b: VId { val: b },
-                                c: VId { val: c },
-                            };
-                            material_info
-                                .surfaces
-                                .get_mut(name)
-                                .unwrap()
-                                .faces
-                                .insert(face.clone());
-                            mesh.try_add_connection(face.a, face.b, face.c)
-                                .or(Err(PlyError::InvalidMeshIndices(Some(*i_line))))?;
-                            idx_to_face.insert(*i_line, face);
-                        }
-                    } else {
-                        return Err(PlyError::LineParse(*i_line));
-                    }
-                } else if format.name == "texcoord" {
-                    match words.next() {
-                        Some(word) => {
-                            // We expect 6 elements. Otherwise we don't
-                            // know how to handle this format of texcoord
-                            if word != b"6" {
-                                return Err(PlyError::LineParse(*i_line));
-                            }
-                        }
-                        None => {
-                            return Err(PlyError::LineParse(*i_line));
-                        }
-                    }
-                    let mut indices = vec![];
-                    for _ in 0..3 {
-                        indices.push(material_info.uv.len());
-                        let x = words
-                            .next()
-                            .and_then(|w| from_ascii(w))
-                            .ok_or(PlyError::LineParse(*i_line))?;
-
-                        let y = words
-                            .next()
-                            .and_then(|w| from_ascii(w))
-                            .ok_or(PlyError::LineParse(*i_line))?;
-                        let z = 1.0f64;
-                        material_info.uv.push_d(Point3D::new(x, y, z));
+                let element_count: usize = words
+                    .next()
+                    .and_then(from_ascii)
+                    .ok_or(Error::on_line(ErrorKind::LineParse, *i_line))?;
+                for _ in 0..element_count {
+                    let word = words
+                        .next()
+                        .ok_or(Error::on_line(ErrorKind::LineParse, *i_line))?;
+                    if format.name == "vertex_indices" {
+                        vids.push(
+                            from_ascii(word)
+                                .ok_or(Error::on_line(ErrorKind::LineParse, *i_line))?,
+                        );
+                    } else if format.name == "texcoord" {
+                        texcoords.push(
+                            from_ascii(word)
+                                .ok_or(Error::on_line(ErrorKind::LineParse, *i_line))?,
+                        );
                     }
-                    idx_to_face.insert(
-                        *i_line,
-                        Face3 {
-                            a: VId { val: indices[0] },
-                            b: VId { val: indices[1] },
-                            c: VId { val: indices[2] },
-                        },
-                    );
                 }
             }
-        }
-        // If we've read both a face and a uv, add a link
-        match idx_to_face.get(&i_line) {
-            Some(face) => match idx_to_uvs.get(&i_line) {
-                Some(uv_face) => {
-                    let surface = material_info.surfaces.get_mut(name).unwrap();
-                    surface.uvs.insert(face.clone(), uv_face.clone());
-                    // Remove it to save memory
-                    idx_to_face.remove(&i_line);
-                    idx_to_uvs.remove(&i_line);
-                }
-                None => {}
-            },
-            None => {}
+            skipped += add_polygon(mesh, &vids[..], &texcoords[..], material_info, name)
+                .map_err(|e| e.at_line(*i_line))?;
+            n_faces_read += 1;
         }
     }
 
     if header.n_vertices != mesh.num_vertices() {
-        return Err(PlyError::LoadVertexCountIncorrect);
+        return Err(ErrorKind::LoadVertexCountIncorrect.into());
+    }
+    if header.n_faces != n_faces_read {
+        return Err(ErrorKind::LoadFaceCountIncorrect.into());
     }
 
-    Ok(())
+    Ok(skipped)
 }
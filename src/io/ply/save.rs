@@ -24,14 +24,18 @@ OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
 use rust_3d::*;
 
-use super::types::*;
+use super::{
+    super::error::{ErrorKind, IoResult},
+    types::*,
+};
+use crate::io::MaterialInfo;
 
 use std::io::Write;
 
 //------------------------------------------------------------------------------
 
 /// Saves an IsMesh3D in the ASCII .ply file format
-pub fn save_ply_ascii<M, P, W>(write: &mut W, mesh: &M) -> PlyResult<()>
+pub fn save_ply_ascii<M, P, W>(write: &mut W, mesh: &M) -> IoResult<()>
 where
     M: IsMesh<P, Face3>,
     P: IsBuildable3D,
@@ -77,7 +81,7 @@ where
 //------------------------------------------------------------------------------
 
 /// Saves an IsMesh3D in the ASCII .ply file format with additional colors
-pub fn save_ply_ascii_colored<M, P, W>(write: &mut W, mesh: &M, colors: &Vec<Rgb>) -> PlyResult<()>
+pub fn save_ply_ascii_colored<M, P, W>(write: &mut W, mesh: &M, colors: &Vec<Rgb>) -> IoResult<()>
 where
     M: IsMesh<P, Face3>,
     P: IsBuildable3D,
@@ -87,7 +91,7 @@ where
     let n_faces = mesh.num_faces();
 
     if n_vertices != colors.len() {
-        return Err(PlyError::ColorArrayIncorrectLength);
+        return Err(ErrorKind::ColorArrayIncorrectLength.into());
     }
 
     let header = "ply\n".to_string()
@@ -145,7 +149,7 @@ where
 //------------------------------------------------------------------------------
 
 /// Saves an IsMesh3D in the binary .ply file format
-pub fn save_ply_binary<M, P, W>(write: &mut W, mesh: &M, precision: &Precision) -> PlyResult<()>
+pub fn save_ply_binary<M, P, W>(write: &mut W, mesh: &M, precision: &Precision) -> IoResult<()>
 where
     M: IsMesh<P, Face3>,
     P: IsBuildable3D,
@@ -227,7 +231,7 @@ pub fn save_ply_binary_colored<M, P, W>(
     mesh: &M,
     precision: &Precision,
     colors: &Vec<Rgb>,
-) -> PlyResult<()>
+) -> IoResult<()>
 where
     M: IsMesh<P, Face3>,
     P: IsBuildable3D,
@@ -237,7 +241,7 @@ where
     let n_faces = mesh.num_faces();
 
     if n_vertices != colors.len() {
-        return Err(PlyError::ColorArrayIncorrectLength);
+        return Err(ErrorKind::ColorArrayIncorrectLength.into());
     }
 
     let header = match precision {
@@ -321,3 +325,142 @@ where
 
     Ok(())
 }
+
+//------------------------------------------------------------------------------
+
+/// Saves an IsMesh3D in the .ply file format, along with the per-wedge
+/// texture coordinates, vertex colors and vertex normals of
+/// `material_info`, where there are any. The texture is referred to
+/// with a `comment TextureFile` line, as done by VCGLib and Meshlab.
+pub fn save_ply_with_attributes<M, P, W>(
+    write: &mut W,
+    mesh: &M,
+    texture_name: Option<&str>,
+    material_info: &MaterialInfo,
+    format: Format,
+) -> IoResult<()>
+where
+    M: IsMesh<P, Face3>,
+    P: IsBuildable3D,
+    W: Write,
+{
+    let n_vertices = mesh.num_vertices();
+    let n_faces = mesh.num_faces();
+    let has_texcoords = material_info.uv.len() == 3 * n_faces && n_faces > 0;
+    let has_colors = material_info.colors.len() == n_vertices && n_vertices > 0;
+    let has_normals = material_info.normals.len() == n_vertices && n_vertices > 0;
+
+    let mut header = "ply\n".to_string();
+    header += match format {
+        Format::Ascii => "format ascii 1.0\n",
+        Format::LittleEndian => "format binary_little_endian 1.0\n",
+        Format::BigEndian => "format binary_big_endian 1.0\n",
+    };
+    header += "comment Created by web-geo-viewer\n";
+    if let Some(texture_name) = texture_name {
+        header += &format!("comment TextureFile {}\n", texture_name);
+    }
+    header += &format!("element vertex {}\n", n_vertices);
+    header += "property float x\nproperty float y\nproperty float z\n";
+    if has_normals {
+        header += "property float nx\nproperty float ny\nproperty float nz\n";
+    }
+    if has_colors {
+        header += "property uchar red\nproperty uchar green\nproperty uchar blue\n";
+    }
+    header += &format!("element face {}\n", n_faces);
+    header += "property list uchar int vertex_indices\n";
+    if has_texcoords {
+        header += "property list uchar float texcoord\n";
+    }
+    header += "end_header\n";
+    write.write_all(header.as_bytes())?;
+
+    for i in 0..n_vertices {
+        let vertex = mesh.vertex(VId { val: i }).unwrap(); // safe since iterating n_vertices
+        let mut values = vec![vertex.x() as f32, vertex.y() as f32, vertex.z() as f32];
+        if has_normals {
+            let normal = material_info.normals[i];
+            values.extend_from_slice(&[normal.x, normal.y, normal.z]);
+        }
+        match format {
+            Format::Ascii => {
+                let mut line = values
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                if has_colors {
+                    let color = to_rgb(&material_info.colors[i]);
+                    line += &format!(" {} {} {}", color[0], color[1], color[2]);
+                }
+                writeln!(write, "{}", line)?;
+            }
+            _ => {
+                for value in values {
+                    write_f32(write, value, format)?;
+                }
+                if has_colors {
+                    write.write_all(&to_rgb(&material_info.colors[i]))?;
+                }
+            }
+        }
+    }
+
+    for i in 0..n_faces {
+        let face = mesh.face_vertex_ids(FId { val: i }).unwrap(); // safe since iterating n_faces
+        let indices = [face.a.val as i32, face.b.val as i32, face.c.val as i32];
+        let mut texcoords = vec![];
+        if has_texcoords {
+            for corner in 0..3 {
+                let uv = material_info.uv.get_d(3 * i + corner);
+                texcoords.extend_from_slice(&[uv.x as f32, uv.y as f32]);
+            }
+        }
+        match format {
+            Format::Ascii => {
+                let mut line = format!("3 {} {} {}", indices[0], indices[1], indices[2]);
+                if has_texcoords {
+                    line += " 6";
+                    for texcoord in texcoords {
+                        line += &format!(" {}", texcoord);
+                    }
+                }
+                writeln!(write, "{}", line)?;
+            }
+            _ => {
+                write.write_all(&[3u8])?;
+                for index in indices.iter() {
+                    match format {
+                        Format::BigEndian => write.write_all(&index.to_be_bytes())?,
+                        _ => write.write_all(&index.to_le_bytes())?,
+                    }
+                }
+                if has_texcoords {
+                    write.write_all(&[6u8])?;
+                    for texcoord in texcoords {
+                        write_f32(write, texcoord, format)?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_f32<W>(write: &mut W, value: f32, format: Format) -> IoResult<()>
+where
+    W: Write,
+{
+    match format {
+        Format::BigEndian => write.write_all(&value.to_be_bytes())?,
+        _ => write.write_all(&value.to_le_bytes())?,
+    }
+    Ok(())
+}
+
+fn to_rgb(color: &three_d::Vec3) -> [u8; 3] {
+    let to_u8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [to_u8(color.x), to_u8(color.y), to_u8(color.z)]
+}
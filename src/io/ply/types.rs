@@ -24,11 +24,11 @@ OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
 use core::convert::TryFrom;
 
-use std::{fmt, io::Error as ioError};
+use super::super::error::{Error, ErrorKind, IoResult};
 
 //------------------------------------------------------------------------------
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub enum Type {
     Char,
     UChar,
@@ -54,12 +54,27 @@ impl Type {
             Self::Double => 8,
         }
     }
+
+    /// Value representing full intensity when a color channel is stored with this type.
+    #[inline(always)]
+    pub fn color_max(&self) -> f64 {
+        match self {
+            Self::Char => i8::MAX as f64,
+            Self::UChar => u8::MAX as f64,
+            Self::Short => i16::MAX as f64,
+            Self::UShort => u16::MAX as f64,
+            Self::Int => i32::MAX as f64,
+            Self::UInt => u32::MAX as f64,
+            Self::Float => 1.0,
+            Self::Double => 1.0,
+        }
+    }
 }
 
 impl TryFrom<&[u8]> for Type {
-    type Error = PlyError;
+    type Error = Error;
 
-    fn try_from(x: &[u8]) -> PlyResult<Self> {
+    fn try_from(x: &[u8]) -> IoResult<Self> {
         match x {
             b"char" => Ok(Self::Char),
             b"uchar" => Ok(Self::UChar),
@@ -74,9 +89,9 @@ impl TryFrom<&[u8]> for Type {
             b"uint" => Ok(Self::UInt),
             b"float" | b"float32" => Ok(Self::Float),
             b"double" | b"float64" => Ok(Self::Double),
-            _ => Err(PlyError::InvalidType(
-                std::str::from_utf8(x).unwrap_or("").to_string(),
-            )),
+            _ => {
+                Err(ErrorKind::InvalidType(std::str::from_utf8(x).unwrap_or("").to_string()).into())
+            }
         }
     }
 }
@@ -103,9 +118,9 @@ pub enum VertexOrder {
 }
 
 impl TryFrom<[Xyz; 3]> for VertexOrder {
-    type Error = PlyError;
+    type Error = Error;
 
-    fn try_from(x: [Xyz; 3]) -> PlyResult<Self> {
+    fn try_from(x: [Xyz; 3]) -> IoResult<Self> {
         match x {
             [Xyz::X, Xyz::Y, Xyz::Z] => Ok(Self::Xyz),
             [Xyz::X, Xyz::Z, Xyz::Y] => Ok(Self::Xzy),
@@ -113,7 +128,7 @@ impl TryFrom<[Xyz; 3]> for VertexOrder {
             [Xyz::Y, Xyz::Z, Xyz::X] => Ok(Self::Yzx),
             [Xyz::Z, Xyz::X, Xyz::Y] => Ok(Self::Zxy),
             [Xyz::Z, Xyz::Y, Xyz::X] => Ok(Self::Zyx),
-            _ => Err(PlyError::InvalidVertexDimensionDefinition),
+            _ => Err(ErrorKind::InvalidVertexDimensionDefinition.into()),
         }
     }
 }
@@ -128,6 +143,42 @@ pub struct BytesWords {
 
 //------------------------------------------------------------------------------
 
+/// Vertex properties following the position that we know how to use.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VertexAttribute {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    NormalX,
+    NormalY,
+    NormalZ,
+    Unknown,
+}
+
+impl From<&[u8]> for VertexAttribute {
+    fn from(x: &[u8]) -> Self {
+        match x {
+            b"red" | b"r" | b"diffuse_red" => Self::Red,
+            b"green" | b"g" | b"diffuse_green" => Self::Green,
+            b"blue" | b"b" | b"diffuse_blue" => Self::Blue,
+            b"alpha" | b"a" => Self::Alpha,
+            b"nx" | b"normal_x" => Self::NormalX,
+            b"ny" | b"normal_y" => Self::NormalY,
+            b"nz" | b"normal_z" => Self::NormalZ,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct VertexProperty {
+    pub attribute: VertexAttribute,
+    pub t: Type,
+}
+
+//------------------------------------------------------------------------------
+
 #[derive(Copy, Clone, Debug)]
 pub enum VertexType {
     Float,
@@ -135,13 +186,13 @@ pub enum VertexType {
 }
 
 impl TryFrom<Type> for VertexType {
-    type Error = PlyError;
+    type Error = Error;
 
-    fn try_from(x: Type) -> PlyResult<Self> {
+    fn try_from(x: Type) -> IoResult<Self> {
         match x {
             Type::Float => Ok(Self::Float),
             Type::Double => Ok(Self::Double),
-            _ => Err(PlyError::InvalidVertexType),
+            _ => Err(ErrorKind::InvalidVertexType.into()),
         }
     }
 }
@@ -161,9 +212,9 @@ pub enum FaceType {
 }
 
 impl TryFrom<Type> for FaceType {
-    type Error = PlyError;
+    type Error = Error;
 
-    fn try_from(x: Type) -> PlyResult<Self> {
+    fn try_from(x: Type) -> IoResult<Self> {
         match x {
             Type::Char => Ok(Self::Char),
             Type::UChar => Ok(Self::UChar),
@@ -188,7 +239,27 @@ pub struct VertexFormat {
     pub before: BytesWords,
     pub between_first_snd: BytesWords,
     pub between_snd_third: BytesWords,
-    pub after: BytesWords,
+    pub after: Vec<VertexProperty>,
+}
+
+impl VertexFormat {
+    /// Whether the vertices carry a color.
+    pub fn has_color(&self) -> bool {
+        self.after.iter().any(|p| {
+            p.attribute == VertexAttribute::Red
+                || p.attribute == VertexAttribute::Green
+                || p.attribute == VertexAttribute::Blue
+        })
+    }
+
+    /// Whether the vertices carry a normal.
+    pub fn has_normal(&self) -> bool {
+        self.after.iter().any(|p| {
+            p.attribute == VertexAttribute::NormalX
+                || p.attribute == VertexAttribute::NormalY
+                || p.attribute == VertexAttribute::NormalZ
+        })
+    }
 }
 
 //------------------------------------------------------------------------------
@@ -229,84 +300,3 @@ pub struct Header {
     pub face_format: Vec<FaceFormat>,
     pub texture_name: Option<String>,
 }
-
-//------------------------------------------------------------------------------
-
-/// Error type for .ply file operations
-pub enum PlyError {
-    LoadStartNotFound,
-    LoadFormatNotFound,
-    LoadWrongPropertyCount,
-    LoadVertexIndexDefinitionNotFound,
-    LoadHeaderInvalid,
-    LoadVertexCountNotFound,
-    LoadFaceCountNotFound,
-    LoadVertexCountIncorrect,
-    AccessFile,
-    ColorArrayIncorrectLength,
-    //@todo these all could name the affected line
-    InvalidType(String),
-    InvalidVertexType, //@todo would be better to name the issue
-    InvalidFaceType,   //@todo would be better to name the issue
-    InvalidMeshIndices(Option<usize>),
-    LineParse(usize),
-    InvalidProperty(usize),
-    InvalidVertex(usize),
-    PropertyLineLocation(usize),
-    FaceStructure,
-    InvalidVertexDimensionDefinition,
-}
-
-/// Result type for .ply file operations
-pub type PlyResult<T> = std::result::Result<T, PlyError>;
-
-impl fmt::Debug for PlyError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::LoadStartNotFound => write!(f, "Start of .ply header not found"),
-            Self::LoadFormatNotFound => write!(f, "Format of .ply missing or not supported"),
-            Self::LoadWrongPropertyCount => {
-                write!(f, "Property count of .ply missing or not supported")
-            }
-            Self::LoadVertexIndexDefinitionNotFound => {
-                write!(f, "Index definition in .ply not found")
-            }
-            Self::LoadHeaderInvalid => write!(f, "Header of .ply seems to be invalid"),
-            Self::LoadVertexCountNotFound => write!(f, "Vertex count of .ply not found"),
-            Self::LoadFaceCountNotFound => write!(f, "Face count of .ply not found"),
-            Self::LoadVertexCountIncorrect => write!(f, "Vertex count of .ply not found"),
-            Self::ColorArrayIncorrectLength => {
-                write!(f, "The provided color array has an incorrect length")
-            }
-            Self::InvalidType(x) => write!(f, "Invalid type in header '{}'", x),
-            Self::InvalidVertexType => write!(f, "Invalid vertex type in header"),
-            Self::InvalidFaceType => write!(f, "Invalid face type in header"),
-            Self::LineParse(x) => write!(f, "Unable to parse line {}", x),
-            Self::AccessFile => write!(f, "Unable to access file"),
-            Self::InvalidMeshIndices(opt_x) => match opt_x {
-                Some(x) => write!(f, "File contains invalid mesh indices on line {}", x),
-                None => write!(f, "File contains invalid mesh indices"),
-            },
-            Self::InvalidProperty(x) => write!(f, "Invalid property on line {}", x),
-            Self::InvalidVertex(x) => write!(f, "Invalid vertex definition on line {}", x),
-            Self::InvalidVertexDimensionDefinition => {
-                write!(f, "Invalid order / definition of vertex dimension order")
-            }
-            Self::PropertyLineLocation(x) => write!(
-                f,
-                "Found property line at unexpected location on line {}",
-                x
-            ),
-            Self::FaceStructure => write!(
-                f,
-                "Invalid face structure, only supporting 3 vertices per face"
-            ),
-        }
-    }
-}
-
-impl From<ioError> for PlyError {
-    fn from(_error: ioError) -> Self {
-        PlyError::AccessFile
-    }
-}
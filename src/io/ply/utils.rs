@@ -24,14 +24,20 @@ OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
 use rust_3d::*;
 
-use super::{super::byte_reader::*, types::*};
+use super::{
+    super::{
+        byte_reader::*,
+        error::{ErrorKind, IoResult},
+    },
+    types::*,
+};
 
 use std::io::Read;
 
 //------------------------------------------------------------------------------
 
 #[inline(always)]
-pub fn read_face_type<BR, R>(read: &mut R, t: FaceType) -> PlyResult<usize>
+pub fn read_face_type<BR, R>(read: &mut R, t: FaceType) -> IoResult<usize>
 where
     BR: IsByteReader,
     R: Read,
@@ -43,14 +49,14 @@ where
         FaceType::UShort => Ok(BR::read_u16(read)? as usize),
         FaceType::Int => Ok(BR::read_i32(read)? as usize),
         FaceType::UInt => Ok(BR::read_u32(read)? as usize),
-        _ => Err(PlyError::InvalidFaceType),
+        _ => Err(ErrorKind::InvalidFaceType.into()),
     }
 }
 
 //------------------------------------------------------------------------------
 
 #[inline(always)]
-pub fn read_vertex_type<BR, R>(read: &mut R, t: VertexType) -> PlyResult<f64>
+pub fn read_vertex_type<BR, R>(read: &mut R, t: VertexType) -> IoResult<f64>
 where
     BR: IsByteReader,
     R: Read,
@@ -63,6 +69,53 @@ where
 
 //------------------------------------------------------------------------------
 
+#[inline(always)]
+pub fn read_type<BR, R>(read: &mut R, t: Type) -> IoResult<f64>
+where
+    BR: IsByteReader,
+    R: Read,
+{
+    Ok(match t {
+        Type::Char => BR::read_i8(read)? as f64,
+        Type::UChar => BR::read_u8(read)? as f64,
+        Type::Short => BR::read_i16(read)? as f64,
+        Type::UShort => BR::read_u16(read)? as f64,
+        Type::Int => BR::read_i32(read)? as f64,
+        Type::UInt => BR::read_u32(read)? as f64,
+        Type::Float => BR::read_f32(read)? as f64,
+        Type::Double => BR::read_f64(read)?,
+    })
+}
+
+//------------------------------------------------------------------------------
+
+/// Stores a color channel of a vertex, normalized to [0, 1]. Alpha is ignored.
+#[inline(always)]
+pub fn add_color_channel(color: &mut three_d::Vec3, property: &VertexProperty, value: f64) {
+    let channel = (value / property.t.color_max()) as f32;
+    match property.attribute {
+        VertexAttribute::Red => color.x = channel,
+        VertexAttribute::Green => color.y = channel,
+        VertexAttribute::Blue => color.z = channel,
+        _ => {}
+    }
+}
+
+//------------------------------------------------------------------------------
+
+/// Stores a component of a vertex normal.
+#[inline(always)]
+pub fn add_normal_component(normal: &mut three_d::Vec3, property: &VertexProperty, value: f64) {
+    match property.attribute {
+        VertexAttribute::NormalX => normal.x = value as f32,
+        VertexAttribute::NormalY => normal.y = value as f32,
+        VertexAttribute::NormalZ => normal.z = value as f32,
+        _ => {}
+    }
+}
+
+//------------------------------------------------------------------------------
+
 #[inline(always)]
 pub fn point_with_order<P>(fst: f64, snd: f64, third: f64, order: VertexOrder) -> P
 where
@@ -0,0 +1,220 @@
+//! Module for the scene that every file format is loaded into
+
+use rust_3d::*;
+use std::collections::HashMap;
+
+use super::{Material, MaterialInfo, MaterialSurface};
+
+//------------------------------------------------------------------------------
+
+/// The mesh type that scenes hold, unless loaded with a specific one.
+pub type SceneMesh3D = Mesh3D<Point3D, PointCloud3D<Point3D>, Vec<usize>>;
+
+/// A mesh of a scene, with its surfaces in `material_info`.
+pub struct SceneMesh<EM> {
+    /// Unique name of the mesh.
+    pub name: String,
+    pub mesh: EM,
+    pub material_info: MaterialInfo,
+    /// Column-major model matrix to place the mesh with.
+    pub transform: [[f32; 4]; 4],
+}
+
+/// Points of a scene. Colors and normals are either empty or hold one
+/// entry per point.
+pub struct ScenePointCloud {
+    pub name: String,
+    pub points: PointCloud3D<Point3D>,
+    pub colors: Vec<three_d::Vec3>,
+    pub normals: Vec<three_d::Vec3>,
+}
+
+/// Everything loaded from a file. Most files only fill in some of it,
+/// for example an image file only has an image.
+pub struct Scene<EM> {
+    pub meshes: Vec<SceneMesh<EM>>,
+    pub point_clouds: Vec<ScenePointCloud>,
+    /// Materials by the names the surfaces refer to.
+    pub materials: HashMap<String, Material>,
+    /// Decoded images by the texture names the materials refer to.
+    pub images: HashMap<String, image::DynamicImage>,
+    /// Raw files that other files may refer to, or that are needed
+    /// to export, by name.
+    pub resources: HashMap<String, Vec<u8>>,
+    /// Problems that didn't stop the file from loading, other than
+    /// those of the meshes.
+    pub warnings: Vec<String>,
+}
+
+impl<EM> Scene<EM> {
+    pub fn new() -> Scene<EM> {
+        Scene {
+            meshes: vec![],
+            point_clouds: vec![],
+            materials: HashMap::new(),
+            images: HashMap::new(),
+            resources: HashMap::new(),
+            warnings: vec![],
+        }
+    }
+}
+
+impl<EM> Default for Scene<EM> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<EM> Scene<EM>
+where
+    EM: IsFaceEditableMesh<Point3D, Face3> + IsVertexEditableMesh<Point3D, Face3> + Default,
+{
+    /// Adds a loaded mesh, in its own frame. Meshes without faces are
+    /// added as point clouds, using the colors and normals of the mesh,
+    /// unless they have no vertices either. Meshes with several parts
+    /// are split into one mesh per part, named "{name}/{part}".
+    pub fn add_mesh(&mut self, name: &str, mesh: EM, mut material_info: MaterialInfo) {
+        if material_info.parts.len() > 1 {
+            self.warnings.append(&mut material_info.warnings);
+            for part in material_info.parts.iter() {
+                let (part_mesh, part_info) = split_part(&mesh, &material_info, &part.faces);
+                self.add_mesh(&format!("{}/{}", name, part.name), part_mesh, part_info);
+            }
+            return;
+        }
+        if mesh.num_faces() > 0 {
+            self.meshes.push(SceneMesh {
+                name: name.to_string(),
+                mesh,
+                material_info,
+                transform: IDENTITY,
+            });
+            return;
+        }
+        self.warnings.append(&mut material_info.warnings);
+        if mesh.num_vertices() == 0 {
+            return;
+        }
+        let mut points = PointCloud3D::new();
+        for vid in 0..mesh.num_vertices() {
+            if let Ok(vertex) = mesh.vertex(VId { val: vid }) {
+                points.push(vertex);
+            }
+        }
+        self.point_clouds.push(ScenePointCloud {
+            name: name.to_string(),
+            points,
+            colors: material_info.colors,
+            normals: material_info.normals,
+        });
+    }
+}
+
+/// Copies the given faces of a mesh into a mesh of their own, along with
+/// the vertices, attributes and surfaces they use.
+fn split_part<EM>(mesh: &EM, material_info: &MaterialInfo, faces: &[usize]) -> (EM, MaterialInfo)
+where
+    EM: IsFaceEditableMesh<Point3D, Face3> + IsVertexEditableMesh<Point3D, Face3> + Default,
+{
+    let mut part_mesh = EM::default();
+    let mut part_info = MaterialInfo::new();
+    part_info.material_libs = material_info.material_libs.clone();
+    let n_wedges = 3 * mesh.num_faces();
+    let wedge_uvs = material_info.uv.len() == n_wedges;
+    let wedge_normals = material_info.normals.len() == n_wedges;
+    let wedge_colors = material_info.colors.len() == n_wedges;
+    // New vertex ids by old ones, and old ones in the order of the new.
+    let mut vids: HashMap<VId, VId> = HashMap::new();
+    let mut old_vids: Vec<usize> = vec![];
+    'faces: for &fid in faces {
+        let face = match mesh.face_vertex_ids(FId { val: fid }) {
+            Ok(face) => face,
+            Err(_) => continue,
+        };
+        let mut corners = [VId { val: 0 }; 3];
+        for (corner, vid) in corners.iter_mut().zip([face.a, face.b, face.c].iter()) {
+            *corner = match vids.get(vid) {
+                Some(new_vid) => *new_vid,
+                None => match mesh.vertex(*vid) {
+                    Ok(vertex) => {
+                        let new_vid = part_mesh.add_vertex(vertex);
+                        vids.insert(*vid, new_vid);
+                        old_vids.push(vid.val);
+                        new_vid
+                    }
+                    Err(_) => continue 'faces,
+                },
+            };
+        }
+        let new_fid = match part_mesh.try_add_connection(corners[0], corners[1], corners[2]) {
+            Ok(new_fid) => new_fid,
+            Err(_) => continue,
+        };
+        let new_face = Face3::new(corners[0], corners[1], corners[2]);
+        for wedge in 3 * fid..3 * fid + 3 {
+            if wedge_uvs {
+                part_info.uv.push_d(material_info.uv.get_d(wedge));
+            }
+            if wedge_normals {
+                part_info.normals.push(material_info.normals[wedge]);
+            }
+            if wedge_colors {
+                part_info.colors.push(material_info.colors[wedge]);
+            }
+        }
+        for (surface_name, surface) in material_info.surfaces.iter() {
+            if !surface.faces.contains(&face) {
+                continue;
+            }
+            let part_surface = part_info
+                .surfaces
+                .entry(surface_name.clone())
+                .or_insert_with(|| MaterialSurface {
+                    material: surface.material.clone(),
+                    ..MaterialSurface::new()
+                });
+            part_surface.faces.insert(new_face.clone());
+            if surface.uvs.contains_key(&face) {
+                let uv_face = if wedge_uvs {
+                    let wedge = 3 * new_fid.val;
+                    Face3::new(
+                        VId { val: wedge },
+                        VId { val: wedge + 1 },
+                        VId { val: wedge + 2 },
+                    )
+                } else {
+                    new_face.clone()
+                };
+                part_surface.uvs.insert(new_face.clone(), uv_face);
+            }
+        }
+    }
+    // Attributes per vertex are only kept if there is one for every
+    // vertex of the part.
+    if !wedge_uvs {
+        if let Some(uvs) = remap(&material_info.uv.data, &old_vids) {
+            part_info.uv.data = uvs;
+        }
+    }
+    if !wedge_normals {
+        part_info.normals = remap(&material_info.normals, &old_vids).unwrap_or_default();
+    }
+    if !wedge_colors {
+        part_info.colors = remap(&material_info.colors, &old_vids).unwrap_or_default();
+    }
+    (part_mesh, part_info)
+}
+
+fn remap<T: Clone>(values: &[T], indices: &[usize]) -> Option<Vec<T>> {
+    if values.is_empty() {
+        return None;
+    }
+    indices.iter().map(|i| values.get(*i).cloned()).collect()
+}
+
+pub const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
@@ -0,0 +1,101 @@
+//! Module for IO of the stl file format
+
+use std::io::Write;
+
+use three_d::core::types::InnerSpace;
+use three_d::{vec4, Mat4, Vec3};
+
+use super::error::{Error, ErrorKind, IoResult};
+
+//------------------------------------------------------------------------------
+
+/// Saves triangles in the ASCII .stl file format. Positions are a flat
+/// array with a stride of 3 and indices hold three vertices per
+/// facet. The transform is applied to every vertex, so it can be
+/// used to place the mesh and to convert it to other units.
+pub fn save_stl_ascii<W>(
+    write: &mut W,
+    name: &str,
+    positions: &[f32],
+    indices: &[u32],
+    transform: &Mat4,
+) -> IoResult<()>
+where
+    W: Write,
+{
+    writeln!(write, "solid {}", name)?;
+    // Lines aren't indented, since some readers (rust-3d among them)
+    // don't expect leading whitespace.
+    for (i_facet, facet) in indices.chunks_exact(3).enumerate() {
+        let (normal, corners) = facet_geometry(positions, facet, transform, i_facet)?;
+        writeln!(
+            write,
+            "facet normal {:e} {:e} {:e}",
+            normal.x, normal.y, normal.z
+        )?;
+        writeln!(write, "outer loop")?;
+        for corner in corners.iter() {
+            writeln!(write, "vertex {:e} {:e} {:e}", corner.x, corner.y, corner.z)?;
+        }
+        writeln!(write, "endloop")?;
+        writeln!(write, "endfacet")?;
+    }
+    writeln!(write, "endsolid {}", name)?;
+    Ok(())
+}
+
+/// Saves triangles in the binary .stl file format. See
+/// `save_stl_ascii` for the arguments.
+pub fn save_stl_binary<W>(
+    write: &mut W,
+    positions: &[f32],
+    indices: &[u32],
+    transform: &Mat4,
+) -> IoResult<()>
+where
+    W: Write,
+{
+    let mut header = [0u8; 80];
+    let comment = b"Created by web-geo-viewer";
+    header[..comment.len()].copy_from_slice(comment);
+    write.write_all(&header)?;
+    write.write_all(&((indices.len() / 3) as u32).to_le_bytes())?;
+    for (i_facet, facet) in indices.chunks_exact(3).enumerate() {
+        let (normal, corners) = facet_geometry(positions, facet, transform, i_facet)?;
+        for vector in std::iter::once(&normal).chain(corners.iter()) {
+            write.write_all(&vector.x.to_le_bytes())?;
+            write.write_all(&vector.y.to_le_bytes())?;
+            write.write_all(&vector.z.to_le_bytes())?;
+        }
+        // Attribute byte count, which is unused.
+        write.write_all(&0u16.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+//------------------------------------------------------------------------------
+
+/// The transformed corners of a facet and its normal. Degenerate
+/// facets get a zero normal, which readers are expected to handle.
+fn facet_geometry(
+    positions: &[f32],
+    facet: &[u32],
+    transform: &Mat4,
+    i_facet: usize,
+) -> IoResult<(Vec3, [Vec3; 3])> {
+    let mut corners = [Vec3::new(0.0, 0.0, 0.0); 3];
+    for (corner, index) in corners.iter_mut().zip(facet.iter()) {
+        let start = 3 * *index as usize;
+        if start + 3 > positions.len() {
+            return Err(Error::new(ErrorKind::InvalidMeshIndices).at_element("facet", i_facet));
+        }
+        let p = &positions[start..start + 3];
+        *corner = (transform * vec4(p[0], p[1], p[2], 1.0)).truncate();
+    }
+    let normal = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+    if normal.magnitude2() > 0.0 {
+        Ok((normal.normalize(), corners))
+    } else {
+        Ok((Vec3::new(0.0, 0.0, 0.0), corners))
+    }
+}
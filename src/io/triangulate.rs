@@ -0,0 +1,136 @@
+//! Module for splitting polygonal faces into triangles
+
+use rust_3d::*;
+
+//------------------------------------------------------------------------------
+
+/// Splits the polygon with the given vertex ids of a mesh into
+/// triangles. The returned triangles index into `vids`, so that
+/// per-corner data such as texture coordinates can be carried along.
+/// Returns None if any vertex id is not part of the mesh.
+pub fn triangulate_mesh_polygon<M, P>(mesh: &M, vids: &[usize]) -> Option<Vec<[usize; 3]>>
+where
+    M: IsMesh<P, Face3>,
+    P: Is3D,
+{
+    let mut positions = vec![];
+    for vid in vids.iter() {
+        let p = mesh.vertex(VId { val: *vid }).ok()?;
+        positions.push([p.x(), p.y(), p.z()]);
+    }
+    Some(triangulate(&positions[..]))
+}
+
+/// Splits a polygon into triangles by ear clipping. The polygon is
+/// projected onto the plane that it is most aligned with, so concave
+/// polygons are handled as long as they are roughly planar. The
+/// triangles keep the winding of the polygon and index into
+/// `positions`. Convex polygons result in a fan around the first
+/// corner.
+pub fn triangulate(positions: &[[f64; 3]]) -> Vec<[usize; 3]> {
+    let n = positions.len();
+    if n < 3 {
+        return vec![];
+    }
+    if n == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    // Newell's method gives a robust normal for non-planar polygons.
+    let mut normal = [0.0f64; 3];
+    for i in 0..n {
+        let p = positions[i];
+        let q = positions[(i + 1) % n];
+        normal[0] += (p[1] - q[1]) * (p[2] + q[2]);
+        normal[1] += (p[2] - q[2]) * (p[0] + q[0]);
+        normal[2] += (p[0] - q[0]) * (p[1] + q[1]);
+    }
+    let (u, v, sign) = projection_axes(normal);
+    if sign == 0.0 {
+        // Degenerate polygon, there is nothing to clip.
+        return fan(&(0..n).collect::<Vec<usize>>()[..]);
+    }
+    let points: Vec<[f64; 2]> = positions.iter().map(|p| [p[u], sign * p[v]]).collect();
+
+    let mut remaining: Vec<usize> = (0..n).collect();
+    let mut triangles = vec![];
+    while remaining.len() > 3 {
+        let m = remaining.len();
+        let mut ear = None;
+        // Starting at the second corner yields a fan for convex polygons.
+        for k in 1..=m {
+            let i = k % m;
+            let prev = remaining[(i + m - 1) % m];
+            let cur = remaining[i];
+            let next = remaining[(i + 1) % m];
+            if is_ear(&points[..], &remaining[..], prev, cur, next) {
+                ear = Some(i);
+                break;
+            }
+        }
+        match ear {
+            Some(i) => {
+                triangles.push([
+                    remaining[(i + m - 1) % m],
+                    remaining[i],
+                    remaining[(i + 1) % m],
+                ]);
+                remaining.remove(i);
+            }
+            None => {
+                // Self-intersecting polygons may not have any ears left.
+                triangles.extend(fan(&remaining[..]));
+                return triangles;
+            }
+        }
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+//------------------------------------------------------------------------------
+
+/// The two axes to project onto, and the sign to apply to the second
+/// one so that the projected polygon is counter-clockwise.
+fn projection_axes(normal: [f64; 3]) -> (usize, usize, f64) {
+    let abs = [normal[0].abs(), normal[1].abs(), normal[2].abs()];
+    let (u, v, dominant) = if abs[0] >= abs[1] && abs[0] >= abs[2] {
+        (1, 2, normal[0])
+    } else if abs[1] >= abs[2] {
+        (2, 0, normal[1])
+    } else {
+        (0, 1, normal[2])
+    };
+    if dominant > 0.0 {
+        (u, v, 1.0)
+    } else if dominant < 0.0 {
+        (u, v, -1.0)
+    } else {
+        (u, v, 0.0)
+    }
+}
+
+fn cross(o: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+fn is_ear(points: &[[f64; 2]], remaining: &[usize], prev: usize, cur: usize, next: usize) -> bool {
+    let (a, b, c) = (points[prev], points[cur], points[next]);
+    if cross(a, b, c) <= 0.0 {
+        // Reflex or degenerate corner.
+        return false;
+    }
+    !remaining.iter().any(|&other| {
+        if other == prev || other == cur || other == next {
+            return false;
+        }
+        let p = points[other];
+        cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+    })
+}
+
+fn fan(corners: &[usize]) -> Vec<[usize; 3]> {
+    (1..corners.len().saturating_sub(1))
+        .map(|i| [corners[0], corners[i], corners[i + 1]])
+        .collect()
+}
@@ -23,13 +23,18 @@ OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 //! Module for interal utility functions for IO operations
 
 use std::{
-    fmt,
-    io::{BufRead, Error as ioError, Read},
+    io::{BufRead, Read},
     str::FromStr,
 };
 
 //------------------------------------------------------------------------------
 
+/// Most elements to reserve memory for up front. Counts in headers can't
+/// be trusted, a damaged file could ask for more memory than there is.
+pub const MAX_RESERVE: usize = 1 << 20;
+
+//------------------------------------------------------------------------------
+
 /// Skip number of bytes
 #[inline(always)]
 pub fn skip_bytes<R>(read: &mut R, n_bytes: usize) -> std::io::Result<()>
@@ -72,16 +77,19 @@ where
 
 //------------------------------------------------------------------------------
 
-/// Fetch a single line
+/// Fetch a single line. Returns None at the end of the input.
 #[inline(always)]
-pub fn fetch_line<'a, R>(read: &mut R, line_buffer: &'a mut Vec<u8>) -> FetchLineResult<&'a [u8]>
+pub fn fetch_line<'a, R>(
+    read: &mut R,
+    line_buffer: &'a mut Vec<u8>,
+) -> std::io::Result<Option<&'a [u8]>>
 where
     R: BufRead,
 {
     line_buffer.clear();
     let n_read = read.read_until(b'\n', line_buffer)?;
     if n_read == 0 {
-        return Err(FetchLineError);
+        return Ok(None);
     }
 
     // We must drop the '\n' we read_until for sure
@@ -95,25 +103,46 @@ where
         }
     }
 
-    Ok(&line_buffer[0..line_buffer.len() - ignore_end])
+    Ok(Some(&line_buffer[0..line_buffer.len() - ignore_end]))
 }
 
 //------------------------------------------------------------------------------
 
-/// Error type for the fetch_line function
-pub struct FetchLineError;
+/// Reader that counts the bytes read through it, to tell where in a
+/// file an error is.
+pub struct ByteCounter<R> {
+    read: R,
+    /// Number of bytes read so far.
+    pub offset: u64,
+}
 
-/// Result type for the fetch_line function
-pub type FetchLineResult<T> = std::result::Result<T, FetchLineError>;
+impl<R> ByteCounter<R> {
+    pub fn new(read: R) -> ByteCounter<R> {
+        ByteCounter { read, offset: 0 }
+    }
+}
 
-impl fmt::Debug for FetchLineError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Unable to fetch line")
+impl<R> Read for ByteCounter<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n_read = self.read.read(buf)?;
+        self.offset += n_read as u64;
+        Ok(n_read)
     }
 }
 
-impl From<ioError> for FetchLineError {
-    fn from(_error: ioError) -> Self {
-        FetchLineError {}
+impl<R> BufRead for ByteCounter<R>
+where
+    R: BufRead,
+{
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.read.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.read.consume(amt);
+        self.offset += amt as u64;
     }
 }
@@ -0,0 +1,55 @@
+//! Module for IO of the xyz file format
+
+use rust_3d::*;
+
+use std::io::BufRead;
+
+use super::{
+    error::{Error, ErrorKind, IoResult},
+    utils::*,
+};
+
+//------------------------------------------------------------------------------
+
+/// Loads IsPushable<Is3D> from the .xyz file format. Each line holds
+/// the x, y and z coordinates of a point. Any further columns, such
+/// as colors or normals, are ignored.
+pub fn load_xyz_points<IP, P, R>(read: &mut R, ip: &mut IP) -> IoResult<()>
+where
+    IP: IsPushable<P>,
+    P: IsBuildable3D,
+    R: BufRead,
+{
+    let mut line_buffer = Vec::new();
+    let mut i_line = 0;
+
+    while let Some(line) = fetch_line(read, &mut line_buffer)? {
+        i_line += 1;
+
+        if line.is_empty() || line.starts_with(b"#") || line.starts_with(b"//") {
+            continue;
+        }
+
+        let mut words = to_words_skip_empty(line);
+
+        let x = match words.next() {
+            Some(word) => from_ascii(word).ok_or(Error::on_line(ErrorKind::LineParse, i_line))?,
+            // Whitespace only
+            None => continue,
+        };
+
+        let y = words
+            .next()
+            .and_then(from_ascii)
+            .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
+
+        let z = words
+            .next()
+            .and_then(from_ascii)
+            .ok_or(Error::on_line(ErrorKind::LineParse, i_line))?;
+
+        ip.push(P::new(x, y, z));
+    }
+
+    Ok(())
+}
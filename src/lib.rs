@@ -1,18 +1,28 @@
 #![recursion_limit = "256"]
 
 use io::Material;
-use std::collections::HashMap;
-use three_d::{Camera, DeferredPipeline, Gl};
+use std::collections::{HashMap, HashSet};
+use three_d::{Camera, Gl};
 use web_sys::HtmlCanvasElement;
 use yew::prelude::*;
 use yew::services::reader::{File, FileData, ReaderService, ReaderTask};
 use yew::services::Task;
 use yew::{html, Component, ComponentLink, Html, NodeRef, ShouldRender};
 
+mod bounds;
+mod deferred_renderer;
+mod diagnostics;
+mod edge_renderer;
+mod environment;
 mod input_controller;
 pub mod io;
+mod light_rig;
+mod mesh_exporter;
 mod mesh_list;
 mod mesh_loader;
+mod origin;
+mod pbr_mesh_renderer;
+mod point_cloud_renderer;
 mod render_buffers;
 mod render_canvas;
 mod tests;
@@ -28,16 +38,89 @@ pub struct MeshContainer {
 
     /// Show the mesh?
     pub visible: bool,
+
+    /// Transform of the mesh in its file.
+    pub transform: three_d::Mat4,
+
+    /// Point of the file that the vertices are relative to, before the
+    /// transform, so that they keep their precision as f32.
+    pub origin: origin::Point64,
+
+    /// Model matrix to render the mesh with, relative to the origin of
+    /// the scene.
+    pub model_matrix: three_d::Mat4,
+
+    /// Bounds of the vertices, relative to the origin.
+    pub bounds: bounds::BoundingBox,
+
+    /// Multiplies the opacity of the materials of the mesh.
+    pub opacity: f32,
+
+    /// Are the faces shaded, or their edges drawn, or both?
+    pub display_mode: edge_renderer::DisplayMode,
+
+    /// Name of the file the mesh was loaded from.
+    pub file: String,
+}
+
+pub struct PointCloudContainer {
+    /// Point info.
+    pub points: render_buffers::PointData,
+
+    /// Diameter of the rendered points, in pixels.
+    pub point_size: f32,
+
+    /// Bounds of the points, relative to the origin.
+    pub bounds: bounds::BoundingBox,
+
+    /// Point of the file that the points are relative to.
+    pub origin: origin::Point64,
+
+    /// Model matrix to render the points with, relative to the origin of
+    /// the scene.
+    pub model_matrix: three_d::Mat4,
+
+    /// Show the point cloud?
+    pub visible: bool,
+
+    /// Name of the file the point cloud was loaded from.
+    pub file: String,
 }
 
 pub struct Model {
     canvas: Option<HtmlCanvasElement>,
     gl: Option<Gl>,
     camera: Option<Camera>,
+    /// Near and far planes of the camera, and where it was last framed.
+    framing: bounds::Framing,
     mesh: HashMap<String, MeshContainer>,
+    /// Point of the files that everything is drawn relative to.
+    origin: origin::Point64,
+    point_clouds: HashMap<String, PointCloudContainer>,
     materials: HashMap<String, Material>,
     images: HashMap<String, three_d::texture::Texture2D>,
-    renderer: Option<DeferredPipeline>,
+    /// Images with pixels that aren't fully opaque.
+    translucent_images: HashSet<String>,
+    /// Files that other files refer to, such as glTF buffers.
+    resources: HashMap<String, Vec<u8>>,
+    /// Files waiting for resources that haven't been loaded yet.
+    pending_files: Vec<FileData>,
+    /// Formats that files can be loaded from.
+    formats: io::FormatRegistry,
+    /// What happened while loading each file.
+    diagnostics: diagnostics::Diagnostics,
+    stl_options: mesh_exporter::StlOptions,
+    /// Object URL of the last download, which is released by the next
+    /// one. Releasing it right away cancels the download in some
+    /// browsers.
+    download_url: Option<String>,
+    /// Files whose parts are hidden in the mesh list.
+    collapsed_files: HashSet<String>,
+    /// Lights of the scene, as the light panel sets them.
+    lights: deferred_renderer::Lights,
+    /// Light from an HDR image all around the scene.
+    environment: Option<environment::Environment>,
+    renderer: Option<deferred_renderer::DeferredRenderer>,
     link: ComponentLink<Self>,
     node_ref: NodeRef,
     render_loop: Option<Box<dyn Task>>,
@@ -48,6 +131,9 @@ pub struct Model {
     wheel_events: Vec<WheelEvent>,
     untextured_mesh_renderer: Option<untextured_mesh_renderer::UntexturedMeshRenderer>,
     textured_mesh_renderer: Option<textured_mesh_renderer::TexturedMeshRenderer>,
+    pbr_mesh_renderer: Option<pbr_mesh_renderer::PbrMeshRenderer>,
+    edge_renderer: Option<edge_renderer::EdgeRenderer>,
+    point_cloud_renderer: Option<point_cloud_renderer::PointCloudRenderer>,
 }
 
 pub enum Msg {
@@ -58,9 +144,34 @@ pub enum Msg {
     PointerMove(PointerEvent),
     PointerWheel(WheelEvent),
     MeshVisibilityToggle(String),
+    FileVisibilityToggle(String),
+    ToggleFileCollapsed(String),
+    IsolateMesh(String),
+    ShowAll,
+    FrameAll,
+    FrameMesh(String),
+    FrameFile(String),
+    PointSizeChanged(String, f32),
+    MeshOpacityChanged(String, f32),
+    MeshDisplayModeChanged(String, edge_renderer::DisplayMode),
     Render(f64),
     Loaded(FileData),
     RemoveMesh(String),
+    RemoveFile(String),
+    ExportMesh(String),
+    ExportStl(String),
+    StlOptionsChanged(mesh_exporter::StlOptions),
+    DismissDiagnostics(String),
+    DismissAllDiagnostics,
+    LightPresetChosen(light_rig::Preset),
+    AddLight(light_rig::Light),
+    RemoveLight(light_rig::LightId),
+    LightEdited(light_rig::LightId, light_rig::LightEdit),
+    EnvironmentFiles(Vec<File>),
+    EnvironmentLoaded(FileData),
+    EnvironmentIntensityChanged(f32),
+    ToggleEnvironmentBackground,
+    RemoveEnvironment,
     Files(Vec<File>),
 }
 
@@ -73,9 +184,22 @@ impl Component for Model {
             canvas: None,
             gl: None,
             camera: None,
+            framing: render_canvas::default_framing(),
             mesh: HashMap::new(),
+            origin: [0.0; 3],
+            point_clouds: HashMap::new(),
             materials: HashMap::new(),
             images: HashMap::new(),
+            translucent_images: HashSet::new(),
+            resources: HashMap::new(),
+            pending_files: vec![],
+            formats: io::FormatRegistry::new(),
+            diagnostics: diagnostics::Diagnostics::new(),
+            stl_options: mesh_exporter::StlOptions::new(),
+            download_url: None,
+            collapsed_files: HashSet::new(),
+            lights: light_rig::Preset::Default.lights(),
+            environment: None,
             renderer: None,
             link,
             node_ref: NodeRef::default(),
@@ -87,6 +211,9 @@ impl Component for Model {
             wheel_events: vec![],
             untextured_mesh_renderer: None,
             textured_mesh_renderer: None,
+            pbr_mesh_renderer: None,
+            edge_renderer: None,
+            point_cloud_renderer: None,
         }
     }
 
@@ -100,6 +227,10 @@ impl Component for Model {
         update = mesh_list::update(self, &msg) || update;
         update = render_canvas::update(self, &msg) || update;
         update = mesh_loader::update(self, &msg) || update;
+        update = mesh_exporter::update(self, &msg) || update;
+        update = diagnostics::update(self, &msg) || update;
+        update = light_rig::update(self, &msg) || update;
+        update = environment::update(self, &msg) || update;
         return update;
     }
 
@@ -123,15 +254,20 @@ impl Component for Model {
                 <div>
                 { mesh_loader::view(self) }
                 </div>
+                <div>
+                { diagnostics::view(self) }
+                </div>
                 <table><tr>
                 <td style="vertical-align: top">
                     <canvas ref={self.node_ref.clone()} onpointerdown=down_cb onpointerup=up_cb onpointermove=move_cb onmousewheel=wheel_cb onmouseout=mouse_up_cb ontouchend=touch_end_cb />
                 </td>
                 <td style="vertical-align: top">
                     { mesh_list::view_mesh_list(self) }
+                    { light_rig::view(self) }
+                    { environment::view(self) }
                 </td>
                 </tr></table>
-                <p>{"Viewer for OBJ, PLY files, and more. An "}<a href="https://github.com/indianajohn/web-geo-viewer/" target="_blank">{"open source project"}</a>{". See "} <a href="https://github.com/indianajohn/web-geo-viewer/tree/master/docs" target="_blank">{"the docs"}</a>{" for more info."}</p>
+                <p>{"Viewer for OBJ, PLY, glTF files, and more. An "}<a href="https://github.com/indianajohn/web-geo-viewer/" target="_blank">{"open source project"}</a>{". See "} <a href="https://github.com/indianajohn/web-geo-viewer/tree/master/docs" target="_blank">{"the docs"}</a>{" for more info."}</p>
             </div>
         }
     }
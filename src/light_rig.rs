@@ -0,0 +1,487 @@
+//! The lights of the scene. They are kept in the model, start from a
+//! preset, and are edited in a panel next to the mesh list.
+
+use super::{Model, Msg, ShouldRender};
+use crate::deferred_renderer::{AmbientLight, DirectionalLight, Lights, PointLight};
+use three_d::*;
+use yew::{html, Html, InputData};
+
+/// Sets of lights to start from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Preset {
+    /// Lights from above, below and both sides.
+    Default,
+    /// Key, fill and rim lights that turn with the camera.
+    Studio,
+    /// A single light shining from the camera.
+    Headlight,
+    /// Ambient light only, showing the colors as they are.
+    Flat,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [
+        Preset::Default,
+        Preset::Studio,
+        Preset::Headlight,
+        Preset::Flat,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Default => "Default",
+            Preset::Studio => "Studio",
+            Preset::Headlight => "Headlight",
+            Preset::Flat => "Flat",
+        }
+    }
+
+    pub fn lights(&self) -> Lights {
+        let white = vec3(1.0, 1.0, 1.0);
+        let grey = vec3(0.8, 0.8, 0.8);
+        let directional = |color, intensity, direction, attached_to_camera| DirectionalLight {
+            color,
+            intensity,
+            direction,
+            attached_to_camera,
+        };
+        let point = |position| PointLight {
+            color: grey,
+            intensity: 0.8,
+            position,
+            attenuation: DEFAULT_ATTENUATION,
+        };
+        match self {
+            Preset::Default => Lights {
+                ambient: AmbientLight {
+                    color: grey,
+                    intensity: 0.2,
+                },
+                directional: vec![
+                    directional(grey, 1.0, vec3(0.0, -1.0, 0.0), false),
+                    directional(grey, 1.0, vec3(0.0, 1.0, 0.0), false),
+                ],
+                point: vec![point(vec3(-5.0, 0.0, 0.0)), point(vec3(5.0, 0.0, 0.0))],
+            },
+            Preset::Studio => Lights {
+                ambient: AmbientLight {
+                    color: white,
+                    intensity: 0.1,
+                },
+                // The key light comes from the upper left, the fill light
+                // from the right and the rim light from behind.
+                directional: vec![
+                    directional(vec3(1.0, 0.95, 0.9), 1.0, vec3(1.0, -1.0, -1.0), true),
+                    directional(vec3(0.9, 0.95, 1.0), 0.4, vec3(-1.0, -0.5, -1.0), true),
+                    directional(white, 0.6, vec3(0.0, -1.0, 1.0), true),
+                ],
+                point: vec![],
+            },
+            Preset::Headlight => Lights {
+                ambient: AmbientLight {
+                    color: white,
+                    intensity: 0.2,
+                },
+                directional: vec![directional(white, 1.0, vec3(0.0, 0.0, -1.0), true)],
+                point: vec![],
+            },
+            Preset::Flat => Lights {
+                ambient: AmbientLight {
+                    color: white,
+                    intensity: 1.0,
+                },
+                directional: vec![],
+                point: vec![],
+            },
+        }
+    }
+}
+
+/// Constant, linear and quadratic attenuation of new point lights.
+const DEFAULT_ATTENUATION: Vec3 = Vec3 {
+    x: 0.5,
+    y: 0.05,
+    z: 0.005,
+};
+
+/// Where a light is in `Lights`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightId {
+    Ambient,
+    Directional(usize),
+    Point(usize),
+}
+
+/// A light of any kind, as the panel edits it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Light {
+    Ambient(AmbientLight),
+    Directional(DirectionalLight),
+    Point(PointLight),
+}
+
+impl Light {
+    /// A white directional light shining down.
+    pub fn directional() -> Light {
+        Light::Directional(DirectionalLight {
+            color: vec3(1.0, 1.0, 1.0),
+            intensity: 0.5,
+            direction: vec3(0.0, -1.0, 0.0),
+            attached_to_camera: false,
+        })
+    }
+
+    /// A white point light above the origin.
+    pub fn point() -> Light {
+        Light::Point(PointLight {
+            color: vec3(1.0, 1.0, 1.0),
+            intensity: 0.5,
+            position: vec3(0.0, 5.0, 0.0),
+            attenuation: DEFAULT_ATTENUATION,
+        })
+    }
+
+    pub fn color(&self) -> Vec3 {
+        match self {
+            Light::Ambient(light) => light.color,
+            Light::Directional(light) => light.color,
+            Light::Point(light) => light.color,
+        }
+    }
+
+    pub fn intensity(&self) -> f32 {
+        match self {
+            Light::Ambient(light) => light.intensity,
+            Light::Directional(light) => light.intensity,
+            Light::Point(light) => light.intensity,
+        }
+    }
+
+    pub fn with_color(mut self, color: Vec3) -> Light {
+        match &mut self {
+            Light::Ambient(light) => light.color = color,
+            Light::Directional(light) => light.color = color,
+            Light::Point(light) => light.color = color,
+        }
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Light {
+        match &mut self {
+            Light::Ambient(light) => light.intensity = intensity,
+            Light::Directional(light) => light.intensity = intensity,
+            Light::Point(light) => light.intensity = intensity,
+        }
+        self
+    }
+
+    /// Direction of a directional light, or position of a point light.
+    pub fn vector(&self) -> Option<Vec3> {
+        match self {
+            Light::Ambient(_) => None,
+            Light::Directional(light) => Some(light.direction),
+            Light::Point(light) => Some(light.position),
+        }
+    }
+
+    pub fn with_vector(mut self, vector: Vec3) -> Light {
+        match &mut self {
+            Light::Ambient(_) => {}
+            Light::Directional(light) => light.direction = vector,
+            Light::Point(light) => light.position = vector,
+        }
+        self
+    }
+
+    /// The light as the other of directional and point lights, with the
+    /// same color and intensity.
+    pub fn toggled_kind(&self) -> Light {
+        let toggled = match self {
+            Light::Ambient(_) => return *self,
+            Light::Directional(_) => Light::point(),
+            Light::Point(_) => Light::directional(),
+        };
+        toggled
+            .with_color(self.color())
+            .with_intensity(self.intensity())
+    }
+}
+
+/// An edit of one field of a light in the panel. Edits carry only the
+/// field they change, so that they apply to the light as it is now.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightEdit {
+    Color(Vec3),
+    /// Ignored unless finite.
+    Intensity(f32),
+    /// A component of the direction or position. Ignored unless finite.
+    Vector(usize, f32),
+    ToggleKind,
+    ToggleAttached,
+}
+
+impl LightEdit {
+    pub fn apply(&self, light: Light) -> Light {
+        match *self {
+            LightEdit::Color(color) => light.with_color(color),
+            LightEdit::Intensity(intensity) if intensity.is_finite() => {
+                light.with_intensity(intensity.max(0.0))
+            }
+            LightEdit::Vector(axis, value) if axis < 3 && value.is_finite() => {
+                match light.vector() {
+                    Some(mut vector) => {
+                        vector[axis] = value;
+                        light.with_vector(vector)
+                    }
+                    None => light,
+                }
+            }
+            LightEdit::ToggleKind => light.toggled_kind(),
+            LightEdit::ToggleAttached => match light {
+                Light::Directional(directional) => Light::Directional(DirectionalLight {
+                    attached_to_camera: !directional.attached_to_camera,
+                    ..directional
+                }),
+                _ => light,
+            },
+            _ => light,
+        }
+    }
+}
+
+/// The light at `id`, if there is one.
+pub fn get_light(lights: &Lights, id: LightId) -> Option<Light> {
+    match id {
+        LightId::Ambient => Some(Light::Ambient(lights.ambient)),
+        LightId::Directional(i) => lights.directional.get(i).cloned().map(Light::Directional),
+        LightId::Point(i) => lights.point.get(i).cloned().map(Light::Point),
+    }
+}
+
+/// Adds a light after the others of its kind. The ambient light is
+/// replaced.
+pub fn add_light(lights: &mut Lights, light: Light) {
+    match light {
+        Light::Ambient(light) => lights.ambient = light,
+        Light::Directional(light) => lights.directional.push(light),
+        Light::Point(light) => lights.point.push(light),
+    }
+}
+
+/// Removes a light. The ambient light stays, without intensity.
+pub fn remove_light(lights: &mut Lights, id: LightId) {
+    match id {
+        LightId::Ambient => lights.ambient.intensity = 0.0,
+        LightId::Directional(i) if i < lights.directional.len() => {
+            lights.directional.remove(i);
+        }
+        LightId::Point(i) if i < lights.point.len() => {
+            lights.point.remove(i);
+        }
+        _ => {}
+    }
+}
+
+/// Replaces the light at `id`. A light of another kind moves after the
+/// others of its kind.
+pub fn set_light(lights: &mut Lights, id: LightId, light: Light) {
+    match (id, light) {
+        (LightId::Ambient, Light::Ambient(light)) => lights.ambient = light,
+        (LightId::Directional(i), Light::Directional(light)) if i < lights.directional.len() => {
+            lights.directional[i] = light
+        }
+        (LightId::Point(i), Light::Point(light)) if i < lights.point.len() => {
+            lights.point[i] = light
+        }
+        (LightId::Ambient, _) | (_, Light::Ambient(_)) => {}
+        _ => {
+            if get_light(lights, id).is_some() {
+                remove_light(lights, id);
+                add_light(lights, light);
+            }
+        }
+    }
+}
+
+/// Applies an edit to the light at `id`, if there is one.
+pub fn edit_light(lights: &mut Lights, id: LightId, edit: LightEdit) {
+    if let Some(light) = get_light(lights, id) {
+        set_light(lights, id, edit.apply(light));
+    }
+}
+
+/// Color as an HTML color input shows it, such as "#ff8000".
+pub fn color_to_hex(color: &Vec3) -> String {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(color.x),
+        channel(color.y),
+        channel(color.z)
+    )
+}
+
+/// Color of an HTML color input. None unless it is "#" and six hex
+/// digits.
+pub fn hex_to_color(hex: &str) -> Option<Vec3> {
+    let digits = hex.strip_prefix('#').filter(|digits| digits.len() == 6)?;
+    let channel = |i: usize| {
+        u8::from_str_radix(digits.get(i..i + 2)?, 16)
+            .ok()
+            .map(|value| value as f32 / 255.0)
+    };
+    Some(vec3(channel(0)?, channel(2)?, channel(4)?))
+}
+
+pub fn update(model: &mut Model, msg: &Msg) -> ShouldRender {
+    match msg {
+        Msg::LightPresetChosen(preset) => {
+            model.lights = preset.lights();
+            return true;
+        }
+        Msg::AddLight(light) => {
+            add_light(&mut model.lights, *light);
+            return true;
+        }
+        Msg::RemoveLight(id) => {
+            remove_light(&mut model.lights, *id);
+            return true;
+        }
+        Msg::LightEdited(id, edit) => {
+            edit_light(&mut model.lights, *id, *edit);
+            // Other kinds of lights have other rows. Other edits are
+            // shown by the inputs already.
+            return *edit == LightEdit::ToggleKind;
+        }
+        _ => {}
+    };
+    false
+}
+
+pub fn view(model: &Model) -> Html {
+    let lights = &model.lights;
+    let add_directional_cb = model.link.callback(|_| Msg::AddLight(Light::directional()));
+    let add_point_cb = model.link.callback(|_| Msg::AddLight(Light::point()));
+    html! {
+        <table>
+            { view_presets(model) }
+            { view_light(model, LightId::Ambient, Light::Ambient(lights.ambient)) }
+            { for lights.directional.iter().enumerate().map(|(i, light)| {
+                view_light(model, LightId::Directional(i), Light::Directional(*light))
+            }) }
+            { for lights.point.iter().enumerate().map(|(i, light)| {
+                view_light(model, LightId::Point(i), Light::Point(*light))
+            }) }
+            <tr>
+            <td>
+            <button onclick=add_directional_cb>{ "Add directional light" }</button>
+            </td>
+            <td>
+            <button onclick=add_point_cb>{ "Add point light" }</button>
+            </td>
+            </tr>
+        </table>
+    }
+}
+
+fn view_presets(model: &Model) -> Html {
+    let view_preset = |preset: Preset| {
+        let preset_cb = model.link.callback(move |_| Msg::LightPresetChosen(preset));
+        html! {
+            <td>
+            <button onclick=preset_cb>{ preset.name() }</button>
+            </td>
+        }
+    };
+    html! {
+        <tr>
+        <td>
+        { "Lights:" }
+        </td>
+        { for Preset::ALL.iter().map(|preset| view_preset(*preset)) }
+        </tr>
+    }
+}
+
+/// A row to edit a light: its kind, color, intensity, and its direction
+/// or position.
+fn view_light(model: &Model, id: LightId, light: Light) -> Html {
+    let kind = match light {
+        Light::Ambient(_) => "ambient",
+        Light::Directional(_) => "directional",
+        Light::Point(_) => "point",
+    };
+    let color_cb = model.link.callback(move |event: InputData| {
+        let color = hex_to_color(&event.value).unwrap_or_else(|| light.color());
+        Msg::LightEdited(id, LightEdit::Color(color))
+    });
+    let intensity_cb = model.link.callback(move |event: InputData| {
+        let intensity = event.value.parse::<f32>().unwrap_or(f32::NAN);
+        Msg::LightEdited(id, LightEdit::Intensity(intensity))
+    });
+    let kind_cb = model
+        .link
+        .callback(move |_| Msg::LightEdited(id, LightEdit::ToggleKind));
+    let remove_cb = model.link.callback(move |_| Msg::RemoveLight(id));
+    let vector_inputs = match light.vector() {
+        Some(vector) => html! {
+            <td>
+            { if let Light::Point(_) = light { "position " } else { "direction " } }
+            { for (0..3).map(|axis| {
+                let vector_cb = model.link.callback(move |event: InputData| {
+                    let value = event.value.parse::<f32>().unwrap_or(f32::NAN);
+                    Msg::LightEdited(id, LightEdit::Vector(axis, value))
+                });
+                html! {
+                    <input type="number" step="any" style="width:5em" value={vector[axis].to_string()} oninput=vector_cb />
+                }
+            }) }
+            </td>
+        },
+        None => html! { <td></td> },
+    };
+    let attached_input = match light {
+        Light::Directional(directional) => {
+            let attached_cb = model
+                .link
+                .callback(move |_| Msg::LightEdited(id, LightEdit::ToggleAttached));
+            html! {
+                <td>
+                <input type="checkbox" checked={directional.attached_to_camera} onclick=attached_cb />
+                { "turns with camera" }
+                </td>
+            }
+        }
+        _ => html! { <td></td> },
+    };
+    html! {
+        <tr>
+        <td>
+        { kind }
+        </td>
+        <td>
+        <input type="color" value={color_to_hex(&light.color())} oninput=color_cb />
+        </td>
+        <td>
+        { "intensity " }
+        <input type="number" min="0" step="0.1" style="width:4em" value={light.intensity().to_string()} oninput=intensity_cb />
+        </td>
+        { vector_inputs }
+        { attached_input }
+        { if id == LightId::Ambient { html! { <td></td> } } else { html! {
+            <>
+            <td>
+            <button onclick=kind_cb>
+                        { if let Light::Point(_) = light { "Make directional" } else { "Make point" } }
+            </button>
+            </td>
+            <td>
+            <button onclick=remove_cb>
+                        { "Remove" }
+            </button>
+            </td>
+            </>
+        } } }
+        </tr>
+    }
+}
@@ -0,0 +1,343 @@
+use super::{MeshContainer, Model, Msg, ShouldRender};
+use crate::diagnostics::FileStatus;
+use crate::io::*;
+use crate::origin;
+use log::*;
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use three_d::core::types::{InnerSpace, Matrix, SquareMatrix};
+use three_d::{vec4, Mat4};
+use wasm_bindgen::{JsCast, JsValue};
+use zip::write::FileOptions;
+
+/// How meshes are written to STL files.
+#[derive(Copy, Clone)]
+pub struct StlOptions {
+    /// Binary or ASCII STL?
+    pub binary: bool,
+
+    /// Place the mesh where it's shown, rather than in its own frame?
+    pub apply_transform: bool,
+
+    /// Factor to convert the mesh units to the units of the file.
+    pub scale: f32,
+}
+
+impl StlOptions {
+    pub fn new() -> StlOptions {
+        StlOptions {
+            binary: true,
+            apply_transform: true,
+            scale: 1.0,
+        }
+    }
+
+    /// Checks that the options give a usable file. A scale that is zero,
+    /// negative or not a number would flatten or mirror the mesh.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.scale.is_finite() && self.scale > 0.0 {
+            Ok(())
+        } else {
+            Err(format!(
+                "The STL scale must be a number above zero, not {}",
+                self.scale
+            ))
+        }
+    }
+}
+
+/// Name that problems with the STL options are listed under in the
+/// diagnostics.
+const STL_OPTIONS_NAME: &str = "STL options";
+
+pub fn update(model: &mut Model, msg: &Msg) -> ShouldRender {
+    match msg {
+        Msg::StlOptionsChanged(options) => {
+            // Invalid options are reported, and the last valid ones kept.
+            match options.validate() {
+                Ok(()) => {
+                    model.stl_options = *options;
+                    model.diagnostics.dismiss(STL_OPTIONS_NAME);
+                }
+                Err(error) => {
+                    warn!("{}", error);
+                    model.diagnostics.start(STL_OPTIONS_NAME);
+                    model
+                        .diagnostics
+                        .set_status(STL_OPTIONS_NAME, FileStatus::Failed(error));
+                }
+            }
+            return true;
+        }
+        Msg::ExportStl(name) => {
+            if let Some(container) = model.mesh.get(name) {
+                let file_name = export_base_name(name) + ".stl";
+                let options = &model.stl_options;
+                let result = options.validate().and_then(|_| {
+                    export_stl(container, options, &export_base_name(name))
+                        .map_err(|e| format!("Could not export {} due to {}", name, e))
+                });
+                return save_export(model, &file_name, result);
+            }
+        }
+        Msg::ExportMesh(name) => {
+            if let Some(container) = model.mesh.get(name) {
+                let base_name = export_base_name(name);
+                let file_name = base_name.clone() + ".zip";
+                let result =
+                    export_obj_zip(container, &model.materials, &model.resources, &base_name)
+                        .map_err(|e| format!("Could not export {} due to {}", name, e));
+                return save_export(model, &file_name, result);
+            }
+        }
+        _ => {}
+    };
+    false
+}
+
+/// Replace anything that might not be valid in a file or material
+/// name, such as the separators in the names of glTF meshes.
+fn export_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Name of the exported files, without the extension.
+fn export_base_name(name: &str) -> String {
+    export_name(
+        &Path::new(name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| name.to_string()),
+    )
+}
+
+/// Positions of a mesh in the coordinates of its file, rather than
+/// relative to its origin, and placed by its transform if asked to.
+fn original_positions(container: &MeshContainer, apply_transform: bool) -> Vec<f32> {
+    let mut positions = vec![];
+    for p in container.vertices.positions.chunks_exact(3) {
+        let mut original = origin::to_original(&container.origin, p);
+        if apply_transform {
+            original = origin::transform_point(&container.transform, &original);
+        }
+        positions.extend(original.iter().map(|value| *value as f32));
+    }
+    positions
+}
+
+/// Write all surfaces of a mesh to an STL file.
+fn export_stl(container: &MeshContainer, options: &StlOptions, name: &str) -> IoResult<Vec<u8>> {
+    let mut indices = vec![];
+    for surface in container.surfaces.iter() {
+        indices.extend_from_slice(&surface.indices[..]);
+    }
+    let transform = Mat4::from_scale(options.scale);
+    let positions = original_positions(container, options.apply_transform);
+    let mut data = vec![];
+    if options.binary {
+        save_stl_binary(&mut data, &positions[..], &indices[..], &transform)?;
+    } else {
+        save_stl_ascii(&mut data, name, &positions[..], &indices[..], &transform)?;
+    }
+    Ok(data)
+}
+
+/// Name of an image in the archive. Embedded images don't have a file
+/// name, so they get an extension that matches their content.
+fn texture_file_name(texture_name: &str, data: &[u8]) -> String {
+    let name = export_name(texture_name);
+    if Path::new(texture_name).extension().is_some() && !texture_name.contains('#') {
+        return name;
+    }
+    match image::guess_format(data) {
+        Ok(image::ImageFormat::Png) => name + ".png",
+        Ok(image::ImageFormat::Jpeg) => name + ".jpg",
+        Ok(image::ImageFormat::Gif) => name + ".gif",
+        Ok(image::ImageFormat::Bmp) => name + ".bmp",
+        _ => name,
+    }
+}
+
+/// Refer to textures by the names they were exported with.
+fn rename_textures(material: &mut Material, file_names: &HashMap<String, String>) {
+    if let Some(texture_name) = &mut material.texture_name {
+        if let Some(file_name) = file_names.get(texture_name) {
+            *texture_name = file_name.clone();
+        }
+    }
+    let mut maps = vec![
+        &mut material.specular_map,
+        &mut material.opacity_map,
+        &mut material.bump_map,
+        &mut material.normal_map,
+        &mut material.emissive_map,
+    ];
+    if let Some(pbr) = &mut material.pbr {
+        maps.push(&mut pbr.metallic_map);
+        maps.push(&mut pbr.roughness_map);
+        maps.push(&mut pbr.occlusion_map);
+    }
+    for map in maps.iter_mut().filter_map(|map| map.as_mut()) {
+        if let Some(file_name) = file_names.get(&map.name) {
+            map.name = file_name.clone();
+        }
+    }
+}
+
+/// Write a mesh, its materials and its textures to a zip archive. The
+/// transform of the mesh is applied to the vertices.
+fn export_obj_zip(
+    container: &MeshContainer,
+    materials: &HashMap<String, Material>,
+    resources: &HashMap<String, Vec<u8>>,
+    base_name: &str,
+) -> Result<Vec<u8>, String> {
+    let vertices = &container.vertices;
+    let positions = original_positions(container, true);
+    let mut normals = vertices.normals.clone();
+    if container.transform != Mat4::identity() {
+        let normal_transform = container
+            .transform
+            .invert()
+            .unwrap_or(container.transform)
+            .transpose();
+        for n in normals.chunks_exact_mut(3) {
+            let transformed = (normal_transform * vec4(n[0], n[1], n[2], 0.0))
+                .truncate()
+                .normalize();
+            n.copy_from_slice(&[transformed.x, transformed.y, transformed.z]);
+        }
+    }
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+    let options = FileOptions::default();
+    let mut exported_materials: Vec<(String, Material)> = vec![];
+    let mut exported_textures: Vec<String> = vec![];
+    let mut surfaces = vec![];
+    let mut uvs = None;
+    for surface in container.surfaces.iter() {
+        if uvs.is_none() {
+            uvs = surface.uvs.as_ref().map(|uvs| &uvs[..]);
+        }
+        let mut exported_name = None;
+        if let Some(material_name) = &surface.maybe_material_name {
+            let name = export_name(material_name);
+            if !exported_materials.iter().any(|m| m.0 == name) {
+                let mut material = materials
+                    .get(material_name)
+                    .cloned()
+                    .unwrap_or_else(Material::new);
+                let mut exported_files = HashMap::new();
+                for texture_name in material.texture_names() {
+                    match resources.get(texture_name) {
+                        Some(data) => {
+                            let file_name = texture_file_name(texture_name, &data[..]);
+                            if !exported_textures.contains(&file_name) {
+                                exported_textures.push(file_name.clone());
+                                zip.start_file(file_name.clone(), options)
+                                    .map_err(|e| format!("{:?}", e))?;
+                                zip.write_all(&data[..]).map_err(|e| format!("{:?}", e))?;
+                            }
+                            exported_files.insert(texture_name.to_string(), file_name);
+                        }
+                        None => {
+                            warn!(
+                                "Texture {} is not loaded, so it isn't exported",
+                                texture_name
+                            );
+                        }
+                    }
+                }
+                rename_textures(&mut material, &exported_files);
+                exported_materials.push((name.clone(), material));
+            }
+            exported_name = Some(name);
+        }
+        surfaces.push((exported_name, &surface.indices[..]));
+    }
+
+    let mtl_name = base_name.to_string() + ".mtl";
+    let mut mtl = vec![];
+    if !exported_materials.is_empty() {
+        save_mtl(&mut mtl, &exported_materials[..]).map_err(|e| format!("{:?}", e))?;
+        zip.start_file(mtl_name.clone(), options)
+            .map_err(|e| format!("{:?}", e))?;
+        zip.write_all(&mtl[..]).map_err(|e| format!("{:?}", e))?;
+    }
+
+    let data = ObjMeshData {
+        positions: &positions[..],
+        normals: Some(&normals[..]),
+        uvs,
+        colors: vertices.colors.as_ref().map(|colors| &colors[..]),
+        surfaces: surfaces
+            .iter()
+            .map(|(material_name, indices)| ObjSurface {
+                material_name: material_name.as_deref(),
+                indices,
+            })
+            .collect(),
+    };
+    let mut obj = vec![];
+    let mtl_lib = if exported_materials.is_empty() {
+        None
+    } else {
+        Some(&mtl_name[..])
+    };
+    save_obj(&mut obj, mtl_lib, &data).map_err(|e| format!("{:?}", e))?;
+    zip.start_file(base_name.to_string() + ".obj", options)
+        .map_err(|e| format!("{:?}", e))?;
+    zip.write_all(&obj[..]).map_err(|e| format!("{:?}", e))?;
+    let archive = zip.finish().map_err(|e| format!("{:?}", e))?;
+    Ok(archive.into_inner())
+}
+
+/// Downloads an exported file, or lists why it couldn't be exported with
+/// the diagnostics of loaded files, under the name of the file.
+fn save_export(model: &mut Model, file_name: &str, result: Result<Vec<u8>, String>) -> bool {
+    let error = match result {
+        Ok(data) => match download(file_name, &data[..]) {
+            Ok(url) => {
+                if let Some(old_url) = model.download_url.replace(url) {
+                    let _ = web_sys::Url::revoke_object_url(&old_url);
+                }
+                return false;
+            }
+            Err(e) => format!("Could not download {} due to {:?}", file_name, e),
+        },
+        Err(error) => error,
+    };
+    warn!("{}", error);
+    model.diagnostics.start(file_name);
+    model
+        .diagnostics
+        .set_status(file_name, FileStatus::Failed(error));
+    true
+}
+
+/// Have the browser save the data to a file. Returns the object URL of
+/// the data, which must stay valid until the download has started.
+fn download(file_name: &str, data: &[u8]) -> Result<String, JsValue> {
+    let array = js_sys::Uint8Array::from(data);
+    let blob = web_sys::Blob::new_with_u8_array_sequence(&js_sys::Array::of1(&array))?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("No document"))?;
+    let anchor = document
+        .create_element("a")?
+        .dyn_into::<web_sys::HtmlAnchorElement>()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    Ok(url)
+}
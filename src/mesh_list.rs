@@ -1,29 +1,355 @@
 use super::{Model, Msg, ShouldRender};
-use yew::{html, Html};
+use crate::mesh_exporter::StlOptions;
+use crate::render_buffers::EdgeData;
+use log::warn;
+use std::collections::BTreeMap;
+use yew::{html, Html, InputData};
 
 pub fn update(model: &mut Model, msg: &Msg) -> ShouldRender {
     match msg {
         Msg::RemoveMesh(event) => {
             model.mesh.remove(event);
+            model.point_clouds.remove(event);
             return true;
         }
         Msg::MeshVisibilityToggle(event) => {
-            let visible = &mut model.mesh.get_mut(event).unwrap().visible;
-            *visible = !(*visible);
+            if let Some(mesh) = model.mesh.get_mut(event) {
+                mesh.visible = !mesh.visible;
+            }
+            if let Some(point_cloud) = model.point_clouds.get_mut(event) {
+                point_cloud.visible = !point_cloud.visible;
+            }
+            // The row of the file may have to be checked or unchecked.
+            return true;
+        }
+        Msg::RemoveFile(file) => {
+            model.mesh.retain(|_, mesh| &mesh.file != file);
+            model
+                .point_clouds
+                .retain(|_, point_cloud| &point_cloud.file != file);
+            model.collapsed_files.remove(file);
+            return true;
+        }
+        Msg::FileVisibilityToggle(file) => {
+            // Show everything unless all of it is shown already.
+            let visible = !model
+                .mesh
+                .values()
+                .filter(|mesh| &mesh.file == file)
+                .all(|mesh| mesh.visible)
+                || !model
+                    .point_clouds
+                    .values()
+                    .filter(|point_cloud| &point_cloud.file == file)
+                    .all(|point_cloud| point_cloud.visible);
+            for mesh in model.mesh.values_mut().filter(|mesh| &mesh.file == file) {
+                mesh.visible = visible;
+            }
+            for point_cloud in model
+                .point_clouds
+                .values_mut()
+                .filter(|point_cloud| &point_cloud.file == file)
+            {
+                point_cloud.visible = visible;
+            }
+            return true;
+        }
+        Msg::ToggleFileCollapsed(file) => {
+            if !model.collapsed_files.remove(file) {
+                model.collapsed_files.insert(file.clone());
+            }
+            return true;
+        }
+        Msg::IsolateMesh(name) => {
+            for (mesh_name, mesh) in model.mesh.iter_mut() {
+                mesh.visible = mesh_name == name;
+            }
+            for (point_cloud_name, point_cloud) in model.point_clouds.iter_mut() {
+                point_cloud.visible = point_cloud_name == name;
+            }
+            return true;
+        }
+        Msg::ShowAll => {
+            for mesh in model.mesh.values_mut() {
+                mesh.visible = true;
+            }
+            for point_cloud in model.point_clouds.values_mut() {
+                point_cloud.visible = true;
+            }
+            return true;
+        }
+        Msg::PointSizeChanged(name, point_size) => {
+            if let Some(point_cloud) = model.point_clouds.get_mut(name) {
+                point_cloud.point_size = *point_size;
+            }
+        }
+        Msg::MeshOpacityChanged(name, opacity) => {
+            if let Some(mesh) = model.mesh.get_mut(name) {
+                mesh.opacity = *opacity;
+            }
+        }
+        Msg::MeshDisplayModeChanged(name, display_mode) => {
+            if let Some(mesh) = model.mesh.get_mut(name) {
+                mesh.display_mode = *display_mode;
+                if display_mode.shows_edges() {
+                    let gl = model.gl.as_ref().expect("GL Context not initialized!");
+                    for surface in mesh.surfaces.iter_mut() {
+                        if surface.maybe_edges.is_some() {
+                            continue;
+                        }
+                        match EdgeData::new(gl, &mesh.vertices.positions, &surface.indices) {
+                            Ok(edges) => surface.maybe_edges = Some(edges),
+                            Err(e) => {
+                                warn!("Could not create the edges of {} due to {:?}", name, e)
+                            }
+                        }
+                    }
+                }
+                return true;
+            }
         }
         _ => {}
     };
     false
 }
 pub fn view_mesh_list(model: &Model) -> Html {
+    // Meshes and point clouds of each file, sorted by name.
+    let mut files: BTreeMap<&str, (Vec<&str>, Vec<&str>)> = BTreeMap::new();
+    for (name, mesh) in model.mesh.iter() {
+        files.entry(&mesh.file).or_default().0.push(name);
+    }
+    for (name, point_cloud) in model.point_clouds.iter() {
+        files.entry(&point_cloud.file).or_default().1.push(name);
+    }
+    let any_hidden = model.mesh.values().any(|mesh| !mesh.visible)
+        || model
+            .point_clouds
+            .values()
+            .any(|point_cloud| !point_cloud.visible);
+    let show_all_cb = model.link.callback(|_| Msg::ShowAll);
+    let frame_all_cb = model.link.callback(|_| Msg::FrameAll);
     html! {
         <table style="width:100%">
-        { for model.mesh.iter().map(|f| view_element(model, f.0)) }
+        { for files.iter_mut().map(|(file, (meshes, point_clouds))| {
+            meshes.sort_unstable();
+            point_clouds.sort_unstable();
+            view_file(model, file, meshes, point_clouds)
+        }) }
+        { if any_hidden { html! { <button onclick=show_all_cb>{ "Show all" }</button> } } else { html! {} } }
+        { if files.is_empty() { html! {} } else { html! { <button onclick=frame_all_cb>{ "Frame all" }</button> } } }
+        { if model.mesh.is_empty() { html! {} } else { view_stl_options(model) } }
+        </table>
+    }
+}
+
+/// Entries of a file. Files with several parts get a row of their own,
+/// with the parts listed below it unless collapsed.
+fn view_file(model: &Model, file: &str, meshes: &[&str], point_clouds: &[&str]) -> Html {
+    if meshes.len() + point_clouds.len() == 1 && (meshes == [file] || point_clouds == [file]) {
+        return html! {
+            <>
+            { for meshes.iter().map(|name| view_element(model, name, name)) }
+            { for point_clouds.iter().map(|name| view_point_cloud_element(model, name, name)) }
+            </>
+        };
+    }
+    let collapsed = model.collapsed_files.contains(file);
+    let visible = meshes.iter().all(|name| model.mesh[*name].visible)
+        && point_clouds
+            .iter()
+            .all(|name| model.point_clouds[*name].visible);
+    // Parts are listed without the file name they start with.
+    let prefix = format!("{}/", file);
+    let part_label = |name: &str| name.strip_prefix(&prefix[..]).unwrap_or(name).to_string();
+    let file_name = file.to_string();
+    let collapse_cb = model
+        .link
+        .callback(move |_| Msg::ToggleFileCollapsed(file_name.clone()));
+    let file_name = file.to_string();
+    let handle_check_cb = model
+        .link
+        .callback(move |_| Msg::FileVisibilityToggle(file_name.clone()));
+    let file_name = file.to_string();
+    let frame_file_cb = model
+        .link
+        .callback(move |_| Msg::FrameFile(file_name.clone()));
+    let file_name = file.to_string();
+    let remove_file_cb = model
+        .link
+        .callback(move |_| Msg::RemoveFile(file_name.clone()));
+    html! {
+        <>
+        <table>
+            <tr>
+            <td>
+            <button onclick=collapse_cb>
+                        { if collapsed { "▸" } else { "▾" } }
+            </button>
+            </td>
+            <td>
+            { file }
+            </td>
+            <td>
+            <input type="checkbox" checked={visible} onclick=handle_check_cb />
+            </td>
+            <td>
+            <button onclick=frame_file_cb>
+                        { "Frame" }
+            </button>
+            </td>
+            <td>
+            <button onclick=remove_file_cb>
+                        { "Remove" }
+            </button>
+            </td>
+            </tr>
+        </table>
+        { if collapsed { html! {} } else { html! {
+            <div style="margin-left:2em">
+            { for meshes.iter().map(|name| view_element(model, name, &part_label(name))) }
+            { for point_clouds.iter().map(|name| view_point_cloud_element(model, name, &part_label(name))) }
+            </div>
+        } } }
+        </>
+    }
+}
+
+fn view_stl_options(model: &Model) -> Html {
+    let options = model.stl_options;
+    let binary_cb = model.link.callback(move |_| {
+        Msg::StlOptionsChanged(StlOptions {
+            binary: !options.binary,
+            ..options
+        })
+    });
+    let transform_cb = model.link.callback(move |_| {
+        Msg::StlOptionsChanged(StlOptions {
+            apply_transform: !options.apply_transform,
+            ..options
+        })
+    });
+    let scale_cb = model.link.callback(move |event: InputData| {
+        // Anything that isn't a number is reported as not one.
+        let scale = event.value.parse::<f32>().unwrap_or(f32::NAN);
+        Msg::StlOptionsChanged(StlOptions { scale, ..options })
+    });
+    html! {
+        <table>
+            <tr>
+            <td>
+            { "STL:" }
+            </td>
+            <td>
+            <input type="checkbox" checked={options.binary} onclick=binary_cb />
+            { "binary" }
+            </td>
+            <td>
+            <input type="checkbox" checked={options.apply_transform} onclick=transform_cb />
+            { "apply transform" }
+            </td>
+            <td>
+            { "scale " }
+            <input type="number" min="0" step="any" value={options.scale.to_string()} oninput=scale_cb title="Must be above zero" />
+            </td>
+            </tr>
+        </table>
+    }
+}
+
+fn view_element(model: &Model, data: &str, label: &str) -> Html {
+    let mesh = model.mesh.get(data).unwrap();
+    let mesh_name = data.to_string();
+    let isolate_mesh_cb = model
+        .link
+        .callback(move |_| Msg::IsolateMesh(mesh_name.clone()));
+    let mesh_name = data.to_string();
+    let frame_mesh_cb = model
+        .link
+        .callback(move |_| Msg::FrameMesh(mesh_name.clone()));
+    let mesh_name = data.to_string();
+    let remove_mesh_cb = model
+        .link
+        .callback(move |_| Msg::RemoveMesh(mesh_name.clone()));
+    let mesh_name = data.to_string();
+    let handle_check_cb = model
+        .link
+        .callback(move |_| Msg::MeshVisibilityToggle(mesh_name.clone()));
+    let mesh_name = data.to_string();
+    let export_mesh_cb = model
+        .link
+        .callback(move |_| Msg::ExportMesh(mesh_name.clone()));
+    let mesh_name = data.to_string();
+    let export_stl_cb = model
+        .link
+        .callback(move |_| Msg::ExportStl(mesh_name.clone()));
+    let mesh_name = data.to_string();
+    let current_opacity = mesh.opacity;
+    let opacity_cb = model.link.callback(move |event: InputData| {
+        let opacity = event.value.parse::<f32>().unwrap_or(current_opacity);
+        Msg::MeshOpacityChanged(mesh_name.clone(), opacity.clamp(0.0, 1.0))
+    });
+    let mesh_name = data.to_string();
+    let next_display_mode = mesh.display_mode.next();
+    let display_mode_cb = model
+        .link
+        .callback(move |_| Msg::MeshDisplayModeChanged(mesh_name.clone(), next_display_mode));
+    html! {
+        <table>
+            <tr>
+            <td>
+            { label }
+            </td>
+            <td>
+            <input type="checkbox" checked={mesh.visible} onclick=handle_check_cb />
+            </td>
+            <td>
+            <button onclick=isolate_mesh_cb>
+                        { "Isolate" }
+            </button>
+            </td>
+            <td>
+            <button onclick=frame_mesh_cb>
+                        { "Frame" }
+            </button>
+            </td>
+            <td>
+            <input type="range" min="0" max="1" step="0.05" value={mesh.opacity.to_string()} oninput=opacity_cb title="Opacity" />
+            </td>
+            <td>
+            <button onclick=display_mode_cb title="Display mode">
+                        { mesh.display_mode.name() }
+            </button>
+            </td>
+            <td>
+            <button onclick=export_mesh_cb>
+                        { "Export OBJ" }
+            </button>
+            </td>
+            <td>
+            <button onclick=export_stl_cb>
+                        { "Export STL" }
+            </button>
+            </td>
+            <td>
+            <button onclick=remove_mesh_cb.clone()>
+                        { "Remove" }
+            </button>
+            </td>
+            </tr>
         </table>
     }
 }
 
-fn view_element(model: &Model, data: &str) -> Html {
+fn view_point_cloud_element(model: &Model, data: &str, label: &str) -> Html {
+    let point_cloud = model.point_clouds.get(data).unwrap();
+    let mesh_name = data.to_string();
+    let isolate_mesh_cb = model
+        .link
+        .callback(move |_| Msg::IsolateMesh(mesh_name.clone()));
+    let mesh_name = data.to_string();
+    let frame_mesh_cb = model
+        .link
+        .callback(move |_| Msg::FrameMesh(mesh_name.clone()));
     let mesh_name = data.to_string();
     let remove_mesh_cb = model
         .link
@@ -32,14 +358,33 @@ fn view_element(model: &Model, data: &str) -> Html {
     let handle_check_cb = model
         .link
         .callback(move |_| Msg::MeshVisibilityToggle(mesh_name.clone()));
+    let mesh_name = data.to_string();
+    let current_size = point_cloud.point_size;
+    let point_size_cb = model.link.callback(move |event: InputData| {
+        let point_size = event.value.parse::<f32>().unwrap_or(current_size);
+        Msg::PointSizeChanged(mesh_name.clone(), point_size.max(1.0))
+    });
     html! {
         <table>
             <tr>
             <td>
-            { data }
+            { label }
+            </td>
+            <td>
+            <input type="checkbox" checked={point_cloud.visible} onclick=handle_check_cb />
+            </td>
+            <td>
+            <button onclick=isolate_mesh_cb>
+                        { "Isolate" }
+            </button>
+            </td>
+            <td>
+            <button onclick=frame_mesh_cb>
+                        { "Frame" }
+            </button>
             </td>
             <td>
-            <input type="checkbox" checked={model.mesh.get(data).unwrap().visible } onclick=handle_check_cb />
+            <input type="range" min="1" max="20" step="1" value={point_cloud.point_size.to_string()} oninput=point_size_cb />
             </td>
             <td>
             <button onclick=remove_mesh_cb.clone()>
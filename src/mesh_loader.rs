@@ -1,46 +1,25 @@
-use super::{MeshContainer, Model, Msg, ShouldRender};
+use super::diagnostics::FileStatus;
+use super::render_canvas;
+use super::{MeshContainer, Model, Msg, PointCloudContainer, ShouldRender};
+use crate::bounds::BoundingBox;
+use crate::edge_renderer::DisplayMode;
 use crate::io::*;
-use crate::render_buffers::{MeshSurface, VertexData};
+use crate::io::{Error, ErrorKind};
+use crate::origin::{self, Point64};
+use crate::render_buffers::{MeshSurface, PointData, VertexData};
 use image::GenericImageView;
 use log::*;
-use rust_3d::{io::load_stl_mesh_unique, *};
+use rust_3d::*;
 use std::collections::HashMap;
-use std::path::Path;
-use three_d::core::types::InnerSpace;
+use three_d::core::types::{InnerSpace, SquareMatrix};
 use three_d::vec3;
-use yew::services::reader::File;
+use yew::services::reader::{File, FileData};
 use yew::{html, ChangeData, Html};
 
-type Rust3DMesh = rust_3d::Mesh3D<Point3D, PointCloud3D<Point3D>, Vec<usize>>;
+type Rust3DMesh = SceneMesh3D;
 
-fn string_to_format(format_string: &String) -> Option<image::ImageFormat> {
-    let test_string = format_string.to_lowercase();
-    if test_string == "png" {
-        Some(image::ImageFormat::Png)
-    } else if test_string == "jpg" || test_string == ".jpeg" {
-        Some(image::ImageFormat::Jpeg)
-    } else if test_string == "gif" {
-        Some(image::ImageFormat::Gif)
-    } else if test_string == "webp" {
-        Some(image::ImageFormat::WebP)
-    } else if test_string == "pnm" {
-        Some(image::ImageFormat::Pnm)
-    } else if test_string == "tiff" || test_string == "tiff" {
-        Some(image::ImageFormat::Tiff)
-    } else if test_string == "tga" {
-        Some(image::ImageFormat::Tga)
-    } else if test_string == "dds" {
-        Some(image::ImageFormat::Dds)
-    } else if test_string == "bmp" {
-        Some(image::ImageFormat::Bmp)
-    } else if test_string == "ico" {
-        Some(image::ImageFormat::Ico)
-    } else if test_string == "hdr" {
-        Some(image::ImageFormat::Hdr)
-    } else {
-        None
-    }
-}
+/// Creating the GPU buffers of a mesh or point cloud can fail too.
+type BufferResult<T> = std::result::Result<T, three_d::objects::Error>;
 
 fn compute_normals(indices: &[u32], positions: &[f32]) -> Vec<f32> {
     let mut normals = vec![0.0f32; positions.len() * 3];
@@ -85,13 +64,76 @@ fn compute_normals(indices: &[u32], positions: &[f32]) -> Vec<f32> {
     normals
 }
 
+fn vector_at(array: &[f32], index: usize) -> three_d::Vec3 {
+    vec3(array[index * 3], array[index * 3 + 1], array[index * 3 + 2])
+}
+
+/// Tangents and their handedness for each vertex, from the directions
+/// in which the UVs increase across the faces around it. UVs have a
+/// stride of 3. Vertices without a usable UV direction get any
+/// tangent, so that lighting them still works.
+pub(crate) fn compute_tangents(
+    indices: &[u32],
+    positions: &[f32],
+    normals: &[f32],
+    uvs: &[f32],
+) -> (Vec<f32>, Vec<f32>) {
+    let num_vertices = positions.len() / 3;
+    let mut face_tangents = vec![vec3(0.0, 0.0, 0.0); num_vertices];
+    let mut face_bitangents = vec![vec3(0.0, 0.0, 0.0); num_vertices];
+    for face in indices.chunks_exact(3) {
+        let (index0, index1, index2) = (face[0] as usize, face[1] as usize, face[2] as usize);
+        let p0 = vector_at(positions, index0);
+        let edge1 = vector_at(positions, index1) - p0;
+        let edge2 = vector_at(positions, index2) - p0;
+        let uv0 = vector_at(uvs, index0);
+        let uv_edge1 = vector_at(uvs, index1) - uv0;
+        let uv_edge2 = vector_at(uvs, index2) - uv0;
+        let determinant = uv_edge1.x * uv_edge2.y - uv_edge2.x * uv_edge1.y;
+        if determinant.abs() < 1e-12 {
+            // All corners have UVs on a line.
+            continue;
+        }
+        let tangent = (edge1 * uv_edge2.y - edge2 * uv_edge1.y) / determinant;
+        let bitangent = (edge2 * uv_edge1.x - edge1 * uv_edge2.x) / determinant;
+        for &index in [index0, index1, index2].iter() {
+            face_tangents[index] += tangent;
+            face_bitangents[index] += bitangent;
+        }
+    }
+
+    let mut tangents = Vec::with_capacity(num_vertices * 3);
+    let mut handedness = Vec::with_capacity(num_vertices);
+    for i in 0..num_vertices {
+        let normal = vector_at(normals, i);
+        // Make the tangent perpendicular to the normal.
+        let mut tangent = face_tangents[i] - normal * normal.dot(face_tangents[i]);
+        if tangent.magnitude2() < 1e-12 {
+            let axis = if normal.x.abs() < 0.9 {
+                vec3(1.0, 0.0, 0.0)
+            } else {
+                vec3(0.0, 1.0, 0.0)
+            };
+            tangent = normal.cross(axis);
+        }
+        extend_by_vec3(&tangent.normalize(), &mut tangents);
+        handedness.push(if normal.cross(tangent).dot(face_bitangents[i]) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        });
+    }
+    (tangents, handedness)
+}
+
 fn create_mesh_surface(
     model: &mut Model,
     mesh: &Rust3DMesh,
+    positions: &[f32],
     uvs: &rust_3d::PointCloud3D<Point3D>,
     material_name: Option<String>,
     surface: &MaterialSurface,
-) -> MeshSurface {
+) -> BufferResult<MeshSurface> {
     let gl_ref = model.gl.as_ref().expect("GL Context not initialized!");
     // Only add faces that belong to this surface.
     let mut indices: Vec<u32> = vec![];
@@ -115,14 +157,17 @@ fn create_mesh_surface(
         info!("Loaded {} uvs", uv_vec.len() / 3);
         maybe_uvs = Some(&uv_vec[..]);
     }
-    MeshSurface::new(gl_ref, &indices[..], maybe_uvs, material_name).unwrap()
+    MeshSurface::new(gl_ref, positions, &indices[..], maybe_uvs, material_name)
 }
 
 fn create_vertex_data(
     model: &mut Model,
     mesh: &Rust3DMesh,
+    origin: &Point64,
     maybe_normals: Option<Vec<f32>>,
-) -> VertexData {
+    colors: &[three_d::Vec3],
+    uvs: &rust_3d::PointCloud3D<Point3D>,
+) -> BufferResult<VertexData> {
     let gl_ref = model.gl.as_ref().expect("GL Context not initialized!");
     let mut vertices: Vec<f32> = vec![];
     let mut indices: Vec<u32> = vec![];
@@ -134,14 +179,21 @@ fn create_vertex_data(
     }
     for vid in 0..mesh.num_vertices() {
         let vertex = mesh.vertex(VId { val: vid }).unwrap();
-        vertices.push(vertex.x as f32);
-        vertices.push(vertex.y as f32);
-        vertices.push(vertex.z as f32);
+        extend_by_position(&vertex, origin, &mut vertices);
+    }
+    let mut maybe_colors: Option<&[f32]> = None;
+    let mut color_vec: Vec<f32> = vec![];
+    if !colors.is_empty() && colors.len() == mesh.num_vertices() {
+        for color in colors.iter() {
+            extend_by_vec3(color, &mut color_vec);
+        }
+        info!("Using {} vertex colors.", colors.len());
+        maybe_colors = Some(&color_vec[..]);
     }
-    match maybe_normals {
+    let normals = match maybe_normals {
         Some(normals) => {
             info!("Using {} provided normals.", normals.len());
-            VertexData::new(gl_ref, &vertices[..], &normals[..]).unwrap()
+            normals
         }
         None => {
             info!(
@@ -149,14 +201,31 @@ fn create_vertex_data(
                 vertices.len(),
                 indices.len()
             );
-            VertexData::new(
-                gl_ref,
-                &vertices[..],
-                &compute_normals(&indices[..], &vertices[..]),
-            )
-            .unwrap()
+            compute_normals(&indices[..], &vertices[..])
+        }
+    };
+    let mut tangents = None;
+    if uvs.len() == mesh.num_vertices() {
+        let mut uv_vec = vec![];
+        for uv in uvs.data.iter() {
+            extend_by_vertex(uv, &mut uv_vec);
         }
+        tangents = Some(compute_tangents(
+            &indices[..],
+            &vertices[..],
+            &normals[..],
+            &uv_vec[..],
+        ));
     }
+    VertexData::new(
+        gl_ref,
+        &vertices[..],
+        &normals[..],
+        maybe_colors,
+        tangents
+            .as_ref()
+            .map(|(tangents, handedness)| (&tangents[..], &handedness[..])),
+    )
 }
 
 fn extend_by_vertex(p: &Point3D, array: &mut Vec<f32>) {
@@ -165,39 +234,85 @@ fn extend_by_vertex(p: &Point3D, array: &mut Vec<f32>) {
     array.push(p.z() as f32);
 }
 
-/// Per-wedge UVs require that we duplicate any
-/// vertices that are referenced multiple times
-/// in the index vector so that the vertex and
-/// UV buffer that we send to the GPU are the
-/// same length. The resulting vectors are the
-/// same length and arrangement of the index
-/// vector, and the index vector is therefore
-/// sequential.
+/// Adds a position relative to the origin of its mesh.
+fn extend_by_position(p: &Point3D, origin: &Point64, array: &mut Vec<f32>) {
+    array.push((p.x() - origin[0]) as f32);
+    array.push((p.y() - origin[1]) as f32);
+    array.push((p.z() - origin[2]) as f32);
+}
+
+/// Origin that the vertices of a mesh are kept relative to.
+fn mesh_origin(mesh: &Rust3DMesh) -> Point64 {
+    origin::origin_of((0..mesh.num_vertices()).map(|vid| {
+        let vertex = mesh.vertex(VId { val: vid }).unwrap();
+        [vertex.x(), vertex.y(), vertex.z()]
+    }))
+}
+
+/// Model matrix of a mesh or point cloud, relative to the origin of the
+/// scene. The first one loaded into an empty scene sets that origin.
+fn scene_model_matrix(
+    model: &mut Model,
+    transform: &three_d::Mat4,
+    origin: &Point64,
+) -> three_d::Mat4 {
+    if model.mesh.is_empty() && model.point_clouds.is_empty() {
+        model.origin = origin::transform_point(transform, origin);
+    }
+    origin::model_matrix(transform, origin, &model.origin)
+}
+
+fn extend_by_vec3(vector: &three_d::Vec3, array: &mut Vec<f32>) {
+    array.push(vector.x);
+    array.push(vector.y);
+    array.push(vector.z);
+}
+
+fn flatten_vectors(vectors: &[three_d::Vec3]) -> Vec<f32> {
+    let mut array = vec![];
+    for vector in vectors.iter() {
+        extend_by_vec3(vector, &mut array);
+    }
+    array
+}
+
+/// Per-wedge UVs and normals require that we
+/// duplicate any vertices that are referenced
+/// multiple times in the index vector so that
+/// the vertex and UV buffer that we send to the
+/// GPU are the same length. The resulting vectors
+/// are the same length and arrangement of the
+/// index vector, and the index vector is
+/// therefore sequential.
 fn divide_mesh_by_materials_per_wedge(
     model: &mut Model,
     mesh: &Rust3DMesh,
+    origin: &Point64,
     material_info: &MaterialInfo,
-) -> MeshContainer {
+) -> BufferResult<MeshContainer> {
     info!("Rebuilding vertex/UV vectors so they agree.");
     let gl_ref = model.gl.as_ref().expect("GL Context not initialized!");
     let mut vertices = vec![];
     let mut uvs = vec![];
+    let mut colors = vec![];
+    let mut normals = vec![];
     let uv_in = &material_info.uv;
-    if 3 * mesh.num_faces() != uv_in.len() {
-        // This function is only meant to be used with a mesh that has
-        // per-face UVs.
-        panic!(
-            "Expected 1 UV per wedge ({} != {})!",
-            mesh.num_faces() * 3,
-            uv_in.len()
-        );
-    }
-    let mut surface_indices: HashMap<String, Vec<u32>> = HashMap::new();
-
-    // Initialize index vector for each surface.
-    for surface in &material_info.surfaces {
-        surface_indices.insert(surface.0.clone(), vec![]);
-    }
+    let normals_in = &material_info.normals;
+    let colors_in = &material_info.colors;
+    // UVs, normals and colors may each be given either per wedge or
+    // per vertex.
+    let has_wedge_uvs = 3 * mesh.num_faces() == uv_in.len();
+    let has_vertex_uvs = !has_wedge_uvs && mesh.num_vertices() == uv_in.len();
+    let has_wedge_normals = 3 * mesh.num_faces() == normals_in.len();
+    let has_vertex_normals = !has_wedge_normals && mesh.num_vertices() == normals_in.len();
+    let has_wedge_colors = 3 * mesh.num_faces() == colors_in.len();
+    let has_vertex_colors = !has_wedge_colors && mesh.num_vertices() == colors_in.len();
+    // Index vector for each surface.
+    let mut surface_indices: HashMap<&String, Vec<u32>> = material_info
+        .surfaces
+        .keys()
+        .map(|name| (name, vec![]))
+        .collect();
     let mut all_indices = vec![];
     for fid in 0..mesh.num_faces() {
         let vids = mesh.face_vertex_ids(FId { val: fid }).unwrap();
@@ -205,57 +320,110 @@ fn divide_mesh_by_materials_per_wedge(
         all_indices.push((fid * 3 + 1) as u32);
         all_indices.push((fid * 3 + 2) as u32);
         // Insert this index into surfaces to which this face belongs.
-        for surface in &material_info.surfaces {
-            if surface.1.faces.contains(&vids) {
-                let index_vector = surface_indices.get_mut(surface.0).unwrap();
+        for (name, index_vector) in surface_indices.iter_mut() {
+            if material_info.surfaces[*name].faces.contains(&vids) {
                 index_vector.push((fid * 3 + 0) as u32);
                 index_vector.push((fid * 3 + 1) as u32);
                 index_vector.push((fid * 3 + 2) as u32);
             }
         }
         let v0 = mesh.vertex(vids.a).unwrap();
-        extend_by_vertex(&v0, &mut vertices);
+        extend_by_position(&v0, origin, &mut vertices);
         let v1 = mesh.vertex(vids.b).unwrap();
-        extend_by_vertex(&v1, &mut vertices);
+        extend_by_position(&v1, origin, &mut vertices);
         let v2 = mesh.vertex(vids.c).unwrap();
-        extend_by_vertex(&v2, &mut vertices);
+        extend_by_position(&v2, origin, &mut vertices);
+        if has_wedge_colors {
+            extend_by_vec3(&colors_in[fid * 3], &mut colors);
+            extend_by_vec3(&colors_in[fid * 3 + 1], &mut colors);
+            extend_by_vec3(&colors_in[fid * 3 + 2], &mut colors);
+        } else if has_vertex_colors {
+            extend_by_vec3(&colors_in[vids.a.val], &mut colors);
+            extend_by_vec3(&colors_in[vids.b.val], &mut colors);
+            extend_by_vec3(&colors_in[vids.c.val], &mut colors);
+        }
+        if has_wedge_normals {
+            extend_by_vec3(&normals_in[fid * 3], &mut normals);
+            extend_by_vec3(&normals_in[fid * 3 + 1], &mut normals);
+            extend_by_vec3(&normals_in[fid * 3 + 2], &mut normals);
+        } else if has_vertex_normals {
+            extend_by_vec3(&normals_in[vids.a.val], &mut normals);
+            extend_by_vec3(&normals_in[vids.b.val], &mut normals);
+            extend_by_vec3(&normals_in[vids.c.val], &mut normals);
+        }
         // 1 UV per face, each a point3D, is stored for each face,
         // for a total of 3 points per face.
         // UVs.
-        let uv_base_idx = fid * 3;
-        let uv0 = uv_in.get_d(uv_base_idx + 0);
-        extend_by_vertex(&uv0, &mut uvs);
-        let uv1 = uv_in.get_d(uv_base_idx + 1);
-        extend_by_vertex(&uv1, &mut uvs);
-        let uv2 = uv_in.get_d(uv_base_idx + 2);
-        extend_by_vertex(&uv2, &mut uvs);
-    }
-    // we always have to compute normals here since the pre-computed
-    // normals will not match, and it's better to have per-face
-    // normals anyway.
+        if has_wedge_uvs {
+            let uv_base_idx = fid * 3;
+            extend_by_vertex(&uv_in.get_d(uv_base_idx), &mut uvs);
+            extend_by_vertex(&uv_in.get_d(uv_base_idx + 1), &mut uvs);
+            extend_by_vertex(&uv_in.get_d(uv_base_idx + 2), &mut uvs);
+        } else if has_vertex_uvs {
+            extend_by_vertex(&uv_in.get_d(vids.a.val), &mut uvs);
+            extend_by_vertex(&uv_in.get_d(vids.b.val), &mut uvs);
+            extend_by_vertex(&uv_in.get_d(vids.c.val), &mut uvs);
+        }
+    }
+    let mut maybe_colors: Option<&[f32]> = None;
+    if has_wedge_colors || has_vertex_colors {
+        maybe_colors = Some(&colors[..]);
+    }
+    let mut maybe_uvs: Option<&[f32]> = None;
+    if has_wedge_uvs || has_vertex_uvs {
+        maybe_uvs = Some(&uvs[..]);
+    }
+    if !has_wedge_normals && !has_vertex_normals {
+        // Without normals from the file, the pre-computed
+        // normals would not match, and it's better to have
+        // per-face normals anyway.
+        normals = compute_normals(&all_indices[..], &vertices[..]);
+    }
+    let tangents =
+        maybe_uvs.map(|uvs| compute_tangents(&all_indices[..], &vertices[..], &normals[..], uvs));
     let vertex_data = VertexData::new(
         gl_ref,
         &vertices[..],
-        &compute_normals(&all_indices[..], &vertices[..]),
-    )
-    .unwrap();
+        &normals[..],
+        maybe_colors,
+        tangents
+            .as_ref()
+            .map(|(tangents, handedness)| (&tangents[..], &handedness[..])),
+    )?;
     let mut surfaces: Vec<MeshSurface> = vec![];
     for name_and_indices in surface_indices {
         info!("Material name {}", name_and_indices.0);
         let surface = MeshSurface::new(
             gl_ref,
+            &vertices[..],
             &name_and_indices.1[..],
-            Some(&uvs[..]),
-            Some(name_and_indices.0),
-        )
-        .unwrap();
+            maybe_uvs,
+            Some(name_and_indices.0.clone()),
+        )?;
         surfaces.push(surface);
     }
-    MeshContainer {
+    if surfaces.is_empty() {
+        // Files without materials have a single surface.
+        surfaces.push(MeshSurface::new(
+            gl_ref,
+            &vertices[..],
+            &all_indices[..],
+            maybe_uvs,
+            None,
+        )?);
+    }
+    Ok(MeshContainer {
+        bounds: BoundingBox::from_positions(&vertices[..]),
         vertices: vertex_data,
         surfaces: surfaces,
         visible: true,
-    }
+        transform: three_d::Mat4::identity(),
+        origin: *origin,
+        model_matrix: three_d::Mat4::identity(),
+        opacity: 1.0,
+        display_mode: DisplayMode::Shaded,
+        file: String::new(),
+    })
 }
 
 /// Divide a mesh by materials, in instances where the only
@@ -265,10 +433,18 @@ fn divide_mesh_by_materials_per_wedge(
 fn divide_mesh_by_materials(
     model: &mut Model,
     mesh: &Rust3DMesh,
+    origin: &Point64,
     material_info: &MaterialInfo,
     maybe_normals: Option<Vec<f32>>,
-) -> MeshContainer {
-    let vertices = create_vertex_data(model, mesh, maybe_normals);
+) -> BufferResult<MeshContainer> {
+    let vertices = create_vertex_data(
+        model,
+        mesh,
+        origin,
+        maybe_normals,
+        &material_info.colors[..],
+        &material_info.uv,
+    )?;
     info!("Adding model with {} vertices", mesh.num_vertices());
     let mut surfaces: Vec<MeshSurface> = vec![];
     if material_info.surfaces.len() > 0 {
@@ -281,10 +457,11 @@ fn divide_mesh_by_materials(
             let mesh_surface = create_mesh_surface(
                 model,
                 mesh,
+                &vertices.positions[..],
                 &material_info.uv,
                 Some(surface.0.clone()),
                 surface.1,
-            );
+            )?;
             surfaces.push(mesh_surface);
         }
     } else {
@@ -296,13 +473,245 @@ fn divide_mesh_by_materials(
             indices.push(vids.b.val as u32);
             indices.push(vids.c.val as u32);
         }
-        let surface = MeshSurface::new(gl_ref, &indices[..], None, None).unwrap();
+        let surface = MeshSurface::new(gl_ref, &vertices.positions[..], &indices[..], None, None)?;
         surfaces.push(surface);
     }
-    MeshContainer {
+    Ok(MeshContainer {
+        bounds: BoundingBox::from_positions(&vertices.positions[..]),
         vertices: vertices,
         surfaces: surfaces,
         visible: true,
+        transform: three_d::Mat4::identity(),
+        origin: *origin,
+        model_matrix: three_d::Mat4::identity(),
+        opacity: 1.0,
+        display_mode: DisplayMode::Shaded,
+        file: String::new(),
+    })
+}
+
+/// Create a point cloud from loaded points. Colors and normals
+/// are only used if there is one per point.
+fn create_point_cloud(
+    model: &mut Model,
+    points: &PointCloud3D<Point3D>,
+    colors: &[three_d::Vec3],
+    normals: &[three_d::Vec3],
+) -> BufferResult<PointCloudContainer> {
+    let origin = origin::origin_of(points.data.iter().map(|p| [p.x(), p.y(), p.z()]));
+    let model_matrix = scene_model_matrix(model, &three_d::Mat4::identity(), &origin);
+    let gl_ref = model.gl.as_ref().expect("GL Context not initialized!");
+    let mut positions = vec![];
+    for point in points.data.iter() {
+        extend_by_position(point, &origin, &mut positions);
+    }
+    let color_vec = flatten_vectors(colors);
+    let mut maybe_colors: Option<&[f32]> = None;
+    if colors.len() == points.len() {
+        maybe_colors = Some(&color_vec[..]);
+    }
+    let normal_vec = flatten_vectors(normals);
+    let mut maybe_normals: Option<&[f32]> = None;
+    if normals.len() == points.len() {
+        maybe_normals = Some(&normal_vec[..]);
+    }
+    info!("Adding point cloud with {} points", points.len());
+    let point_data = PointData::new(
+        gl_ref,
+        &positions[..],
+        maybe_normals,
+        maybe_colors,
+        &Material::new().diffuse_color,
+    )?;
+    Ok(PointCloudContainer {
+        points: point_data,
+        bounds: BoundingBox::from_positions(&positions[..]),
+        origin,
+        model_matrix,
+        point_size: 3.0,
+        visible: true,
+        file: String::new(),
+    })
+}
+
+/// Does the image have an alpha channel that isn't fully opaque?
+pub(crate) fn has_translucent_pixels(image: &image::DynamicImage) -> bool {
+    image.color().has_alpha() && image.to_rgba8().pixels().any(|pixel| pixel[3] < 255)
+}
+
+fn create_texture(model: &mut Model, file_name: &str, name: &str, image: &image::DynamicImage) {
+    let gl_ref = model.gl.as_ref().expect("GL Context not initialized!");
+    let (width, height) = image.dimensions();
+    info!("Loaded {}x{} image {}", name, width, height);
+    match three_d::texture::Texture2D::new_with_u8(
+        gl_ref,
+        three_d::Interpolation::Linear,
+        three_d::Interpolation::Linear,
+        Some(three_d::Interpolation::Linear),
+        // Texture coordinates outside of 0 to 1 repeat the texture, as
+        // scaled MTL textures and glTF expect.
+        three_d::Wrapping::Repeat,
+        three_d::Wrapping::Repeat,
+        width,
+        height,
+        &image.to_bytes()[..],
+    ) {
+        Ok(texture) => {
+            info!("Created texture {}", name);
+            model.images.insert(name.to_string(), texture);
+            if has_translucent_pixels(image) {
+                model.translucent_images.insert(name.to_string());
+            } else {
+                model.translucent_images.remove(name);
+            }
+        }
+        Err(e) => {
+            let warning = format!("Could not load {} as a texture due to error {:?}", name, e);
+            warn!("{}", warning);
+            model.diagnostics.warn(file_name, warning);
+        }
+    }
+}
+
+/// Add a mesh of a scene, as it should be drawn.
+fn add_scene_mesh(
+    model: &mut Model,
+    file_name: &str,
+    scene_mesh: &SceneMesh<Rust3DMesh>,
+) -> BufferResult<()> {
+    let mesh = &scene_mesh.mesh;
+    let material_info = &scene_mesh.material_info;
+    info!(
+        "{} has {} vertices and {} faces",
+        scene_mesh.name,
+        mesh.num_vertices(),
+        mesh.num_faces()
+    );
+    let origin = mesh_origin(mesh);
+    let mut container = if material_info.uv.len() == 3 * mesh.num_faces()
+        || material_info.normals.len() == 3 * mesh.num_faces()
+        || material_info.colors.len() == 3 * mesh.num_faces()
+    {
+        // Per-wedge UVs, normals or colors. If a model has both per-wedge
+        // and per-vertex UVs we should prefer per-wedge.
+        divide_mesh_by_materials_per_wedge(model, mesh, &origin, material_info)?
+    } else {
+        // Per-vetex UVs
+        let mut maybe_normals = None;
+        if material_info.normals.len() == mesh.num_vertices() {
+            maybe_normals = Some(flatten_vectors(&material_info.normals[..]));
+        }
+        divide_mesh_by_materials(model, mesh, &origin, material_info, maybe_normals)?
+    };
+    container.transform = three_d::Mat4::from(scene_mesh.transform);
+    container.model_matrix = scene_model_matrix(model, &container.transform, &origin);
+    container.file = file_name.to_string();
+    model.mesh.insert(scene_mesh.name.clone(), container);
+    Ok(())
+}
+
+/// Add everything loaded from a file. Returns whether there is
+/// anything new to draw.
+fn add_scene(model: &mut Model, file_name: &str, scene: Scene<Rust3DMesh>) -> ShouldRender {
+    let mut update = false;
+    // Meshes or points that were added, which the camera frames.
+    let mut added = false;
+    for warning in scene.warnings.iter() {
+        warn!("{}: {}", file_name, warning);
+        model.diagnostics.warn(file_name, warning.clone());
+    }
+    for (name, image) in scene.images.iter() {
+        create_texture(model, file_name, name, image);
+        update = true;
+    }
+    for (name, material) in scene.materials {
+        info!("Loading material {}: {:?}.", name, material);
+        model.materials.insert(name, material);
+        update = true;
+    }
+    for scene_mesh in scene.meshes.iter() {
+        for warning in scene_mesh.material_info.warnings.iter() {
+            warn!("{}: {}", scene_mesh.name, warning);
+            model
+                .diagnostics
+                .warn(file_name, format!("{}: {}", scene_mesh.name, warning));
+        }
+        if let Err(e) = add_scene_mesh(model, file_name, scene_mesh) {
+            let warning = format!("Could not add {} due to error {:?}", scene_mesh.name, e);
+            warn!("{}", warning);
+            model.diagnostics.warn(file_name, warning);
+            continue;
+        }
+        update = true;
+        added = true;
+    }
+    for scene_points in scene.point_clouds.iter() {
+        match create_point_cloud(
+            model,
+            &scene_points.points,
+            &scene_points.colors[..],
+            &scene_points.normals[..],
+        ) {
+            Ok(mut point_cloud) => {
+                point_cloud.file = file_name.to_string();
+                model
+                    .point_clouds
+                    .insert(scene_points.name.clone(), point_cloud);
+                update = true;
+                added = true;
+            }
+            Err(e) => {
+                let warning = format!("Could not add {} due to error {:?}", scene_points.name, e);
+                warn!("{}", warning);
+                model.diagnostics.warn(file_name, warning);
+            }
+        }
+    }
+    if added {
+        render_canvas::frame_all(model);
+    }
+    if !scene.resources.is_empty() {
+        model.resources.extend(scene.resources);
+        // Files that were waiting for one of these can be loaded now.
+        for pending in std::mem::take(&mut model.pending_files) {
+            update = load_file(model, &pending) || update;
+        }
+    }
+    update
+}
+
+/// Load a file with the format the registry finds for it. Files that
+/// refer to other files which haven't been loaded yet are kept until
+/// those arrive.
+fn load_file(model: &mut Model, file: &FileData) -> ShouldRender {
+    info!("Loading file {}", file.name);
+    model.diagnostics.start(&file.name);
+    match model
+        .formats
+        .load(&file.name, &file.content[..], &model.resources)
+    {
+        Ok(scene) => {
+            model.diagnostics.set_status(&file.name, FileStatus::Loaded);
+            add_scene(model, &file.name, scene)
+        }
+        Err(Error {
+            kind: ErrorKind::MissingResource(resource),
+            ..
+        }) => {
+            info!("{} is waiting for {}", file.name, resource);
+            model.pending_files.push(file.clone());
+            model
+                .diagnostics
+                .set_status(&file.name, FileStatus::Waiting(resource));
+            true
+        }
+        Err(e) => {
+            warn!("Could not load {} due to {}", file.name, e);
+            model
+                .diagnostics
+                .set_status(&file.name, FileStatus::Failed(e.to_string()));
+            true
+        }
     }
 }
 
@@ -310,159 +719,17 @@ pub fn update(model: &mut Model, msg: &Msg) -> ShouldRender {
     match msg {
         Msg::Files(files) => {
             for file in files.into_iter() {
+                model.diagnostics.start(&file.name());
                 let task = {
                     let callback = model.link.callback(Msg::Loaded);
                     model.reader.read_file(file.clone(), callback).unwrap()
                 };
                 model.tasks.push(task);
             }
+            return !files.is_empty();
         }
         Msg::Loaded(file) => {
-            let path = Path::new(&file.name);
-            info!("Loading file {}", file.name);
-            if let Some(ext) = path.extension() {
-                let maybe_image_format =
-                    string_to_format(&ext.to_os_string().into_string().unwrap().to_lowercase());
-                if let Some(image_format) = maybe_image_format {
-                    info!("Loading image {}", file.name);
-                    match image::load_from_memory_with_format(&file.content[..], image_format) {
-                        Ok(image) => {
-                            let gl_ref = model.gl.as_ref().expect("GL Context not initialized!");
-                            let (width, height) = image.dimensions();
-                            info!("Loaded {}x{} image {}", file.name, width, height);
-                            match three_d::texture::Texture2D::new_with_u8(
-                                gl_ref,
-                                three_d::Interpolation::Linear,
-                                three_d::Interpolation::Linear,
-                                Some(three_d::Interpolation::Linear),
-                                three_d::Wrapping::ClampToEdge,
-                                three_d::Wrapping::ClampToEdge,
-                                width,
-                                height,
-                                &image.to_bytes()[..],
-                            ) {
-                                Ok(texture) => {
-                                    info!("Created texture {}", file.name);
-                                    model.images.insert(file.name.clone(), texture);
-                                }
-                                Err(e) => {
-                                    info!(
-                                        "Could not load {} as a texture due to error {:?}",
-                                        file.name, e
-                                    );
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            info!(
-                                "Could not load {} as an image due to error {}",
-                                file.name, e
-                            );
-                        }
-                    }
-                } else if ext == "ply" || ext == "obj" {
-                    let mut m = Rust3DMesh::default();
-                    let mut material_info = MaterialInfo::new();
-                    let mut maybe_normals = None;
-                    if ext == "ply" {
-                        let mut material = Material::new();
-                        match load_ply(
-                            &mut &file.content[..],
-                            &mut m,
-                            &mut material,
-                            &mut material_info,
-                            &file.name,
-                        ) {
-                            Ok(_) => {
-                                model.materials.insert(file.name.clone(), material);
-                            }
-                            Err(e) => {
-                                warn!("Could not load {} as a PLY due to {:?}", file.name, e);
-                            }
-                        }
-                    } else if ext == "obj" {
-                        match load_obj_mesh(&mut &file.content[..], &mut m, &mut material_info) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                warn!("Could not load {} as an OBJ due to {:?}", file.name, e);
-                            }
-                        }
-                    } else if ext == "off" {
-                        match load_off_mesh(&mut &file.content[..], &mut m) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                warn!("Could not load {} as an off due to {:?}", file.name, e);
-                            }
-                        }
-                    } else if ext == "stl" {
-                        let mut r3d_normals = vec![];
-                        match load_stl_mesh_unique(
-                            &mut &file.content[..],
-                            rust_3d::io::StlFormat::Auto,
-                            &mut m,
-                            &mut r3d_normals,
-                        ) {
-                            Ok(_) => {
-                                let mut normals = vec![];
-                                for normal in r3d_normals {
-                                    normals.push(normal.x as f32);
-                                    normals.push(normal.x as f32);
-                                    normals.push(normal.z as f32);
-                                }
-                                maybe_normals = Some(normals);
-                                let meshes = divide_mesh_by_materials(
-                                    model,
-                                    &m,
-                                    &material_info,
-                                    maybe_normals,
-                                );
-                                model.mesh.insert(file.name.clone(), meshes);
-                            }
-                            Err(e) => {
-                                warn!("Could not load {} as an off due to {:?}", file.name, e);
-                            }
-                        }
-                        return true;
-                    }
-                    if m.num_vertices() == 0 {
-                        return false;
-                    }
-                    info!(
-                        "{} has {} vertices and {} indices",
-                        file.name,
-                        m.num_vertices(),
-                        m.num_faces()
-                    );
-                    if material_info.uv.len() == 3 * m.num_faces() {
-                        // Per-wedge UVs. If a model has both per-wedge and per-vertex
-                        // UVs we should prefer per-wedge.
-                        let meshes = divide_mesh_by_materials_per_wedge(model, &m, &material_info);
-                        model.mesh.insert(file.name.clone(), meshes);
-                    } else {
-                        // Per-vetex UVs
-                        let meshes =
-                            divide_mesh_by_materials(model, &m, &material_info, maybe_normals);
-                        model.mesh.insert(file.name.clone(), meshes);
-                    }
-                    return true;
-                } else if ext == "mtl" {
-                    info!("Loading an MTL file.");
-                    match load_mtl(&mut &file.content[..]) {
-                        Ok(materials) => {
-                            for material in materials {
-                                info!("Loading material {}: {:?}.", material.0, material.1);
-                                model.materials.insert(material.0, material.1);
-                            }
-                        }
-                        Err(e) => {
-                            warn!(
-                                "Could not load {} as an mtl file due to error: {:?}",
-                                file.name, e
-                            );
-                        }
-                    }
-                }
-            }
+            return load_file(model, file);
         }
         _ => {}
     };
@@ -471,7 +738,7 @@ pub fn update(model: &mut Model, msg: &Msg) -> ShouldRender {
 pub fn view(model: &Model) -> Html {
     html! {
         <form>
-        <input id="load_mesh" type="file" multiple=true accept=".ply, .obj, .off, .stl, .mtl, .png, .jpeg, .jpg, .gif, .webp, .pnm, .tif, .tiff, .tga, .dds, .bmp, .ico, .hdr" onchange=model.link.callback(move |value| {
+        <input id="load_mesh" type="file" multiple=true accept=model.formats.accept() onchange=model.link.callback(move |value| {
             let mut result = Vec::new();
             if let ChangeData::Files(files) = value {
                 let files = js_sys::try_iter(&files)
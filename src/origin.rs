@@ -0,0 +1,69 @@
+//! Coordinates far from zero, such as UTM or ECEF ones, lose their
+//! precision as f32. Vertices are kept relative to an f64 origin of
+//! their mesh, and meshes are drawn relative to an origin that the scene
+//! shares, so that they stay precise and line up with each other.
+
+use three_d::*;
+
+/// Point in the f64 coordinates that files are loaded in.
+pub type Point64 = [f64; 3];
+
+/// Origin for points, the center of their bounding box rounded to whole
+/// units. Points around zero keep zero as their origin.
+pub fn origin_of<I>(points: I) -> Point64
+where
+    I: Iterator<Item = Point64>,
+{
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for point in points {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(point[axis]);
+            max[axis] = max[axis].max(point[axis]);
+        }
+    }
+    let mut origin = [0.0; 3];
+    for axis in 0..3 {
+        if min[axis] <= max[axis] {
+            origin[axis] = ((min[axis] + max[axis]) * 0.5).round();
+        }
+    }
+    origin
+}
+
+/// Transforms a point in f64, as an f32 model matrix would.
+pub fn transform_point(transform: &Mat4, point: &Point64) -> Point64 {
+    let mut result = [0.0; 3];
+    for (row, value) in result.iter_mut().enumerate() {
+        *value = transform[3][row] as f64
+            + (0..3)
+                .map(|column| transform[column][row] as f64 * point[column])
+                .sum::<f64>();
+    }
+    result
+}
+
+/// Model matrix for vertices relative to `origin`, which `transform`
+/// places in the file, drawn relative to `scene_origin`. The large
+/// translation between the two is taken in f64, before it is made f32.
+pub fn model_matrix(transform: &Mat4, origin: &Point64, scene_origin: &Point64) -> Mat4 {
+    let placed = transform_point(transform, origin);
+    let mut matrix = *transform;
+    matrix[3] = vec4(
+        (placed[0] - scene_origin[0]) as f32,
+        (placed[1] - scene_origin[1]) as f32,
+        (placed[2] - scene_origin[2]) as f32,
+        1.0,
+    );
+    matrix
+}
+
+/// Position in the coordinates of the file, before its transform, of a
+/// vertex relative to `origin`.
+pub fn to_original(origin: &Point64, relative: &[f32]) -> Point64 {
+    [
+        origin[0] + relative[0] as f64,
+        origin[1] + relative[1] as f64,
+        origin[2] + relative[2] as f64,
+    ]
+}
@@ -0,0 +1,156 @@
+use crate::deferred_renderer::{use_pbr_material, MaterialPrograms, Pass};
+use crate::io::{Material, PbrMaterial};
+use crate::render_buffers::MeshSurface;
+use crate::textured_mesh_renderer::{
+    texture_alpha, texture_maps_programs, use_surface_detail, use_texture_map, white_texture,
+    MaterialTextures,
+};
+use crate::MeshContainer;
+use three_d::*;
+
+/// Renders surfaces with metallic-roughness materials, with or without
+/// textures.
+pub struct PbrMeshRenderer {
+    shader: MaterialPrograms,
+    vertex_color_shader: MaterialPrograms,
+    textured_shader: MaterialPrograms,
+    /// Variant for materials with bump or normal maps, on meshes with
+    /// tangents.
+    normal_mapped_shader: MaterialPrograms,
+    /// Used in place of textures that a material doesn't have.
+    white: Texture2D,
+}
+
+impl PbrMeshRenderer {
+    pub fn new(gl: &Gl) -> PbrMeshRenderer {
+        let fragment_shader = include_str!("shaders/pbr.frag");
+        PbrMeshRenderer {
+            shader: MaterialPrograms::new(
+                gl,
+                include_str!("shaders/mesh_shaded.vert"),
+                fragment_shader,
+            ),
+            vertex_color_shader: MaterialPrograms::new(
+                gl,
+                include_str!("shaders/mesh_vertex_colored.vert"),
+                &format!("#define VERTEX_COLOR\n{}", fragment_shader),
+            ),
+            textured_shader: texture_maps_programs(gl, "#define TEXTURED\n", fragment_shader),
+            normal_mapped_shader: texture_maps_programs(
+                gl,
+                "#define TEXTURED\n#define NORMAL_MAP\n",
+                fragment_shader,
+            ),
+            white: white_texture(gl),
+        }
+    }
+
+    /// Materials without metallic-roughness properties are drawn with
+    /// the defaults.
+    pub fn render(
+        &self,
+        pass: Pass,
+        camera: &camera::Camera,
+        mesh: &MeshContainer,
+        mesh_surface: &MeshSurface,
+        material: &Material,
+        textures: &MaterialTextures,
+    ) {
+        let transformation = &mesh.model_matrix;
+        let vertex_data = &mesh.vertices;
+        let default_pbr = PbrMaterial::new();
+        let pbr = material.pbr.as_ref().unwrap_or(&default_pbr);
+        let uvs = match &mesh_surface.maybe_uvs {
+            Some(uvs) if !textures.is_empty() => Some(uvs),
+            _ => None,
+        };
+        let tangents = match &vertex_data.maybe_tangents {
+            Some(tangents) if uvs.is_some() && textures.has_surface_detail() => Some(tangents),
+            _ => None,
+        };
+        // Vertex colors, when present, take the place of the base color
+        // of surfaces without textures.
+        let programs = match (uvs, tangents, &vertex_data.maybe_colors) {
+            (Some(_), Some(_), _) => &self.normal_mapped_shader,
+            (Some(_), None, _) => &self.textured_shader,
+            (None, _, Some(_)) => &self.vertex_color_shader,
+            (None, _, None) => &self.shader,
+        };
+        let program = programs.program(pass, camera);
+        use_pbr_material(program, material, pbr);
+
+        if let Some(uvs) = uvs {
+            let maps = [
+                (
+                    "texture0",
+                    textures.diffuse,
+                    Some(&material.texture_options),
+                ),
+                (
+                    "metallic_map",
+                    textures.metallic,
+                    pbr.metallic_map.as_ref().map(|map| &map.options),
+                ),
+                (
+                    "roughness_map",
+                    textures.roughness,
+                    pbr.roughness_map.as_ref().map(|map| &map.options),
+                ),
+                (
+                    "occlusion_map",
+                    textures.occlusion,
+                    pbr.occlusion_map.as_ref().map(|map| &map.options),
+                ),
+                (
+                    "emissive_map",
+                    textures.emissive,
+                    material.emissive_map.as_ref().map(|map| &map.options),
+                ),
+            ];
+            for (name, texture, options) in maps.iter() {
+                use_texture_map(program, name, *texture, *options, &self.white);
+            }
+            for (name, options) in maps[1..4].iter().map(|map| (map.0, map.2)) {
+                let mut channel = vec4(0.0, 0.0, 0.0, 0.0);
+                channel[options.map_or(0, |options| options.channel)] = 1.0;
+                program
+                    .add_uniform_vec4(&format!("{}_channel", name), &channel)
+                    .unwrap();
+            }
+            program
+                .add_uniform_float("occlusion_strength", &pbr.occlusion_strength)
+                .unwrap();
+            program
+                .add_uniform_float("texture_alpha", &texture_alpha(material))
+                .unwrap();
+            if let Some(tangents) = tangents {
+                use_surface_detail(program, material, textures, tangents, &self.white);
+            }
+            program.use_attribute_vec3_float(uvs, "uvw").unwrap();
+        }
+        match (uvs, &vertex_data.maybe_colors) {
+            (None, Some(colors)) => program.use_attribute_vec3_float(colors, "color").unwrap(),
+            _ => program
+                .add_uniform_vec3("color", &material.diffuse_color)
+                .unwrap(),
+        }
+
+        program
+            .add_uniform_mat4("modelMatrix", transformation)
+            .unwrap();
+        program.use_uniform_block(camera.matrix_buffer(), "Camera");
+        program
+            .add_uniform_mat4(
+                "normalMatrix",
+                &transformation.invert().unwrap().transpose(),
+            )
+            .unwrap();
+        program
+            .use_attribute_vec3_float(&vertex_data.position_buffer, "position")
+            .unwrap();
+        program
+            .use_attribute_vec3_float(&vertex_data.normal_buffer, "normal")
+            .unwrap();
+        program.draw_elements(&mesh_surface.index_buffer);
+    }
+}
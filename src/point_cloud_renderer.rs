@@ -0,0 +1,71 @@
+use crate::deferred_renderer::{material_shader, use_material};
+use crate::io::Material;
+use crate::render_buffers::PointData;
+use three_d::*;
+
+pub struct PointCloudRenderer {
+    shader: program::Program,
+    corner_buffer: VertexBuffer,
+}
+
+impl PointCloudRenderer {
+    pub fn new(gl: &Gl) -> PointCloudRenderer {
+        // Two triangles covering a quad, instanced once per point.
+        let corners: [f32; 12] = [
+            -1.0, -1.0, 1.0, -1.0, 1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, 1.0,
+        ];
+        PointCloudRenderer {
+            shader: program::Program::from_source(
+                gl,
+                include_str!("shaders/point.vert"),
+                &material_shader(include_str!("shaders/point.frag")),
+            )
+            .unwrap(),
+            corner_buffer: VertexBuffer::new_with_static_f32(gl, &corners).unwrap(),
+        }
+    }
+
+    /// Renders each point as a disc with a diameter of `point_size`
+    /// pixels.
+    pub fn render(
+        &self,
+        transformation: &Mat4,
+        camera: &camera::Camera,
+        point_data: &PointData,
+        point_size: f32,
+        viewport_size: &Vec2,
+        material: &Material,
+    ) {
+        let program = &self.shader;
+        use_material(program, material);
+        program
+            .add_uniform_float("point_size", &point_size)
+            .unwrap();
+        program
+            .add_uniform_vec2("viewport_size", viewport_size)
+            .unwrap();
+        program
+            .add_uniform_mat4("modelMatrix", transformation)
+            .unwrap();
+        program.use_uniform_block(camera.matrix_buffer(), "Camera");
+        program
+            .add_uniform_mat4(
+                "normalMatrix",
+                &transformation.invert().unwrap().transpose(),
+            )
+            .unwrap();
+        program
+            .use_attribute_vec2_float(&self.corner_buffer, "corner")
+            .unwrap();
+        program
+            .use_attribute_vec3_float_divisor(&point_data.position_buffer, "position", 1)
+            .unwrap();
+        program
+            .use_attribute_vec3_float_divisor(&point_data.normal_buffer, "normal", 1)
+            .unwrap();
+        program
+            .use_attribute_vec3_float_divisor(&point_data.color_buffer, "color", 1)
+            .unwrap();
+        program.draw_arrays_instanced(6, point_data.num_points as u32);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::bounds::BoundingBox;
 use three_d::objects::Error;
 use three_d::*;
 
@@ -15,6 +16,20 @@ pub struct MeshSurface {
 
     /// The name of the material, if there is one.
     pub maybe_material_name: Option<String>,
+
+    /// CPU copy of the indices, for exporting.
+    pub indices: Vec<u32>,
+
+    /// CPU copy of the UVs, for exporting.
+    pub uvs: Option<Vec<f32>>,
+
+    /// Center of the bounding box of the faces, to sort transparent
+    /// surfaces by.
+    pub center: Vec3,
+
+    /// Corners of the faces, to draw their edges with. Made the first
+    /// time the edges are shown.
+    pub maybe_edges: Option<EdgeData>,
 }
 
 /// Everything associated with vertices. We separate vertex data from
@@ -28,16 +43,93 @@ pub struct VertexData {
 
     /// per-vertex normals
     pub normal_buffer: VertexBuffer,
+
+    /// per-vertex colors. A 1D float array with a stride of 3.
+    pub maybe_colors: Option<VertexBuffer>,
+
+    /// per-vertex tangents, for meshes with UVs.
+    pub maybe_tangents: Option<TangentData>,
+
+    /// CPU copy of the positions, for exporting.
+    pub positions: Vec<f32>,
+
+    /// CPU copy of the normals, for exporting.
+    pub normals: Vec<f32>,
+
+    /// CPU copy of the colors, for exporting.
+    pub colors: Option<Vec<f32>>,
+}
+
+/// Tangent space of each vertex, which normal and bump maps are
+/// relative to.
+pub struct TangentData {
+    /// Tangents, pointing along increasing U. A 1D float array with a
+    /// stride of 3.
+    pub tangent_buffer: VertexBuffer,
+
+    /// 1 where the bitangent, pointing along increasing V, is the cross
+    /// product of the normal and the tangent, -1 where UVs are mirrored.
+    pub handedness_buffer: VertexBuffer,
+}
+
+/// The corners of every face of a surface, each face with three of its
+/// own, so that the edge shader knows which corner is which from the
+/// vertex index.
+pub struct EdgeData {
+    /// Positions. a 1D array with a stride of 3, three per face.
+    pub position_buffer: VertexBuffer,
+
+    /// Number of corners in the buffer.
+    pub num_corners: usize,
+}
+
+/// Everything associated with a point cloud. Points are drawn as
+/// camera-facing quads, so every buffer holds one entry per point
+/// and is used once per instance.
+pub struct PointData {
+    /// Positions. a 1D array with a stride of 3.
+    pub position_buffer: VertexBuffer,
+
+    /// per-point normals. Zero where a point has no normal.
+    pub normal_buffer: VertexBuffer,
+
+    /// per-point colors. A 1D float array with a stride of 3.
+    pub color_buffer: VertexBuffer,
+
+    /// Number of points in the buffers.
+    pub num_points: usize,
 }
 
 impl VertexData {
-    pub fn new(gl: &Gl, positions: &[f32], normals: &[f32]) -> Result<Self, Error> {
+    pub fn new(
+        gl: &Gl,
+        positions: &[f32],
+        normals: &[f32],
+        maybe_colors_cpu: Option<&[f32]>,
+        maybe_tangents_cpu: Option<(&[f32], &[f32])>,
+    ) -> Result<Self, Error> {
         let position_buffer = VertexBuffer::new_with_static_f32(gl, positions)?;
         let normal_buffer = VertexBuffer::new_with_static_f32(gl, normals)?;
+        let mut maybe_colors_gpu: Option<VertexBuffer> = None;
+        if let Some(colors_cpu) = maybe_colors_cpu {
+            maybe_colors_gpu = Some(VertexBuffer::new_with_static_f32(gl, colors_cpu)?);
+        }
+        let mut maybe_tangents: Option<TangentData> = None;
+        if let Some((tangents, handedness)) = maybe_tangents_cpu {
+            maybe_tangents = Some(TangentData {
+                tangent_buffer: VertexBuffer::new_with_static_f32(gl, tangents)?,
+                handedness_buffer: VertexBuffer::new_with_static_f32(gl, handedness)?,
+            });
+        }
 
         Ok(VertexData {
             position_buffer,
             normal_buffer,
+            maybe_colors: maybe_colors_gpu,
+            maybe_tangents,
+            positions: positions.to_vec(),
+            normals: normals.to_vec(),
+            colors: maybe_colors_cpu.map(|colors| colors.to_vec()),
         })
     }
 }
@@ -45,6 +137,7 @@ impl VertexData {
 impl MeshSurface {
     pub fn new(
         gl: &Gl,
+        positions: &[f32],
         indices: &[u32],
         maybe_uvs_cpu: Option<&[f32]>,
         maybe_material_name: Option<String>,
@@ -63,6 +156,81 @@ impl MeshSurface {
             index_buffer: index_buffer,
             maybe_uvs: maybe_uvs_gpu,
             maybe_material_name: maybe_material_name,
+            indices: indices.to_vec(),
+            uvs: maybe_uvs_cpu.map(|uvs| uvs.to_vec()),
+            center: bounds_center(positions, indices),
+            maybe_edges: None,
+        })
+    }
+}
+
+impl EdgeData {
+    pub fn new(gl: &Gl, positions: &[f32], indices: &[u32]) -> Result<Self, Error> {
+        let corners = corner_positions(positions, indices);
+        Ok(EdgeData {
+            position_buffer: VertexBuffer::new_with_static_f32(gl, &corners)?,
+            num_corners: corners.len() / 3,
+        })
+    }
+}
+
+/// Positions of the corners of indexed triangles, one after the other.
+pub fn corner_positions(positions: &[f32], indices: &[u32]) -> Vec<f32> {
+    indices
+        .iter()
+        .flat_map(|&index| positions[index as usize * 3..index as usize * 3 + 3].iter())
+        .copied()
+        .collect()
+}
+
+/// Center of the bounding box of the indexed positions.
+fn bounds_center(positions: &[f32], indices: &[u32]) -> Vec3 {
+    let mut bounds = BoundingBox::new();
+    for &index in indices.iter() {
+        let p = &positions[index as usize * 3..index as usize * 3 + 3];
+        bounds.add_point(vec3(p[0], p[1], p[2]));
+    }
+    if bounds.is_empty() {
+        vec3(0.0, 0.0, 0.0)
+    } else {
+        bounds.center()
+    }
+}
+
+impl PointData {
+    pub fn new(
+        gl: &Gl,
+        positions: &[f32],
+        maybe_normals_cpu: Option<&[f32]>,
+        maybe_colors_cpu: Option<&[f32]>,
+        default_color: &Vec3,
+    ) -> Result<Self, Error> {
+        let num_points = positions.len() / 3;
+        let position_buffer = VertexBuffer::new_with_static_f32(gl, positions)?;
+        let normal_buffer = match maybe_normals_cpu {
+            Some(normals_cpu) => VertexBuffer::new_with_static_f32(gl, normals_cpu)?,
+            None => VertexBuffer::new_with_static_f32(gl, &vec![0.0; positions.len()][..])?,
+        };
+        let color_buffer = match maybe_colors_cpu {
+            Some(colors_cpu) => VertexBuffer::new_with_static_f32(gl, colors_cpu)?,
+            None => {
+                let mut colors_cpu = Vec::with_capacity(positions.len());
+                for _ in 0..num_points {
+                    colors_cpu.extend_from_slice(&[
+                        default_color.x,
+                        default_color.y,
+                        default_color.z,
+                    ]);
+                }
+                VertexBuffer::new_with_static_f32(gl, &colors_cpu[..])?
+            }
+        };
+
+        Ok(PointData {
+            position_buffer,
+            normal_buffer,
+            color_buffer,
+            num_points,
         })
     }
 }
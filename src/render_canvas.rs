@@ -1,7 +1,16 @@
-use super::{Model, Msg, ShouldRender};
+use super::{MeshContainer, Model, Msg, ShouldRender};
+use crate::bounds::{BoundingBox, Framing};
+use crate::deferred_renderer::{DeferredRenderer, ForwardLights, Pass};
+use crate::edge_renderer::EdgeRenderer;
 use crate::io::Material;
-use crate::textured_mesh_renderer::TexturedMeshRenderer;
+use crate::pbr_mesh_renderer::PbrMeshRenderer;
+use crate::point_cloud_renderer::PointCloudRenderer;
+use crate::render_buffers::MeshSurface;
+use crate::textured_mesh_renderer::{MaterialTextures, TexturedMeshRenderer};
 use crate::untextured_mesh_renderer::UntexturedMeshRenderer;
+use log::warn;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use three_d::*;
 use wasm_bindgen::JsCast;
 use web_sys::HtmlCanvasElement;
@@ -10,17 +19,102 @@ use yew::services::RenderService;
 
 static ZOOM_SPEED: f32 = 0.01f32;
 static MOVE_SPEED: f32 = 0.01f32;
+/// Vertical field of view of the camera, in degrees.
+static FIELD_OF_VIEW: f32 = 45.0;
+/// Distance of the camera from its target before anything is framed,
+/// which the speeds above are for.
+static DEFAULT_DISTANCE: f32 = 2.0;
+
+/// Framing of the camera before anything is framed.
+pub fn default_framing() -> Framing {
+    Framing {
+        target: vec3(0.0, 0.0, 0.0),
+        distance: DEFAULT_DISTANCE,
+        z_near: 0.1,
+        z_far: 150.0,
+    }
+}
 
 pub fn update(model: &mut Model, msg: &Msg) -> ShouldRender {
     match msg {
         Msg::Render(timestamp) => {
             render_gl(model, *timestamp);
         }
+        Msg::FrameAll => frame_all(model),
+        Msg::FrameMesh(name) => {
+            let bounds = bounds_of(model, |entry, _, _| entry == name);
+            frame(model, &bounds);
+        }
+        Msg::FrameFile(file) => {
+            let bounds = bounds_of(model, |_, entry_file, _| entry_file == file);
+            frame(model, &bounds);
+        }
         _ => {}
     };
     false
 }
 
+/// Bounds of the meshes and point clouds that `include` is true for,
+/// given their name, file and whether they are visible.
+fn bounds_of<F>(model: &Model, include: F) -> BoundingBox
+where
+    F: Fn(&str, &str, bool) -> bool,
+{
+    let mut bounds = BoundingBox::new();
+    for (name, mesh) in model.mesh.iter() {
+        if include(name, &mesh.file, mesh.visible) {
+            bounds.add_box(&mesh.bounds.transformed(&mesh.model_matrix));
+        }
+    }
+    for (name, point_cloud) in model.point_clouds.iter() {
+        if include(name, &point_cloud.file, point_cloud.visible) {
+            bounds.add_box(&point_cloud.bounds.transformed(&point_cloud.model_matrix));
+        }
+    }
+    bounds
+}
+
+/// Frames everything that is visible, or everything that is loaded when
+/// nothing is.
+pub fn frame_all(model: &mut Model) {
+    let mut bounds = bounds_of(model, |_, _, visible| visible);
+    if bounds.is_empty() {
+        bounds = bounds_of(model, |_, _, _| true);
+    }
+    frame(model, &bounds);
+}
+
+/// Moves the camera to see all of `bounds` from the direction it looks
+/// in, with near and far planes that fit them.
+fn frame(model: &mut Model, bounds: &BoundingBox) {
+    if bounds.is_empty() {
+        return;
+    }
+    let aspect = match &model.canvas {
+        Some(canvas) if canvas.height() > 0 => canvas.width() as f32 / canvas.height() as f32,
+        _ => 1.0,
+    };
+    let camera = match model.camera.as_mut() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let framing = Framing::new(bounds, degrees(FIELD_OF_VIEW), aspect);
+    let direction = (*camera.position() - *camera.target()).normalize();
+    let up = *camera.up();
+    camera.set_view(
+        framing.target + direction * framing.distance,
+        framing.target,
+        up,
+    );
+    camera.set_perspective_projection(
+        degrees(FIELD_OF_VIEW),
+        aspect,
+        framing.z_near,
+        framing.z_far,
+    );
+    model.framing = framing;
+}
+
 fn render_gl(model: &mut Model, _timestamp: f64) {
     let gl = model.gl.as_ref().expect("GL Context not initialized!");
     let screen_width = model.canvas.as_ref().unwrap().width();
@@ -31,6 +125,9 @@ fn render_gl(model: &mut Model, _timestamp: f64) {
         .unwrap()
         .set_size(screen_width as f32, screen_height as f32);
 
+    // Moving the camera is as fast on screen however far away it is.
+    let camera = model.camera.as_ref().unwrap();
+    let speed = (*camera.position() - *camera.target()).magnitude() / DEFAULT_DISTANCE;
     for mouse_event in model.mouse_events.iter() {
         if mouse_event.shift_key() || model.mouse_down == 2 {
             let target = model.camera.as_ref().unwrap().target();
@@ -38,131 +135,143 @@ fn render_gl(model: &mut Model, _timestamp: f64) {
             let up = model.camera.as_ref().unwrap().up();
             let forward = target - position;
             let right = forward.cross(*up);
-            let translation_y = MOVE_SPEED * (mouse_event.movement_y() as f32) * up;
-            let translation_x = -1f32 * MOVE_SPEED * (mouse_event.movement_x() as f32) * right;
+            let translation_y = speed * MOVE_SPEED * (mouse_event.movement_y() as f32) * up;
+            let translation_x =
+                -1f32 * speed * MOVE_SPEED * (mouse_event.movement_x() as f32) * right;
             let translation = translation_x + translation_y;
             model.camera.as_mut().unwrap().translate(&translation);
         } else {
             model.camera.as_mut().unwrap().rotate(
-                mouse_event.movement_x() as f32,
-                mouse_event.movement_y() as f32,
+                speed * mouse_event.movement_x() as f32,
+                speed * mouse_event.movement_y() as f32,
             );
         }
     }
     model.mouse_events = vec![];
+    // The camera zooms by a fraction of its distance, so small objects
+    // can be zoomed into as well as large ones.
     for wheel_event in model.wheel_events.iter() {
-        model
-            .camera
-            .as_mut()
-            .unwrap()
-            .zoom(ZOOM_SPEED * wheel_event.delta_y() as f32);
+        let camera = model.camera.as_mut().unwrap();
+        let target = *camera.target();
+        let up = *camera.up();
+        let offset = *camera.position() - target;
+        let distance = model.framing.zoomed_distance(
+            offset.magnitude(),
+            ZOOM_SPEED / DEFAULT_DISTANCE * wheel_event.delta_y() as f32,
+        );
+        camera.set_view(target + offset.normalize() * distance, target, up);
     }
     model.wheel_events = vec![];
 
-    let ambient_light0 = AmbientLight::new(&gl, 0.2, &vec3(0.8, 0.8, 0.8)).unwrap();
-    let directional_light0 =
-        DirectionalLight::new(&gl, 1.0, &vec3(0.8, 0.8, 0.8), &vec3(0.0, -1.0, 0.0)).unwrap();
-    let directional_light1 =
-        DirectionalLight::new(&gl, 1.0, &vec3(0.8, 0.8, 0.8), &vec3(0.0, 1.0, 0.0)).unwrap();
-    let point_light0 = PointLight::new(
-        &gl,
-        0.8,
-        &vec3(0.8, 0.8, 0.8),
-        &vec3(-5.0, 0.0, 0.0),
-        0.5,
-        0.05,
-        0.005,
-    )
-    .unwrap();
-    let point_light1 = PointLight::new(
-        &gl,
-        0.8,
-        &vec3(0.8, 0.8, 0.8),
-        &vec3(5.0, 0.0, 0.0),
-        0.5,
-        0.05,
-        0.005,
-    )
-    .unwrap();
-    let renderer = model.renderer.as_mut().unwrap();
+    let lights = &model.lights;
     let camera = model.camera.as_ref().unwrap();
-    let mesh_groups = &model.mesh;
-    let materials = &model.materials;
-    let images = &model.images;
-    let untextured = model.untextured_mesh_renderer.as_ref();
-    let textured = model.textured_mesh_renderer.as_ref();
-    renderer
+    let renderers = MeshRenderers {
+        untextured: model.untextured_mesh_renderer.as_ref().unwrap(),
+        textured: model.textured_mesh_renderer.as_ref().unwrap(),
+        pbr: model.pbr_mesh_renderer.as_ref().unwrap(),
+        images: &model.images,
+    };
+
+    // Surfaces that can be seen through are drawn after the others,
+    // from the farthest to the nearest.
+    let mut opaque_surfaces = vec![];
+    let mut transparent_surfaces = vec![];
+    let mut edges = vec![];
+    for mesh in model.mesh.values().filter(|mesh| mesh.visible) {
+        if mesh.display_mode.shows_edges() {
+            for surface in mesh.surfaces.iter() {
+                if let Some(surface_edges) = &surface.maybe_edges {
+                    edges.push((mesh, surface_edges));
+                }
+            }
+        }
+        if !mesh.display_mode.shows_surfaces() {
+            continue;
+        }
+        for surface in mesh.surfaces.iter() {
+            let mut material = surface
+                .maybe_material_name
+                .as_ref()
+                .and_then(|name| model.materials.get(name))
+                .cloned()
+                .unwrap_or_else(Material::new);
+            material.opacity *= mesh.opacity;
+            if is_transparent(&material, &model.images, &model.translucent_images) {
+                let center = mesh.model_matrix * surface.center.extend(1.0);
+                let distance = (center.truncate() - camera.position()).magnitude2();
+                transparent_surfaces.push((distance, mesh, surface, material));
+            } else {
+                opaque_surfaces.push((mesh, surface, material));
+            }
+        }
+    }
+    transparent_surfaces.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+    let point_clouds = &model.point_clouds;
+    let point_renderer = model.point_cloud_renderer.as_ref();
+    let edge_renderer = model.edge_renderer.as_ref().unwrap();
+    let viewport_size = vec2(screen_width as f32, screen_height as f32);
+    model
+        .renderer
+        .as_mut()
+        .unwrap()
         .geometry_pass(screen_width as usize, screen_height as usize, &|| {
-            for group in mesh_groups.iter() {
-                for surface in group.1.surfaces.iter() {
-                    if group.1.visible {
-                        let mut maybe_texture: Option<&Texture2D> = None;
-                        let material = match &surface.maybe_material_name {
-                            Some(material_name) => match materials.get(material_name) {
-                                Some(material) => {
-                                    if let Some(texture_name) = &material.texture_name {
-                                        if let Some(texture) = images.get(texture_name) {
-                                            maybe_texture = Some(texture);
-                                        }
-                                    }
-                                    material.clone()
-                                }
-                                None => Material::new(),
-                            },
-                            None => Material::new(),
-                        };
-                        match &surface.maybe_uvs {
-                            Some(_) => match maybe_texture {
-                                Some(texture) => textured.unwrap().render(
-                                    &Mat4::identity(),
-                                    camera,
-                                    &group.1.vertices,
-                                    surface,
-                                    &material,
-                                    &texture,
-                                ),
-                                None => untextured.unwrap().render(
-                                    &Mat4::identity(),
-                                    camera,
-                                    &group.1.vertices,
-                                    surface,
-                                    &material,
-                                ),
-                            },
-                            None => untextured.unwrap().render(
-                                &Mat4::identity(),
-                                camera,
-                                &group.1.vertices,
-                                surface,
-                                &material,
-                            ),
-                        }
-                    }
+            for (mesh, surface, material) in opaque_surfaces.iter() {
+                renderers.render(Pass::Geometry, camera, mesh, surface, material);
+            }
+            for (mesh, surface_edges) in edges.iter() {
+                edge_renderer.render(camera, mesh, surface_edges);
+            }
+            for group in point_clouds.iter() {
+                if group.1.visible {
+                    point_renderer.unwrap().render(
+                        &group.1.model_matrix,
+                        camera,
+                        &group.1.points,
+                        group.1.point_size,
+                        &viewport_size,
+                        &Material::new(),
+                    );
                 }
             }
         })
         .unwrap();
+    let renderer = model.renderer.as_ref().unwrap();
+    // The lights of transparent surfaces are shared by all of them.
+    let forward_lights = if transparent_surfaces.is_empty() {
+        None
+    } else {
+        match ForwardLights::new(gl, lights, camera) {
+            Ok(forward_lights) => Some(forward_lights),
+            Err(e) => {
+                warn!(
+                    "Could not set the lights of transparent surfaces due to {:?}",
+                    e
+                );
+                None
+            }
+        }
+    };
     Screen::write(
-        &gl,
+        gl,
         0,
         0,
         screen_width as usize,
         screen_height as usize,
         Some(&vec4(0.1, 0.1, 0.1, 1.0)),
-        None,
+        Some(1.0),
         &|| {
-            model
-                .renderer
-                .as_ref()
-                .unwrap()
-                .light_pass(
-                    &model.camera.as_ref().unwrap(),
-                    Some(&ambient_light0),
-                    &[&directional_light0, &directional_light1],
-                    &[],
-                    &[&point_light0, &point_light1],
-                )
+            renderer
+                .light_pass(camera, lights, model.environment.as_ref())
                 .unwrap();
+            if let Some(forward_lights) = &forward_lights {
+                renderer.forward_pass(&|| {
+                    for (_, mesh, surface, material) in transparent_surfaces.iter() {
+                        let pass = Pass::Forward(forward_lights);
+                        renderers.render(pass, camera, mesh, surface, material);
+                    }
+                });
+            }
         },
     )
     .unwrap();
@@ -174,6 +283,56 @@ fn render_gl(model: &mut Model, _timestamp: f64) {
     model.render_loop = Some(Box::new(handle));
 }
 
+/// Renderers of each kind of surface, and the textures they draw with.
+struct MeshRenderers<'a> {
+    untextured: &'a UntexturedMeshRenderer,
+    textured: &'a TexturedMeshRenderer,
+    pbr: &'a PbrMeshRenderer,
+    images: &'a HashMap<String, Texture2D>,
+}
+
+impl<'a> MeshRenderers<'a> {
+    /// Draws a surface with the renderer for its material.
+    fn render(
+        &self,
+        pass: Pass,
+        camera: &Camera,
+        mesh: &MeshContainer,
+        surface: &MeshSurface,
+        material: &Material,
+    ) {
+        let textures = MaterialTextures::new(material, self.images);
+        match &surface.maybe_uvs {
+            _ if material.pbr.is_some() => self
+                .pbr
+                .render(pass, camera, mesh, surface, material, &textures),
+            Some(_) if !textures.is_empty() => self
+                .textured
+                .render(pass, camera, mesh, surface, material, &textures),
+            _ => self
+                .untextured
+                .render(pass, camera, mesh, surface, material),
+        }
+    }
+}
+
+/// Can surfaces with the material be seen through? Either the material
+/// is, or its opacity map or texture has pixels that are.
+fn is_transparent(
+    material: &Material,
+    images: &HashMap<String, Texture2D>,
+    translucent_images: &HashSet<String>,
+) -> bool {
+    let opacity_map = material.opacity_map.as_ref();
+    material.opacity < 1.0
+        || opacity_map.is_some_and(|map| images.contains_key(&map.name))
+        || (material.texture_alpha
+            && material
+                .texture_name
+                .as_ref()
+                .is_some_and(|name| translucent_images.contains(name)))
+}
+
 /// Not implemented for non-wasm. In place so tests can be run on
 /// the host system.
 #[cfg(not(target_arch = "wasm32"))]
@@ -188,35 +347,50 @@ pub fn new_gl(gl: &GL) -> Option<Gl> {
 
 pub fn rendered(model: &mut Model, first_render: bool) {
     let canvas = model.node_ref.cast::<HtmlCanvasElement>().unwrap();
-
-    let gl: GL = canvas
-        .get_context("webgl2")
-        .unwrap()
-        .unwrap()
-        .dyn_into()
-        .unwrap();
-
     model.canvas = Some(canvas);
-    model.gl = new_gl(&gl);
+
+    // The page is drawn again whenever the model changes, but the canvas
+    // stays the same, so its context and the renderers with their
+    // programs are only created once.
+    if first_render || model.gl.is_none() {
+        let gl: GL = model
+            .canvas
+            .as_ref()
+            .unwrap()
+            .get_context("webgl2")
+            .unwrap()
+            .unwrap()
+            .dyn_into()
+            .unwrap();
+        model.gl = new_gl(&gl);
+    }
     let gl_ref = model.gl.as_ref().expect("GL Context not initialized!");
 
-    model.renderer = Some(DeferredPipeline::new(&gl_ref).unwrap());
-    model.untextured_mesh_renderer = Some(UntexturedMeshRenderer::new(&gl_ref));
-    model.textured_mesh_renderer = Some(TexturedMeshRenderer::new(&gl_ref));
-
-    // Camera
-    let camera = Camera::new_perspective(
-        &gl_ref,
-        vec3(0.0, 0.0, 2.0),
-        vec3(0.0, 0.0, 0.0),
-        vec3(0.0, 1.0, 0.0),
-        degrees(45.0),
-        model.canvas.as_ref().unwrap().width() as f32
-            / model.canvas.as_ref().unwrap().width() as f32,
-        0.1,
-        150.0,
-    );
-    model.camera = Some(camera);
+    if model.renderer.is_none() {
+        model.renderer = Some(DeferredRenderer::new(&gl_ref).unwrap());
+        model.untextured_mesh_renderer = Some(UntexturedMeshRenderer::new(&gl_ref));
+        model.textured_mesh_renderer = Some(TexturedMeshRenderer::new(&gl_ref));
+        model.pbr_mesh_renderer = Some(PbrMeshRenderer::new(gl_ref));
+        model.edge_renderer = Some(EdgeRenderer::new(gl_ref));
+        model.point_cloud_renderer = Some(PointCloudRenderer::new(gl_ref));
+    }
+
+    // Camera, which stays where it is when the page is drawn again.
+    if model.camera.is_none() {
+        let framing = &model.framing;
+        let camera = Camera::new_perspective(
+            &gl_ref,
+            framing.target + vec3(0.0, 0.0, framing.distance),
+            framing.target,
+            vec3(0.0, 1.0, 0.0),
+            degrees(FIELD_OF_VIEW),
+            model.canvas.as_ref().unwrap().width() as f32
+                / model.canvas.as_ref().unwrap().width() as f32,
+            framing.z_near,
+            framing.z_far,
+        );
+        model.camera = Some(camera);
+    }
 
     if first_render {
         // The callback to request animation frame is passed a time value which can be used for
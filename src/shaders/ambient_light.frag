@@ -0,0 +1,20 @@
+uniform sampler2DArray gbuffer;
+uniform sampler2DArray depthMap;
+uniform vec3 ambient_light;
+
+in vec2 uv;
+
+layout (location = 0) out vec4 color;
+
+void main()
+{
+	float depth = texture(depthMap, vec3(uv, 0)).r;
+	if (depth > 0.99999) {
+		discard;
+	}
+	vec3 emissive = texture(gbuffer, vec3(uv, 2)).rgb;
+	vec3 ambient = texture(gbuffer, vec3(uv, 3)).rgb;
+	color = vec4(ambient * ambient_light + emissive, 1.0);
+	// Surfaces of the forward pass are hidden behind these.
+	gl_FragDepth = depth;
+}
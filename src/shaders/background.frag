@@ -0,0 +1,14 @@
+uniform sampler2D background;
+uniform mat4 viewProjectionInverse;
+
+in vec2 uv;
+
+layout (location = 0) out vec4 color;
+
+void main()
+{
+	vec4 far_position = viewProjectionInverse * vec4(uv * 2.0 - 1.0, 1.0, 1.0);
+	vec3 direction = far_position.xyz / far_position.w - eyePosition;
+	vec3 radiance = decode_rgbm(texture(background, environment_uv(direction)));
+	color = vec4(radiance * environment_intensity, 1.0);
+}
@@ -0,0 +1,11 @@
+uniform vec3 light_color;
+uniform vec3 light_direction;
+
+void main()
+{
+	Surface surface;
+	if (!read_surface(surface)) {
+		discard;
+	}
+	color = vec4(calculate_light(light_color, light_direction, surface), 1.0);
+}
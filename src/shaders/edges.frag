@@ -0,0 +1,19 @@
+uniform vec3 edge_color;
+uniform vec3 fill_color;
+// 1 where the faces are filled with the fill color, hiding what is
+// behind them, and 0 where only their edges are drawn.
+uniform float fill;
+
+in vec3 barycentric;
+
+void main()
+{
+	// Edges are about a pixel wide, however far away the faces are.
+	vec3 pixels = barycentric / max(fwidth(barycentric), vec3(1e-6));
+	bool edge = min(min(pixels.x, pixels.y), pixels.z) < 1.0;
+	if (!edge && fill < 0.5) {
+		discard;
+	}
+	vec3 n = normalize(cross(dFdx(pos), dFdy(pos)));
+	write_surface(edge ? edge_color : fill_color, n, specular_intensity, emissive_color, opacity);
+}
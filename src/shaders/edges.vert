@@ -0,0 +1,29 @@
+uniform mat4 modelMatrix;
+// Moves the edges towards the camera, in clip space, so that they are
+// drawn over the faces they border.
+uniform float depth_offset;
+
+layout (std140) uniform Camera
+{
+    mat4 viewProjection;
+    mat4 view;
+    mat4 projection;
+    vec3 position;
+    float padding;
+} camera;
+
+in vec3 position;
+
+out vec3 pos;
+out vec3 barycentric;
+
+void main()
+{
+    // Every face has its own three corners.
+    int corner = gl_VertexID % 3;
+    barycentric = vec3(corner == 0 ? 1.0 : 0.0, corner == 1 ? 1.0 : 0.0, corner == 2 ? 1.0 : 0.0);
+    vec4 worldPosition = modelMatrix * vec4(position, 1.);
+    pos = worldPosition.xyz;
+    gl_Position = camera.viewProjection * worldPosition;
+    gl_Position.z -= depth_offset * gl_Position.w;
+}
@@ -0,0 +1,16 @@
+// Environments are equirectangular images, with straight up (+y) at the
+// top, stored as RGBM to keep colors brighter than one in eight bits.
+const float RGBM_RANGE = 16.0;
+
+uniform float environment_intensity;
+
+vec2 environment_uv(vec3 direction)
+{
+	vec3 d = normalize(direction);
+	return vec2(atan(d.z, d.x) / (2.0 * PI) + 0.5, acos(clamp(d.y, -1.0, 1.0)) / PI);
+}
+
+vec3 decode_rgbm(vec4 rgbm)
+{
+	return rgbm.rgb * rgbm.a * RGBM_RANGE;
+}
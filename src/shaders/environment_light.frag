@@ -0,0 +1,95 @@
+uniform vec3 ambient_light;
+// Spherical harmonics coefficients of the diffuse light.
+uniform vec3 irradiance_0;
+uniform vec3 irradiance_1;
+uniform vec3 irradiance_2;
+uniform vec3 irradiance_3;
+uniform vec3 irradiance_4;
+uniform vec3 irradiance_5;
+uniform vec3 irradiance_6;
+uniform vec3 irradiance_7;
+uniform vec3 irradiance_8;
+// Reflections of each roughness level, from a mirror at the top to the
+// roughest at the bottom.
+uniform sampler2D specular_map;
+
+const float SPECULAR_LEVELS = 5.0;
+
+// Diffuse light on a surface facing n, with the spherical harmonics of
+// sh_basis in environment.rs.
+vec3 environment_irradiance(vec3 n)
+{
+	return irradiance_0 * 0.282095
+		+ irradiance_1 * 0.488603 * n.y
+		+ irradiance_2 * 0.488603 * n.z
+		+ irradiance_3 * 0.488603 * n.x
+		+ irradiance_4 * 1.092548 * n.x * n.y
+		+ irradiance_5 * 1.092548 * n.y * n.z
+		+ irradiance_6 * 0.315392 * (3.0 * n.z * n.z - 1.0)
+		+ irradiance_7 * 1.092548 * n.x * n.z
+		+ irradiance_8 * 0.546274 * (n.x * n.x - n.y * n.y);
+}
+
+vec3 specular_level(vec2 environment, float level)
+{
+	// Rows at the edges of a level aren't blended with the next one.
+	float half_texel = 0.5 / float(textureSize(specular_map, 0).y) * SPECULAR_LEVELS;
+	float v = clamp(environment.y, half_texel, 1.0 - half_texel);
+	return decode_rgbm(texture(specular_map, vec2(environment.x, (level + v) / SPECULAR_LEVELS)));
+}
+
+// Light reflected in a direction by a surface with a roughness,
+// blended between the two nearest levels.
+vec3 environment_radiance(vec3 direction, float roughness)
+{
+	vec2 environment = environment_uv(direction);
+	float level = clamp(roughness, 0.0, 1.0) * (SPECULAR_LEVELS - 1.0);
+	float lower = floor(level);
+	float upper = min(lower + 1.0, SPECULAR_LEVELS - 1.0);
+	return mix(specular_level(environment, lower), specular_level(environment, upper), level - lower);
+}
+
+// Share of the light that a metallic-roughness surface reflects, over
+// all directions, with an analytic fit instead of a lookup table.
+vec3 environment_brdf(vec3 f0, float roughness, float n_dot_v)
+{
+	const vec4 c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+	const vec4 c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+	vec4 r = roughness * c0 + c1;
+	float a004 = min(r.x * r.x, exp2(-9.28 * n_dot_v)) * r.x + r.y;
+	vec2 ab = vec2(-1.04, 1.04) * a004 + r.zw;
+	return f0 * ab.x + ab.y;
+}
+
+void main()
+{
+	Surface surface;
+	if (!read_surface(surface)) {
+		discard;
+	}
+	vec3 emissive = texture(gbuffer, vec3(uv, 2)).rgb;
+	vec3 ambient = texture(gbuffer, vec3(uv, 3)).rgb;
+	vec3 irradiance = environment_irradiance(surface.normal);
+	vec3 v = normalize(eyePosition - surface.position);
+	vec3 reflected = reflect(-v, surface.normal);
+	vec3 environment_light;
+	if (surface.metallic_roughness) {
+		// The ambient layer holds the base color with occlusion.
+		float metallic = surface.specular_intensity;
+		float roughness = clamp(surface.specular_power, 0.04, 1.0);
+		float n_dot_v = max(dot(surface.normal, v), 1e-4);
+		vec3 f0 = mix(vec3(0.04), surface.albedo, metallic);
+		vec3 diffuse = (1.0 - metallic) * ambient * irradiance;
+		vec3 specular = environment_radiance(reflected, roughness) * environment_brdf(f0, roughness, n_dot_v);
+		environment_light = diffuse + specular * surface.diffuse_intensity;
+	} else {
+		// The roughness with about as wide a highlight as the power.
+		float roughness = sqrt(sqrt(2.0 / (surface.specular_power + 2.0)));
+		vec3 diffuse = surface.albedo * surface.diffuse_intensity * irradiance;
+		vec3 specular = surface.specular_intensity * environment_radiance(reflected, roughness);
+		environment_light = diffuse + specular;
+	}
+	color = vec4(ambient * ambient_light + emissive + environment_light * environment_intensity, 1.0);
+	// Surfaces of the forward pass are hidden behind these.
+	gl_FragDepth = texture(depthMap, vec3(uv, 0)).r;
+}
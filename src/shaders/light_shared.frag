@@ -0,0 +1,36 @@
+uniform sampler2DArray gbuffer;
+uniform sampler2DArray depthMap;
+uniform mat4 viewProjectionInverse;
+
+in vec2 uv;
+
+layout (location = 0) out vec4 color;
+
+vec3 world_position(float depth)
+{
+	vec4 clip_position = vec4(uv * 2.0 - 1.0, depth * 2.0 - 1.0, 1.0);
+	vec4 position = viewProjectionInverse * clip_position;
+	return position.xyz / position.w;
+}
+
+// Reads the surface drawn at this pixel by the geometry pass. False if
+// nothing was drawn there.
+bool read_surface(out Surface surface)
+{
+	float depth = texture(depthMap, vec3(uv, 0)).r;
+	if (depth > 0.99999) {
+		return false;
+	}
+	vec4 c = texture(gbuffer, vec3(uv, 0));
+	vec4 n = texture(gbuffer, vec3(uv, 1));
+	vec4 e = texture(gbuffer, vec3(uv, 2));
+	float model = texture(gbuffer, vec3(uv, 3)).a;
+	surface.position = world_position(depth);
+	surface.normal = normalize(n.xyz * 2.0 - 1.0);
+	surface.albedo = c.rgb;
+	surface.diffuse_intensity = c.a;
+	surface.specular_intensity = n.a;
+	surface.metallic_roughness = model < 0.5;
+	surface.specular_power = surface.metallic_roughness ? e.a : e.a * e.a * 1000.0;
+	return true;
+}
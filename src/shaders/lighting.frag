@@ -0,0 +1,83 @@
+uniform vec3 eyePosition;
+
+// Surfaces are either lit with the MTL lighting model, or they are
+// metallic-roughness surfaces. Those keep how much they are lit in the
+// diffuse intensity, their metallic value in the specular intensity,
+// and their roughness in the specular power.
+struct Surface
+{
+	vec3 position;
+	vec3 normal;
+	vec3 albedo;
+	float diffuse_intensity;
+	float specular_intensity;
+	float specular_power;
+	bool metallic_roughness;
+};
+
+const float PI = 3.14159265;
+
+// Light reflected by a metallic-roughness surface, with the GGX
+// distribution of microfacets. Lights are scaled by pi, so that a white
+// surface facing a light reflects the color of the light, like a fully
+// diffuse surface of the MTL model does.
+vec3 calculate_pbr_light(vec3 light_color, vec3 light_direction, Surface surface)
+{
+	vec3 n = surface.normal;
+	vec3 l = -light_direction;
+	float n_dot_l = dot(n, l);
+	if (n_dot_l <= 0.0) {
+		return vec3(0.0);
+	}
+	vec3 v = normalize(eyePosition - surface.position);
+	vec3 h = normalize(l + v);
+	float n_dot_v = max(dot(n, v), 1e-4);
+	float n_dot_h = max(dot(n, h), 0.0);
+	float v_dot_h = max(dot(v, h), 0.0);
+	float metallic = surface.specular_intensity;
+	float roughness = clamp(surface.specular_power, 0.04, 1.0);
+
+	float alpha = roughness * roughness;
+	float alpha2 = alpha * alpha;
+	float d = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+	float distribution = alpha2 / (PI * d * d);
+	float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+	float geometry = n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+	vec3 f0 = mix(vec3(0.04), surface.albedo, metallic);
+	vec3 fresnel = f0 + (1.0 - f0) * pow(1.0 - v_dot_h, 5.0);
+
+	vec3 specular = distribution * geometry * fresnel / (4.0 * n_dot_v * n_dot_l);
+	vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * surface.albedo / PI;
+	return light_color * PI * (diffuse + specular) * n_dot_l * surface.diffuse_intensity;
+}
+
+// Light reflected by a surface, from a light shining in a direction.
+// Highlights have the color of the light, not of the surface, except
+// on metallic-roughness surfaces.
+vec3 calculate_light(vec3 light_color, vec3 light_direction, Surface surface)
+{
+	if (surface.metallic_roughness) {
+		return calculate_pbr_light(light_color, light_direction, surface);
+	}
+	float diffuse_factor = dot(surface.normal, -light_direction);
+	if (diffuse_factor <= 0.0) {
+		return vec3(0.0);
+	}
+	vec3 diffuse = surface.albedo * surface.diffuse_intensity * diffuse_factor;
+	vec3 to_eye = normalize(eyePosition - surface.position);
+	vec3 reflected = normalize(reflect(light_direction, surface.normal));
+	float specular_factor = max(dot(to_eye, reflected), 0.0);
+	float specular = surface.specular_intensity * pow(specular_factor, surface.specular_power);
+	return light_color * (diffuse + vec3(specular));
+}
+
+// Light reflected by a surface, from a point light getting weaker with
+// distance.
+vec3 calculate_point_light(vec3 light_color, vec3 light_position, vec3 attenuation, Surface surface)
+{
+	vec3 light_direction = surface.position - light_position;
+	float distance = length(light_direction);
+	vec3 light = calculate_light(light_color, light_direction / distance, surface);
+	float factor = attenuation.x + attenuation.y * distance + attenuation.z * distance * distance;
+	return light / max(1.0, factor);
+}
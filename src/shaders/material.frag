@@ -0,0 +1,99 @@
+uniform float diffuse_intensity;
+uniform float specular_intensity;
+uniform float specular_power;
+uniform vec3 ambient_color;
+uniform vec3 emissive_color;
+uniform float opacity;
+// 1 for surfaces that aren't lit, which show their color as it is.
+uniform float unlit;
+
+in vec3 pos;
+
+#ifdef FORWARD
+// Transparent surfaces are lit as they are drawn, by up to MAX_LIGHTS
+// lights of each kind.
+const int MAX_LIGHTS = 8;
+uniform vec3 ambient_light;
+layout (std140) uniform ForwardLights
+{
+	// Number of directional lights in x, and of point lights in y.
+	vec4 light_counts;
+	vec4 directional_light_colors[MAX_LIGHTS];
+	vec4 directional_light_directions[MAX_LIGHTS];
+	vec4 point_light_colors[MAX_LIGHTS];
+	vec4 point_light_positions[MAX_LIGHTS];
+	vec4 point_light_attenuations[MAX_LIGHTS];
+};
+
+layout (location = 0) out vec4 out_color;
+
+// Light reflected by a surface from all lights other than the ambient
+// light.
+vec3 forward_light(Surface surface)
+{
+	vec3 light = vec3(0.0);
+	int directional_light_count = int(light_counts.x);
+	int point_light_count = int(light_counts.y);
+	for (int i = 0; i < directional_light_count; i++) {
+		light += calculate_light(directional_light_colors[i].xyz, directional_light_directions[i].xyz, surface);
+	}
+	for (int i = 0; i < point_light_count; i++) {
+		light += calculate_point_light(point_light_colors[i].xyz, point_light_positions[i].xyz, point_light_attenuations[i].xyz, surface);
+	}
+	return light;
+}
+#else
+layout (location = 0) out vec4 out_color;
+layout (location = 1) out vec4 out_normal;
+layout (location = 2) out vec4 out_emissive;
+layout (location = 3) out vec4 out_ambient;
+#endif
+
+// Writes a surface to the layers of the geometry pass, or lights it in
+// the forward pass. The specular power is stored as a square root, to
+// keep the precision of small powers. The alpha of the ambient layer is
+// 1 for this lighting model.
+void write_surface(vec3 albedo, vec3 n, float specular, vec3 emissive, float alpha)
+{
+	float lit = 1.0 - unlit;
+	float power = clamp(specular_power, 1.0, 1000.0);
+	vec3 ambient = ambient_color * albedo * lit;
+#ifdef FORWARD
+	Surface surface = Surface(pos, n, albedo, diffuse_intensity * lit, specular * lit, power, false);
+	vec3 light = ambient * ambient_light + emissive + unlit * albedo + forward_light(surface);
+	out_color = vec4(light, alpha);
+#else
+	// Surfaces of the geometry pass are opaque, apart from holes.
+	if (alpha <= 0.0) {
+		discard;
+	}
+	out_color = vec4(albedo, diffuse_intensity * lit);
+	out_normal = vec4(0.5 * n + 0.5, specular * lit);
+	out_emissive = vec4(emissive + unlit * albedo, sqrt(power / 1000.0));
+	out_ambient = vec4(ambient, 1.0);
+#endif
+}
+
+// Writes a metallic-roughness surface to the layers of the geometry
+// pass, or lights it in the forward pass. The metallic value takes the
+// place of the specular intensity, the roughness the place of the
+// specular power, and the occlusion is applied to the ambient light.
+// The alpha of the ambient layer is 0 for this lighting model.
+void write_pbr_surface(vec3 base_color, vec3 n, float metallic, float roughness, float occlusion, vec3 emissive, float alpha)
+{
+	float lit = 1.0 - unlit;
+	vec3 ambient = occlusion * base_color * lit;
+#ifdef FORWARD
+	Surface surface = Surface(pos, n, base_color, lit, metallic, roughness, true);
+	vec3 light = ambient * ambient_light + emissive + unlit * base_color + forward_light(surface);
+	out_color = vec4(light, alpha);
+#else
+	if (alpha <= 0.0) {
+		discard;
+	}
+	out_color = vec4(base_color, lit);
+	out_normal = vec4(0.5 * n + 0.5, metallic);
+	out_emissive = vec4(emissive + unlit * base_color, roughness);
+	out_ambient = vec4(ambient, 0.0);
+#endif
+}
@@ -0,0 +1,28 @@
+uniform mat4 modelMatrix;
+uniform mat4 normalMatrix;
+
+layout (std140) uniform Camera
+{
+    mat4 viewProjection;
+    mat4 view;
+    mat4 projection;
+    vec3 position;
+    float padding;
+} camera;
+
+in vec3 position;
+in vec3 normal;
+in vec3 color;
+
+out vec3 pos;
+out vec3 nor;
+out vec3 col;
+
+void main()
+{
+    vec4 worldPosition = modelMatrix * vec4(position, 1.);
+    nor = mat3(normalMatrix) * normal;
+    pos = worldPosition.xyz;
+    col = color;
+    gl_Position = camera.viewProjection * worldPosition;
+}
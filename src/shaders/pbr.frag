@@ -0,0 +1,58 @@
+uniform float metallic;
+uniform float roughness;
+#ifdef TEXTURED
+uniform float occlusion_strength;
+uniform sampler2D texture0;
+uniform sampler2D metallic_map;
+uniform sampler2D roughness_map;
+uniform sampler2D occlusion_map;
+uniform sampler2D emissive_map;
+uniform vec4 texture0_transform;
+uniform vec4 metallic_map_transform;
+uniform vec4 roughness_map_transform;
+uniform vec4 occlusion_map_transform;
+uniform vec4 emissive_map_transform;
+// Channel that each map of single values is read from, with a 1 for it.
+uniform vec4 metallic_map_channel;
+uniform vec4 roughness_map_channel;
+uniform vec4 occlusion_map_channel;
+// 1 if the alpha of the base color texture scales the opacity, 0 if not.
+uniform float texture_alpha;
+#else
+in vec3 nor;
+#endif
+// Vertex colors take the place of the base color.
+#ifdef VERTEX_COLOR
+in vec3 col;
+#else
+uniform vec3 color;
+#endif
+
+void main()
+{
+#ifdef VERTEX_COLOR
+	vec3 base_color = col;
+#else
+	vec3 base_color = color;
+#endif
+	float surface_metallic = metallic;
+	float surface_roughness = roughness;
+	float occlusion = 1.0;
+	vec3 emissive = emissive_color;
+	float alpha = opacity;
+#ifdef TEXTURED
+	vec3 n = surface_normal();
+	// Textures that a material doesn't have are white.
+	vec4 base = sample_map(texture0, texture0_transform);
+	base_color *= base.rgb;
+	alpha *= mix(1.0, base.a, texture_alpha);
+	surface_metallic *= dot(sample_map(metallic_map, metallic_map_transform), metallic_map_channel);
+	surface_roughness *= dot(sample_map(roughness_map, roughness_map_transform), roughness_map_channel);
+	float occlusion_sample = dot(sample_map(occlusion_map, occlusion_map_transform), occlusion_map_channel);
+	occlusion = mix(1.0, occlusion_sample, occlusion_strength);
+	emissive *= sample_map(emissive_map, emissive_map_transform).rgb;
+#else
+	vec3 n = normalize(gl_FrontFacing ? nor : -nor);
+#endif
+	write_pbr_surface(base_color, n, surface_metallic, surface_roughness, occlusion, emissive, alpha);
+}
@@ -0,0 +1,12 @@
+in vec3 nor;
+in vec3 col;
+in vec2 uv;
+
+void main()
+{
+	if (dot(uv, uv) > 1.0) {
+		discard;
+	}
+	vec3 n = normalize(nor);
+	write_surface(col, n, specular_intensity, emissive_color, opacity);
+}
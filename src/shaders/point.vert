@@ -0,0 +1,39 @@
+uniform mat4 modelMatrix;
+uniform mat4 normalMatrix;
+uniform vec2 viewport_size;
+uniform float point_size;
+
+layout (std140) uniform Camera
+{
+    mat4 viewProjection;
+    mat4 view;
+    mat4 projection;
+    vec3 position;
+    float padding;
+} camera;
+
+// Corner of the quad, per vertex.
+in vec2 corner;
+
+// Per point.
+in vec3 position;
+in vec3 normal;
+in vec3 color;
+
+out vec3 pos;
+out vec3 nor;
+out vec3 col;
+out vec2 uv;
+
+void main()
+{
+    vec4 worldPosition = modelMatrix * vec4(position, 1.);
+    pos = worldPosition.xyz;
+    // Points without a normal are lit as if they face the camera.
+    nor = length(normal) > 0.0 ? mat3(normalMatrix) * normal : camera.position - pos;
+    col = color;
+    uv = corner;
+    gl_Position = camera.viewProjection * worldPosition;
+    // Offset in screen space so points keep their size in pixels.
+    gl_Position.xy += corner * point_size / viewport_size * gl_Position.w;
+}
@@ -0,0 +1,13 @@
+uniform vec3 light_color;
+uniform vec3 light_position;
+// Constant, linear and quadratic attenuation with distance.
+uniform vec3 attenuation;
+
+void main()
+{
+	Surface surface;
+	if (!read_surface(surface)) {
+		discard;
+	}
+	color = vec4(calculate_point_light(light_color, light_position, attenuation, surface), 1.0);
+}
@@ -0,0 +1,60 @@
+in vec3 nor;
+in vec3 uvw_passthrough;
+#ifdef NORMAL_MAP
+in vec3 tangent_passthrough;
+in float handedness_passthrough;
+
+uniform sampler2D normal_map;
+uniform sampler2D bump_map;
+uniform vec4 normal_map_transform;
+uniform vec4 bump_map_transform;
+// Strength of each map, 0 for maps that a material doesn't have.
+uniform float normal_scale;
+uniform float bump_scale;
+#endif
+
+// Texture coordinates for a map with a scale in xy and an offset in zw.
+vec2 map_uv(vec4 transform)
+{
+	return uvw_passthrough.xy * transform.xy + transform.zw;
+}
+
+vec4 sample_at(sampler2D map, vec2 uv)
+{
+	return texture(map, vec2(uv.x, 1.0 - uv.y));
+}
+
+vec4 sample_map(sampler2D map, vec4 transform)
+{
+	return sample_at(map, map_uv(transform));
+}
+
+#ifdef NORMAL_MAP
+// Normal in the tangent space of the surface, from the normal map and
+// the slopes of the bump map between neighbouring texels.
+vec3 tangent_space_normal()
+{
+	vec3 n = sample_map(normal_map, normal_map_transform).rgb * 2.0 - 1.0;
+	n.xy *= normal_scale;
+	vec2 uv = map_uv(bump_map_transform);
+	vec2 texel = 1.0 / vec2(textureSize(bump_map, 0));
+	float height = sample_at(bump_map, uv).r;
+	float slope_u = sample_at(bump_map, uv + vec2(texel.x, 0.0)).r - height;
+	float slope_v = sample_at(bump_map, uv + vec2(0.0, texel.y)).r - height;
+	return normalize(n + vec3(-slope_u, -slope_v, 0.0) * bump_scale);
+}
+#endif
+
+// Normal of the side of the surface that is seen.
+vec3 surface_normal()
+{
+#ifdef NORMAL_MAP
+	vec3 n = normalize(nor);
+	vec3 t = normalize(tangent_passthrough - n * dot(n, tangent_passthrough));
+	vec3 b = handedness_passthrough * cross(n, t);
+	n = normalize(mat3(t, b, n) * tangent_space_normal());
+	return gl_FrontFacing ? n : -n;
+#else
+	return normalize(gl_FrontFacing ? nor : -nor);
+#endif
+}
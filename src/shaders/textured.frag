@@ -1,27 +1,27 @@
-uniform float diffuse_intensity;
-uniform float specular_intensity;
-uniform float specular_power;
+uniform vec3 color;
 uniform sampler2D texture0;
-
-in vec3 nor;
-in vec3 pos;
-in vec3 uvw_passthrough;
-
-layout (location = 0) out vec4 out_color;
-layout (location = 1) out vec4 normal;
-
-vec3 blendNormal(vec3 normal){
-	vec3 blending = abs(normal);
-	blending = normalize(max(blending, 0.00001));
-	blending /= vec3(blending.x + blending.y + blending.z);
-	return blending;
-}
+uniform sampler2D specular_map;
+uniform sampler2D opacity_map;
+uniform sampler2D emissive_map;
+uniform vec4 texture0_transform;
+uniform vec4 specular_map_transform;
+uniform vec4 opacity_map_transform;
+uniform vec4 emissive_map_transform;
+// 1 if the alpha of the texture scales the opacity, 0 if not.
+uniform float texture_alpha;
 
 void main()
 {
-	vec3 n = normalize(gl_FrontFacing ? nor : -nor);
-  out_color = vec4(texture(texture0, vec2(uvw_passthrough.x, 1.0 - uvw_passthrough.y)).rgb, diffuse_intensity);
-	int intensity = int(floor(specular_intensity * 15.0));
-	int power = int(floor(specular_power*0.5));
-  normal = vec4(0.5 * n + 0.5, float(power << 4 | intensity)/255.0);
+	vec3 n = surface_normal();
+	// Textures that a material doesn't have are white.
+	vec4 diffuse = sample_map(texture0, texture0_transform);
+	vec3 albedo = color * diffuse.rgb;
+	vec3 specular_scale = sample_map(specular_map, specular_map_transform).rgb;
+	float specular = specular_intensity * max(specular_scale.r, max(specular_scale.g, specular_scale.b));
+	// Opacity maps either have an alpha channel, or are grey.
+	vec4 opacity_scale = sample_map(opacity_map, opacity_map_transform);
+	float alpha = opacity * (opacity_scale.a < 1.0 ? opacity_scale.a : opacity_scale.r);
+	alpha *= mix(1.0, diffuse.a, texture_alpha);
+	vec3 emissive = emissive_color * sample_map(emissive_map, emissive_map_transform).rgb;
+	write_surface(albedo, n, specular, emissive, alpha);
 }
@@ -13,10 +13,18 @@ layout (std140) uniform Camera
 in vec3 position;
 in vec3 normal;
 in vec3 uvw;
+#ifdef NORMAL_MAP
+in vec3 tangent;
+in float handedness;
+#endif
 
 out vec3 pos;
 out vec3 nor;
 out vec3 uvw_passthrough;
+#ifdef NORMAL_MAP
+out vec3 tangent_passthrough;
+out float handedness_passthrough;
+#endif
 
 void main()
 {
@@ -24,5 +32,10 @@ void main()
     nor = mat3(normalMatrix) * normal;
     pos = worldPosition.xyz;
     uvw_passthrough = uvw;
+#ifdef NORMAL_MAP
+    tangent_passthrough = mat3(modelMatrix) * tangent;
+    // Mirroring transforms swap the side of the bitangent.
+    handedness_passthrough = determinant(mat3(modelMatrix)) < 0.0 ? -handedness : handedness;
+#endif
     gl_Position = camera.viewProjection * worldPosition;
 }
@@ -0,0 +1,8 @@
+in vec3 nor;
+in vec3 col;
+
+void main()
+{
+	vec3 n = normalize(gl_FrontFacing ? nor : -nor);
+	write_surface(col, n, specular_intensity, emissive_color, opacity);
+}
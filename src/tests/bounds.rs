@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod test {
+    use crate::bounds::{BoundingBox, Framing};
+    use three_d::*;
+
+    #[test]
+    fn bounds_grow_with_points() {
+        let mut bounds = BoundingBox::new();
+        assert!(bounds.is_empty());
+        bounds.add_box(&BoundingBox::new());
+        assert!(bounds.is_empty());
+        let positions = [1.0, -2.0, 3.0, -1.0, 2.0, 5.0];
+        bounds.add_box(&BoundingBox::from_positions(&positions));
+        assert!(!bounds.is_empty());
+        assert_eq!(bounds.min, vec3(-1.0, -2.0, 3.0));
+        assert_eq!(bounds.max, vec3(1.0, 2.0, 5.0));
+        assert_eq!(bounds.center(), vec3(0.0, 0.0, 4.0));
+        assert!((bounds.radius() - 2.449_49).abs() < 1e-4);
+    }
+
+    #[test]
+    fn transformed_bounds_hold_every_corner() {
+        let bounds = BoundingBox::from_positions(&[0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+        let transform =
+            Mat4::from_translation(vec3(10.0, 0.0, 0.0)) * Mat4::from_angle_z(degrees(90.0));
+        let transformed = bounds.transformed(&transform);
+        assert!((transformed.min - vec3(9.0, 0.0, 0.0)).magnitude() < 1e-5);
+        assert!((transformed.max - vec3(10.0, 1.0, 1.0)).magnitude() < 1e-5);
+        assert!(BoundingBox::new().transformed(&transform).is_empty());
+    }
+
+    #[test]
+    fn framing_fits_far_away_bounds() {
+        // A survey in metres, far from the origin.
+        let bounds = BoundingBox::from_positions(&[
+            500_000.0,
+            4_000_000.0,
+            100.0,
+            500_200.0,
+            4_000_100.0,
+            150.0,
+        ]);
+        let framing = Framing::new(&bounds, degrees(90.0), 1.0);
+        assert_eq!(framing.target, bounds.center());
+        // The sphere around the box touches the sides of the view.
+        let radius = bounds.radius();
+        assert!((framing.distance - radius * 2.0f32.sqrt()).abs() < 1e-2);
+        assert!(framing.z_near < framing.distance - radius);
+        assert!(framing.z_far > framing.distance + radius);
+
+        // Narrow views are fitted horizontally.
+        let narrow = Framing::new(&bounds, degrees(90.0), 0.5);
+        assert!(narrow.distance > framing.distance);
+    }
+
+    #[test]
+    fn zooming_keeps_small_bounds_between_the_planes() {
+        // A part a few millimetres across, in metres.
+        let bounds = BoundingBox::from_positions(&[0.0, 0.0, 0.0, 0.01, 0.01, 0.005]);
+        let framing = Framing::new(&bounds, degrees(45.0), 1.0);
+        let radius = bounds.radius();
+        assert!(framing.distance < 0.1);
+
+        // One step either way changes the distance by a fraction of it.
+        let closer = framing.zoomed_distance(framing.distance, -0.5);
+        let farther = framing.zoomed_distance(framing.distance, 0.5);
+        assert!(closer < framing.distance && closer > framing.distance * 0.5);
+        assert!(farther > framing.distance && farther < framing.distance * 2.0);
+
+        // However far it zooms, the target stays between the planes.
+        let mut distance = framing.distance;
+        for _ in 0..100 {
+            distance = framing.zoomed_distance(distance, 0.5);
+        }
+        assert!(distance + radius < framing.z_far);
+        for _ in 0..200 {
+            distance = framing.zoomed_distance(distance, -0.5);
+        }
+        assert!(distance > framing.z_near);
+    }
+}
@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod test {
+    use crate::deferred_renderer::{forward_light_block, DirectionalLight, PointLight};
+    use crate::light_rig::Preset;
+    use three_d::*;
+
+    #[test]
+    fn forward_lights_fill_every_element_of_the_block() {
+        // The default lights have two of each kind, beyond the first
+        // element of each array.
+        let mut lights = Preset::Default.lights();
+        lights.directional.truncate(2);
+        lights.point = vec![
+            PointLight {
+                color: vec3(1.0, 0.0, 0.0),
+                intensity: 2.0,
+                position: vec3(1.0, 2.0, 3.0),
+                attenuation: vec3(1.0, 0.1, 0.01),
+            },
+            PointLight {
+                color: vec3(0.0, 1.0, 0.0),
+                intensity: 0.5,
+                position: vec3(-1.0, -2.0, -3.0),
+                attenuation: vec3(1.0, 0.0, 0.0),
+            },
+        ];
+        let block = forward_light_block(&lights, &Mat4::identity());
+        assert_eq!(block.len(), 6);
+        assert_eq!(&block[0][..2], &[2.0, 2.0]);
+        for (i, light) in lights.directional.iter().enumerate() {
+            let color = light.color * light.intensity;
+            let direction = light.direction_for_view(&Mat4::identity());
+            assert_eq!(&block[1][i * 4..i * 4 + 3], &[color.x, color.y, color.z]);
+            assert_eq!(
+                &block[2][i * 4..i * 4 + 3],
+                &[direction.x, direction.y, direction.z]
+            );
+        }
+        assert_eq!(&block[3][4..7], &[0.0, 0.5, 0.0]);
+        assert_eq!(&block[4][4..7], &[-1.0, -2.0, -3.0]);
+        assert_eq!(&block[5][..3], &[1.0, 0.1, 0.01]);
+        // Elements past the lights stay zero.
+        assert!(block[1][8..].iter().all(|value| *value == 0.0));
+    }
+
+    #[test]
+    fn forward_lights_beyond_the_limit_are_left_out() {
+        let mut lights = Preset::Flat.lights();
+        lights.directional = vec![
+            DirectionalLight {
+                color: vec3(1.0, 1.0, 1.0),
+                intensity: 1.0,
+                direction: vec3(0.0, 0.0, -1.0),
+                attached_to_camera: true,
+            };
+            20
+        ];
+        // Lights that turn with the camera follow its view.
+        let view = Mat4::from_angle_y(degrees(90.0));
+        let block = forward_light_block(&lights, &view);
+        assert_eq!(block[0][0], 8.0);
+        assert_eq!(block[1].len(), 8 * 4);
+        let direction = vec3(block[2][0], block[2][1], block[2][2]);
+        assert!((direction - vec3(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn shaders_have_no_uniform_arrays_outside_blocks() {
+        // three-d only finds the first element of a uniform array.
+        let material = include_str!("../shaders/material.frag");
+        for line in material.lines() {
+            let line = line.trim();
+            assert!(
+                !(line.starts_with("uniform") && line.contains('[')),
+                "{}",
+                line
+            );
+        }
+    }
+}
@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod test {
+    use crate::edge_renderer::DisplayMode;
+    use crate::render_buffers::corner_positions;
+
+    #[test]
+    fn display_modes_go_round() {
+        let mut mode = DisplayMode::Shaded;
+        let mut seen = vec![];
+        for _ in 0..DisplayMode::ALL.len() {
+            seen.push(mode);
+            mode = mode.next();
+        }
+        assert_eq!(mode, DisplayMode::Shaded);
+        assert_eq!(seen, DisplayMode::ALL.to_vec());
+        let shown: Vec<(bool, bool)> = DisplayMode::ALL
+            .iter()
+            .map(|mode| (mode.shows_surfaces(), mode.shows_edges()))
+            .collect();
+        assert_eq!(
+            shown,
+            vec![(true, false), (false, true), (true, true), (false, true)]
+        );
+    }
+
+    #[test]
+    fn faces_get_their_own_corners() {
+        // Two triangles sharing an edge.
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+        let indices = [0, 1, 2, 0, 2, 3];
+        let corners = corner_positions(&positions, &indices);
+        assert_eq!(corners.len(), 6 * 3);
+        assert_eq!(&corners[..9], &positions[..9]);
+        assert_eq!(&corners[9..12], &positions[..3]);
+        assert_eq!(&corners[12..15], &positions[6..9]);
+        assert_eq!(&corners[15..], &positions[9..]);
+    }
+}
@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod test {
+    use crate::environment::{encode_rgbm, sh_basis, EnvironmentMap, RGBM_RANGE};
+    use image::hdr::HdrEncoder;
+    use image::Rgb;
+    use three_d::*;
+
+    /// Diffuse light on a surface facing `normal`, as
+    /// environment_light.frag computes it.
+    fn irradiance_towards(coefficients: &[Vec3; 9], normal: Vec3) -> Vec3 {
+        coefficients
+            .iter()
+            .zip(sh_basis(normal.normalize()).iter())
+            .fold(vec3(0.0, 0.0, 0.0), |sum, (coefficient, value)| {
+                sum + coefficient * *value
+            })
+    }
+
+    /// Light from above only, as under an overcast sky.
+    fn sky(width: usize, height: usize) -> EnvironmentMap {
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |_| y))
+            .map(|y| {
+                if y < height / 2 {
+                    vec3(1.0, 1.0, 1.0)
+                } else {
+                    vec3(0.0, 0.0, 0.0)
+                }
+            })
+            .collect();
+        EnvironmentMap::new(width, height, pixels)
+    }
+
+    #[test]
+    fn hdr_files_are_read_from_the_top() {
+        let (width, height) = (4, 2);
+        let pixels: Vec<Rgb<f32>> = (0..width * height)
+            .map(|i| Rgb([i as f32, 0.5, 2.0]))
+            .collect();
+        let mut data = vec![];
+        HdrEncoder::new(&mut data)
+            .encode(&pixels, width, height)
+            .unwrap();
+        let map = EnvironmentMap::from_hdr(&data).unwrap();
+        assert_eq!((map.width, map.height), (width, height));
+        assert!((map.pixels[5] - vec3(5.0, 0.5, 2.0)).magnitude() < 0.05);
+        assert!(
+            (map.sample(EnvironmentMap::direction(0.125, 0.25)) - map.pixels[0]).magnitude() < 1e-6
+        );
+        assert!(EnvironmentMap::from_hdr(b"not an image").is_err());
+    }
+
+    #[test]
+    fn irradiance_matches_the_light_around() {
+        let gray = EnvironmentMap::new(32, 16, vec![vec3(0.5, 0.25, 2.0); 32 * 16]);
+        let coefficients = gray.irradiance();
+        for normal in [
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, -1.0, 0.0),
+            vec3(1.0, 1.0, 1.0),
+        ]
+        .iter()
+        {
+            let irradiance = irradiance_towards(&coefficients, *normal);
+            assert!((irradiance - vec3(0.5, 0.25, 2.0)).magnitude() < 0.01);
+        }
+
+        // Surfaces facing up see all of the sky, and ones facing down
+        // none of it.
+        let coefficients = sky(64, 32).irradiance();
+        let up = irradiance_towards(&coefficients, vec3(0.0, 1.0, 0.0));
+        let side = irradiance_towards(&coefficients, vec3(1.0, 0.0, 0.0));
+        let down = irradiance_towards(&coefficients, vec3(0.0, -1.0, 0.0));
+        assert!((up.x - 1.0).abs() < 0.02);
+        assert!((side.x - 0.5).abs() < 0.02);
+        assert!(down.x.abs() < 0.02);
+    }
+
+    #[test]
+    fn reflections_blur_with_roughness() {
+        let sky = sky(64, 32);
+        let mirror = sky.prefiltered(0.0, 32, 16);
+        assert_eq!((mirror.width, mirror.height), (32, 16));
+        assert_eq!(mirror.sample(vec3(0.0, 1.0, 0.0)), vec3(1.0, 1.0, 1.0));
+        assert_eq!(mirror.sample(vec3(0.0, -1.0, 0.0)), vec3(0.0, 0.0, 0.0));
+
+        // Just above the horizon, rougher surfaces reflect more of what
+        // is below it.
+        let just_above = EnvironmentMap::direction(0.5, 0.45);
+        let mut last = 1.0;
+        for roughness in [0.25, 0.5, 1.0].iter() {
+            let reflected = sky.prefiltered(*roughness, 32, 16).sample(just_above).x;
+            assert!(reflected < last);
+            assert!(reflected > 0.5);
+            last = reflected;
+        }
+        let rough = sky.prefiltered(1.0, 32, 16);
+        assert!((rough.sample(vec3(0.0, 1.0, 0.0)).x - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn rgbm_keeps_bright_colors() {
+        let decode = |rgbm: [u8; 4]| {
+            vec3(rgbm[0] as f32, rgbm[1] as f32, rgbm[2] as f32) / 255.0 * rgbm[3] as f32 / 255.0
+                * RGBM_RANGE
+        };
+        assert_eq!(encode_rgbm(vec3(0.0, 0.0, 0.0)), [0, 0, 0, 0]);
+        for color in [
+            vec3(0.2, 0.4, 0.6),
+            vec3(10.0, 3.0, 0.5),
+            vec3(0.01, 0.0, 0.02),
+        ]
+        .iter()
+        {
+            let decoded = decode(encode_rgbm(*color));
+            let largest = color.x.max(color.y).max(color.z);
+            assert!((decoded - color).magnitude() < largest * 0.01 + 1e-3);
+        }
+        // Colors beyond the range are clamped to it.
+        assert!((decode(encode_rgbm(vec3(100.0, 0.0, 0.0))).x - RGBM_RANGE).abs() < 1e-3);
+    }
+}
@@ -1,8 +1,10 @@
 #[cfg(test)]
 mod test {
     use crate::io::*;
+    use crate::io::{Error, ErrorKind};
     use rust_3d::*;
     use std::{fs::File, io::BufReader};
+    use three_d::core::types::InnerSpace;
     #[test]
     fn load_untextured_obj() {
         let path = "src/tests/data/box.obj".to_string();
@@ -44,6 +46,49 @@ mod test {
         );
         assert_eq!(material_info.material_libs.len(), 1);
         assert_eq!(material_info.material_libs.contains("capsule.mtl"), true);
+        assert_eq!(material_info.normals.len(), 5252);
+    }
+    #[test]
+    fn load_obj_with_wedge_normals() {
+        let path = "src/tests/data/box_normals.obj".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material_info = MaterialInfo::new();
+        load_obj_mesh(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material_info,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 8);
+        assert_eq!(m.num_faces(), 12);
+        assert_eq!(material_info.uv.len(), 0);
+        // Each vertex is shared by faces with different normals,
+        // so normals must be stored per wedge.
+        assert_eq!(material_info.normals.len(), 3 * 12);
+        for i in 0..6 {
+            assert!((material_info.normals[i].x + 1.0).abs() < 1e-6);
+        }
+        for i in 6..12 {
+            assert!((material_info.normals[i].y + 1.0).abs() < 1e-6);
+        }
+        for i in 30..36 {
+            assert!((material_info.normals[i].x - 1.0).abs() < 1e-6);
+        }
+    }
+    #[test]
+    fn obj_vertices_without_normals_get_computed_ones() {
+        // The second face has no normals, and its last vertex is in no
+        // face that has one.
+        let data = b"v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+            vn 0 0 1\nvn 0 0 1\nvn 0 0 1\n\
+            f 1//1 2//2 3//3\nf 1 3 4\n";
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material_info = MaterialInfo::new();
+        load_obj_mesh(&mut &data[..], &mut m, &mut material_info).unwrap();
+        assert_eq!(material_info.normals.len(), 4);
+        for normal in material_info.normals.iter() {
+            assert!((normal - three_d::Vec3::new(0.0, 0.0, 1.0)).magnitude() < 1e-6);
+        }
     }
     #[test]
     fn quad_mesh_works() {
@@ -66,6 +111,186 @@ mod test {
         );
     }
 
+    fn load_obj_file(path: &str) -> (TestMesh, MaterialInfo) {
+        let mut m = TestMesh::default();
+        let mut material_info = MaterialInfo::new();
+        load_obj_mesh(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material_info,
+        )
+        .unwrap();
+        (m, material_info)
+    }
+
+    #[test]
+    fn obj_parts_work() {
+        let (m, material_info) = load_obj_file("src/tests/data/assembly.obj");
+        assert_eq!(m.num_faces(), 14);
+        let parts: Vec<(&str, usize)> = material_info
+            .parts
+            .iter()
+            .map(|part| (&part.name[..], part.faces.len()))
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("default", 2),
+                ("body/sides", 8),
+                ("body/caps", 2),
+                ("lid", 2)
+            ]
+        );
+        assert_eq!(material_info.parts[3].faces, vec![12, 13]);
+
+        // Files without parts or with a single one don't list any.
+        let (_, material_info) = load_obj_file("src/tests/data/capsule.obj");
+        assert!(material_info.parts.is_empty());
+        let (_, material_info) = load_obj_file("src/tests/data/box_quads.obj");
+        assert_eq!(material_info.parts.len(), 1);
+    }
+
+    #[test]
+    fn obj_parts_are_split_into_meshes() {
+        let registry = FormatRegistry::new();
+        let resources = std::collections::HashMap::new();
+        let data = std::fs::read("src/tests/data/assembly.obj").unwrap();
+        let scene = registry
+            .load("assembly.obj", &data[..], &resources)
+            .unwrap();
+        let meshes: Vec<(&str, usize, usize)> = scene
+            .meshes
+            .iter()
+            .map(|mesh| {
+                (
+                    &mesh.name[..],
+                    mesh.mesh.num_vertices(),
+                    mesh.mesh.num_faces(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            meshes,
+            vec![
+                ("assembly.obj/default", 4, 2),
+                ("assembly.obj/body/sides", 8, 8),
+                ("assembly.obj/body/caps", 4, 2),
+                ("assembly.obj/lid", 4, 2),
+            ]
+        );
+        let lid = &scene.meshes[3];
+        assert_eq!(lid.material_info.uv.len(), 4);
+        for vid in 0..4 {
+            // UVs stay with their vertices.
+            let vertex = lid.mesh.vertex(VId { val: vid }).unwrap();
+            let uv = &lid.material_info.uv.data[vid];
+            assert_eq!(vertex.y, 1.0);
+            assert_eq!(uv.x, if vertex.x > 0.0 { 1.0 } else { 0.0 });
+            assert_eq!(uv.y, if vertex.z < 0.0 { 1.0 } else { 0.0 });
+        }
+        assert_eq!(lid.material_info.surfaces["Lid"].faces.len(), 2);
+        assert!(!lid.material_info.surfaces.contains_key("NotSpecified"));
+    }
+
+    #[test]
+    fn relative_quad_mesh_works() {
+        let (m, material_info) = load_obj_file("src/tests/data/box_quads_relative.obj");
+        assert_eq!(m.num_vertices(), 8);
+        assert_eq!(m.num_faces(), 12);
+        assert_eq!(material_info.uv.len(), 0);
+        assert_eq!(material_info.normals.len(), 0);
+        assert_eq!(
+            material_info.surfaces.get("Default").unwrap().faces.len(),
+            12
+        );
+        // -5 is the 4th of 8 vertices.
+        let face = m.face_vertex_ids(FId { val: 0 }).unwrap();
+        assert_eq!(face.a.val, 3);
+    }
+
+    #[test]
+    fn relative_quad_mesh_with_uvs_works() {
+        let (m, material_info) = load_obj_file("src/tests/data/box_quads_relative_uvs.obj");
+        assert_eq!(m.num_vertices(), 8);
+        assert_eq!(m.num_faces(), 12);
+        // Texture coordinates have the same indices as the vertices, so
+        // they are per vertex.
+        assert_eq!(material_info.uv.len(), 8);
+        assert_eq!(material_info.normals.len(), 0);
+        assert_eq!(
+            material_info.surfaces.get("Default").unwrap().faces.len(),
+            12
+        );
+    }
+
+    #[test]
+    fn relative_quad_mesh_with_normals_works() {
+        let (m, material_info) = load_obj_file("src/tests/data/box_quads_relative_normals.obj");
+        assert_eq!(m.num_vertices(), 8);
+        assert_eq!(m.num_faces(), 12);
+        assert_eq!(material_info.uv.len(), 0);
+        // Each face refers to the normal just before it.
+        assert_eq!(material_info.normals.len(), 3 * 12);
+        for i in 0..6 {
+            assert!((material_info.normals[i].x + 1.0).abs() < 1e-6);
+        }
+        for i in 6..12 {
+            assert!((material_info.normals[i].y + 1.0).abs() < 1e-6);
+        }
+        for i in 30..36 {
+            assert!((material_info.normals[i].x - 1.0).abs() < 1e-6);
+        }
+        assert_eq!(
+            material_info.surfaces.get("Default").unwrap().faces.len(),
+            12
+        );
+    }
+
+    #[test]
+    fn relative_quad_mesh_with_uvs_and_normals_works() {
+        let (m, material_info) = load_obj_file("src/tests/data/box_quads_relative_full.obj");
+        assert_eq!(m.num_vertices(), 8);
+        assert_eq!(m.num_faces(), 12);
+        // Normals are per wedge, so texture coordinates are too.
+        assert_eq!(material_info.uv.len(), 3 * 12);
+        assert_eq!(material_info.normals.len(), 3 * 12);
+        // The first face starts at the 4th vertex, at (-0.5, 0.5, 0.5).
+        let uv = material_info.uv.get_d(0);
+        assert!((uv.x - 0.0).abs() < 1e-6);
+        assert!((uv.y - 1.0).abs() < 1e-6);
+        for i in 30..36 {
+            assert!((material_info.normals[i].x - 1.0).abs() < 1e-6);
+        }
+        assert_eq!(
+            material_info.surfaces.get("Default").unwrap().faces.len(),
+            12
+        );
+    }
+
+    #[test]
+    fn invalid_relative_indices_are_errors() {
+        for face in [
+            "f -9 -1 -2",
+            "f 1/-9 2 3",
+            "f 1//-1 2 3",
+            "f 1/2/3/4 2 3",
+            "f 1/x 2 3",
+        ]
+        .iter()
+        {
+            let data = format!("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nv 1 1 1\nv 1 0 1\nv 0 1 1\nv 1 1 0\nvt 0 0\n{}\n", face);
+            let mut m = TestMesh::default();
+            let mut material_info = MaterialInfo::new();
+            match load_obj_mesh(&mut data.as_bytes(), &mut m, &mut material_info) {
+                Err(e) => {
+                    assert!(matches!(e.kind, ErrorKind::LineParse));
+                    assert_eq!(e.line, Some(10));
+                }
+                Ok(_) => panic!("{} loaded", face),
+            }
+        }
+    }
+
     #[test]
     fn load_mtl_works() {
         let mtl_path = "src/tests/data/capsule.mtl".to_string();
@@ -78,7 +303,12 @@ mod test {
                     assert!((material.diffuse_color.x - 0.8).abs() < 1e-6);
                     assert!((material.diffuse_color.y - 0.75).abs() < 1e-6);
                     assert!((material.diffuse_color.z - 0.70).abs() < 1e-6);
-                    assert!((material.specular_intensity - 30.0).abs() < 1e-6);
+                    assert!((material.specular_power - 30.0).abs() < 1e-6);
+                    assert_eq!(material.ambient_color, three_d::vec3(1.0, 1.0, 1.0));
+                    assert_eq!(material.specular_color, three_d::vec3(1.0, 1.0, 1.0));
+                    // "Tr 1" is taken to mean opaque.
+                    assert_eq!(material.opacity, 1.0);
+                    assert_eq!(material.illumination, 1);
                     match material.texture_name.clone() {
                         Some(texture_name) => {
                             assert_eq!(texture_name, "capsule0.jpg");
@@ -95,6 +325,179 @@ mod test {
         }
     }
 
+    #[test]
+    fn save_obj_round_trip() {
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+        let normals = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+        let uvs = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+        let data = ObjMeshData {
+            positions: &positions[..],
+            normals: Some(&normals[..]),
+            uvs: Some(&uvs[..]),
+            colors: None,
+            surfaces: vec![
+                ObjSurface {
+                    material_name: Some("first"),
+                    indices: &[0, 1, 2],
+                },
+                ObjSurface {
+                    material_name: Some("second"),
+                    indices: &[0, 2, 3],
+                },
+            ],
+        };
+        let mut obj = vec![];
+        save_obj(&mut obj, Some("quad.mtl"), &data).unwrap();
+
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material_info = MaterialInfo::new();
+        load_obj_mesh(&mut &obj[..], &mut m, &mut material_info).unwrap();
+        assert_eq!(m.num_vertices(), 4);
+        assert_eq!(m.num_faces(), 2);
+        assert_eq!(material_info.uv.len(), 4);
+        assert_eq!(material_info.uv.get_d(2).y, 1.0);
+        assert_eq!(material_info.normals.len(), 4);
+        assert!(material_info.material_libs.contains("quad.mtl"));
+        assert_eq!(material_info.surfaces["first"].faces.len(), 1);
+        assert_eq!(material_info.surfaces["second"].faces.len(), 1);
+    }
+
+    #[test]
+    fn load_mtl_properties_works() {
+        let path = "src/tests/data/materials.mtl";
+        let materials = load_mtl(&mut BufReader::new(File::open(path).unwrap())).unwrap();
+        let glass = &materials["glass"];
+        assert_eq!(glass.ambient_color, three_d::vec3(0.1, 0.2, 0.3));
+        assert_eq!(glass.diffuse_color, three_d::vec3(0.4, 0.5, 0.6));
+        assert_eq!(glass.specular_color, three_d::vec3(0.7, 0.7, 0.7));
+        assert_eq!(glass.emissive_color, three_d::vec3(0.25, 0.5, 0.75));
+        assert_eq!(glass.specular_power, 250.0);
+        // "d" wins over "Tr".
+        assert_eq!(glass.opacity, 0.5);
+        assert_eq!(glass.illumination, 4);
+        assert_eq!(glass.texture_name.as_deref(), Some("glass texture.png"));
+        assert_eq!(glass.texture_options.scale, three_d::vec3(2.0, 3.0, 1.0));
+        assert_eq!(glass.texture_options.offset, three_d::vec3(0.5, 0.0, 0.0));
+        assert_eq!(glass.specular_map, Some(TextureMap::new("specular.png")));
+        assert_eq!(glass.opacity_map, Some(TextureMap::new("alpha.png")));
+        let bump_map = glass.bump_map.as_ref().unwrap();
+        assert_eq!(bump_map.name, "bump.png");
+        assert_eq!(bump_map.options.bump_multiplier, 0.5);
+        assert_eq!(glass.normal_map, Some(TextureMap::new("normal.png")));
+        assert_eq!(glass.emissive_map, Some(TextureMap::new("emission.png")));
+        assert_eq!(glass.pbr, None);
+
+        let flat = &materials["flat"];
+        assert_eq!(flat.diffuse_color, three_d::vec3(1.0, 0.0, 0.0));
+        assert_eq!(flat.ambient_color, Material::new().ambient_color);
+        assert_eq!(flat.opacity, 0.75);
+        assert_eq!(flat.illumination, 0);
+        let bump_map = flat.bump_map.as_ref().unwrap();
+        assert_eq!(bump_map.options.offset, three_d::vec3(0.1, 0.0, 0.0));
+        assert_eq!(flat.texture_name, None);
+        assert_eq!(flat.specular_map, None);
+
+        let metal = materials["metal"].pbr.as_ref().unwrap();
+        assert_eq!(metal.metallic, 1.0);
+        assert_eq!(metal.roughness, 0.25);
+        let roughness_map = metal.roughness_map.as_ref().unwrap();
+        assert_eq!(roughness_map.name, "metal_roughness.png");
+        assert_eq!(roughness_map.options.channel, 1);
+        assert_eq!(metal.metallic_map.as_ref().unwrap().options.channel, 2);
+        assert_eq!(metal.occlusion_map, None);
+    }
+
+    #[test]
+    fn invalid_mtl_statements_are_errors() {
+        for statement in [
+            "Ka 1 0",
+            "Ks red",
+            "Ns",
+            "d",
+            "illum two",
+            "map_Kd",
+            "map_Ks -s",
+            "map_d -bm",
+            "map_Ke -unknown emission.png",
+            "map_Pr -imfchan x roughness.png",
+            "Pm metal",
+        ]
+        .iter()
+        {
+            let mtl = format!("newmtl material\n{}\n", statement);
+            match load_mtl(&mut mtl.as_bytes()) {
+                Err(e) => {
+                    assert!(matches!(e.kind, ErrorKind::LineParse));
+                    assert_eq!(e.line, Some(2));
+                }
+                Ok(_) => panic!("{} loaded", statement),
+            }
+        }
+    }
+
+    #[test]
+    fn save_mtl_round_trip() {
+        let mut material = Material::new();
+        material.diffuse_color = three_d::vec3(0.25, 0.5, 0.75);
+        material.ambient_color = three_d::vec3(0.5, 0.5, 0.5);
+        material.specular_color = three_d::vec3(0.25, 0.25, 0.25);
+        material.emissive_color = three_d::vec3(1.0, 0.5, 0.0);
+        material.specular_power = 10.0;
+        material.opacity = 0.5;
+        material.illumination = 1;
+        material.texture_name = Some("texture.png".to_string());
+        material.texture_options.scale = three_d::vec3(2.0, 2.0, 1.0);
+        let mut bump_map = TextureMap::new("bump.png");
+        bump_map.options.bump_multiplier = 2.0;
+        bump_map.options.offset = three_d::vec3(0.5, 0.25, 0.0);
+        material.bump_map = Some(bump_map);
+        material.specular_map = Some(TextureMap::new("specular.png"));
+        material.opacity_map = Some(TextureMap::new("alpha.png"));
+        material.normal_map = Some(TextureMap::new("normal.png"));
+        material.emissive_map = Some(TextureMap::new("emission.png"));
+        let mut pbr = PbrMaterial::new();
+        pbr.metallic = 0.5;
+        pbr.roughness = 0.75;
+        let mut roughness_map = TextureMap::new("roughness.png");
+        roughness_map.options.channel = 1;
+        pbr.roughness_map = Some(roughness_map);
+        pbr.metallic_map = Some(TextureMap::new("metallic.png"));
+        material.pbr = Some(pbr);
+        let mut mtl = vec![];
+        save_mtl(&mut mtl, &[("quad".to_string(), material.clone())]).unwrap();
+
+        let materials = load_mtl(&mut &mtl[..]).unwrap();
+        let saved = &materials["quad"];
+        assert_eq!(saved.diffuse_color, material.diffuse_color);
+        assert_eq!(saved.ambient_color, material.ambient_color);
+        assert_eq!(saved.specular_color, material.specular_color);
+        assert_eq!(saved.emissive_color, material.emissive_color);
+        assert_eq!(saved.specular_power, 10.0);
+        assert_eq!(saved.opacity, 0.5);
+        assert_eq!(saved.illumination, 1);
+        assert_eq!(saved.texture_name, Some("texture.png".to_string()));
+        assert_eq!(saved.texture_options, material.texture_options);
+        assert_eq!(saved.specular_map, material.specular_map);
+        assert_eq!(saved.opacity_map, material.opacity_map);
+        assert_eq!(saved.bump_map, material.bump_map);
+        assert_eq!(saved.normal_map, material.normal_map);
+        assert_eq!(saved.emissive_map, material.emissive_map);
+        assert_eq!(saved.pbr, material.pbr);
+        assert_eq!(
+            material.texture_names(),
+            vec![
+                "texture.png",
+                "specular.png",
+                "alpha.png",
+                "bump.png",
+                "normal.png",
+                "emission.png",
+                "metallic.png",
+                "roughness.png"
+            ]
+        );
+    }
+
     #[test]
     fn load_textured_binary_ply() {
         let path = "src/tests/data/capsule.ply".to_string();
@@ -186,4 +589,920 @@ mod test {
         assert_eq!(material_info.surfaces.contains_key(&file_name), true);
         assert_eq!(material.texture_name, None);
     }
+
+    #[test]
+    fn load_colored_ascii_ply() {
+        let path = "src/tests/data/cube_colored-ascii.ply".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        let file_name = "cube_colored-ascii.ply".to_string();
+        load_ply(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &file_name,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 8);
+        assert_eq!(m.num_faces(), 12);
+        assert_eq!(material_info.colors.len(), 8);
+        assert!((material_info.colors[0].x - 1.0).abs() < 1e-6);
+        assert!(material_info.colors[0].y.abs() < 1e-6);
+        assert!(material_info.colors[0].z.abs() < 1e-6);
+        assert!((material_info.colors[3].x - 1.0).abs() < 1e-6);
+        assert!((material_info.colors[3].y - 1.0).abs() < 1e-6);
+        assert!(material_info.colors[3].z.abs() < 1e-6);
+        assert_eq!(m.vertex(VId { val: 7 }).unwrap().x, 0.5);
+    }
+
+    #[test]
+    fn load_colored_binary_ply() {
+        let path = "src/tests/data/cube_colored-binary.ply".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        let file_name = "cube_colored-binary.ply".to_string();
+        load_ply(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &file_name,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 8);
+        assert_eq!(m.num_faces(), 12);
+        assert_eq!(material_info.colors.len(), 8);
+        assert!(material_info.colors[2].x.abs() < 1e-6);
+        assert!(material_info.colors[2].y.abs() < 1e-6);
+        assert!((material_info.colors[2].z - 1.0).abs() < 1e-6);
+        assert_eq!(m.vertex(VId { val: 7 }).unwrap().z, 0.5);
+    }
+
+    #[test]
+    fn ply_alpha_is_ignored() {
+        let data = b"ply\nformat ascii 1.0\n\
+            element vertex 3\n\
+            property float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\n\
+            property uchar alpha\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0 255 0 0 0\n1 0 0 0 255 0 128\n0 1 0 0 0 255 255\n\
+            3 0 1 2\n";
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        let file_name = "alpha.ply".to_string();
+        load_ply(
+            &mut &data[..],
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &file_name,
+        )
+        .unwrap();
+        assert_eq!(material_info.colors.len(), 3);
+        // Only the RGB channels are kept, whatever the alpha.
+        let expected = [
+            three_d::Vec3::new(1.0, 0.0, 0.0),
+            three_d::Vec3::new(0.0, 1.0, 0.0),
+            three_d::Vec3::new(0.0, 0.0, 1.0),
+        ];
+        for (color, expected) in material_info.colors.iter().zip(expected.iter()) {
+            assert!((color - expected).magnitude() < 1e-6);
+        }
+        assert_eq!(material.opacity, 1.0);
+    }
+
+    #[test]
+    fn uncolored_ply_has_no_colors() {
+        let path = "src/tests/data/capsule.ply".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        let file_name = "capsule.ply".to_string();
+        load_ply(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &file_name,
+        )
+        .unwrap();
+        assert_eq!(material_info.colors.len(), 0);
+    }
+
+    #[test]
+    fn load_ascii_ply_with_normals() {
+        let path = "src/tests/data/cube_normals-ascii.ply".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        let file_name = "cube_normals-ascii.ply".to_string();
+        load_ply(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &file_name,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 8);
+        assert_eq!(m.num_faces(), 12);
+        assert_eq!(material_info.colors.len(), 0);
+        assert_eq!(material_info.normals.len(), 8);
+        let expected = 1.0 / 3.0f32.sqrt();
+        assert!((material_info.normals[0].x + expected).abs() < 1e-5);
+        assert!((material_info.normals[0].y + expected).abs() < 1e-5);
+        assert!((material_info.normals[0].z - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn load_binary_ply_with_normals() {
+        let path = "src/tests/data/cube_normals-binary.ply".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        let file_name = "cube_normals-binary.ply".to_string();
+        load_ply(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &file_name,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 8);
+        assert_eq!(m.num_faces(), 12);
+        assert_eq!(material_info.normals.len(), 8);
+        let expected = 1.0 / 3.0f32.sqrt();
+        assert!((material_info.normals[7].x - expected).abs() < 1e-5);
+        assert!((material_info.normals[7].y - expected).abs() < 1e-5);
+        assert!((material_info.normals[7].z - expected).abs() < 1e-5);
+        assert_eq!(m.vertex(VId { val: 7 }).unwrap().z, 0.5);
+    }
+
+    fn load_ply_file(
+        path: &str,
+    ) -> (
+        rust_3d::Mesh3D<Point3D, PointCloud3D<Point3D>, Vec<usize>>,
+        Material,
+        MaterialInfo,
+    ) {
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        load_ply(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &"mesh.ply".to_string(),
+        )
+        .unwrap();
+        (m, material, material_info)
+    }
+
+    fn assert_ply_round_trip(path: &str, format: PlyFormat) {
+        let (m, material, material_info) = load_ply_file(path);
+        let mut data = vec![];
+        save_ply_with_attributes(
+            &mut data,
+            &m,
+            material.texture_name.as_deref(),
+            &material_info,
+            format,
+        )
+        .unwrap();
+
+        let mut saved = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut saved_material = Material::new();
+        let mut saved_info = MaterialInfo::new();
+        load_ply(
+            &mut &data[..],
+            &mut saved,
+            &mut saved_material,
+            &mut saved_info,
+            &"mesh.ply".to_string(),
+        )
+        .unwrap();
+        assert_eq!(saved.num_vertices(), m.num_vertices());
+        assert_eq!(saved.num_faces(), m.num_faces());
+        assert_eq!(saved_material.texture_name, material.texture_name);
+        for i in 0..m.num_vertices() {
+            let expected = m.vertex(VId { val: i }).unwrap();
+            let actual = saved.vertex(VId { val: i }).unwrap();
+            assert!((expected.x - actual.x).abs() < 1e-5);
+            assert!((expected.y - actual.y).abs() < 1e-5);
+            assert!((expected.z - actual.z).abs() < 1e-5);
+        }
+        for i in 0..m.num_faces() {
+            let expected = m.face_vertex_ids(FId { val: i }).unwrap();
+            assert_eq!(saved.face_vertex_ids(FId { val: i }).unwrap(), expected);
+        }
+        assert_eq!(saved_info.uv.len(), material_info.uv.len());
+        for i in 0..material_info.uv.len() {
+            assert!((saved_info.uv.get_d(i).x - material_info.uv.get_d(i).x).abs() < 1e-6);
+            assert!((saved_info.uv.get_d(i).y - material_info.uv.get_d(i).y).abs() < 1e-6);
+        }
+        assert_eq!(saved_info.colors.len(), material_info.colors.len());
+        for (actual, expected) in saved_info.colors.iter().zip(material_info.colors.iter()) {
+            assert!((actual - expected).magnitude() < 1e-2);
+        }
+        assert_eq!(saved_info.normals.len(), material_info.normals.len());
+        for (actual, expected) in saved_info.normals.iter().zip(material_info.normals.iter()) {
+            assert!((actual - expected).magnitude() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn save_textured_ascii_ply() {
+        assert_ply_round_trip("src/tests/data/capsule.ply", PlyFormat::Ascii);
+    }
+
+    #[test]
+    fn save_textured_little_endian_ply() {
+        assert_ply_round_trip("src/tests/data/capsule.ply", PlyFormat::LittleEndian);
+    }
+
+    #[test]
+    fn save_textured_big_endian_ply() {
+        assert_ply_round_trip("src/tests/data/capsule.ply", PlyFormat::BigEndian);
+    }
+
+    #[test]
+    fn save_colored_ply() {
+        assert_ply_round_trip("src/tests/data/cube_colored-ascii.ply", PlyFormat::Ascii);
+        assert_ply_round_trip(
+            "src/tests/data/cube_colored-ascii.ply",
+            PlyFormat::LittleEndian,
+        );
+    }
+
+    #[test]
+    fn save_ply_with_normals() {
+        assert_ply_round_trip("src/tests/data/cube_normals-binary.ply", PlyFormat::Ascii);
+        assert_ply_round_trip(
+            "src/tests/data/cube_normals-binary.ply",
+            PlyFormat::BigEndian,
+        );
+    }
+
+    /// Sum of the signed areas of the mesh faces projected onto the xy plane.
+    fn signed_area_xy(m: &rust_3d::Mesh3D<Point3D, PointCloud3D<Point3D>, Vec<usize>>) -> f64 {
+        let mut area = 0.0;
+        for fid in 0..m.num_faces() {
+            let [a, b, c] = m.face_vertices(FId { val: fid }).unwrap();
+            area += 0.5 * ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x));
+        }
+        area
+    }
+
+    #[test]
+    fn triangulate_concave_polygon() {
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [2.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 2.0, 0.0],
+            [0.0, 2.0, 0.0],
+        ];
+        let triangles = triangulate(&positions);
+        assert_eq!(triangles.len(), 4);
+        let mut area = 0.0;
+        for [a, b, c] in triangles {
+            let (a, b, c) = (positions[a], positions[b], positions[c]);
+            let triangle_area =
+                0.5 * ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]));
+            // Every triangle keeps the winding of the polygon.
+            assert!(triangle_area > 0.0);
+            area += triangle_area;
+        }
+        assert!((area - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn polygon_obj_works() {
+        let path = "src/tests/data/lshape.obj".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material_info = MaterialInfo::new();
+        load_obj_mesh(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material_info,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 6);
+        assert_eq!(m.num_faces(), 4);
+        assert_eq!(material_info.uv.len(), 6);
+        let surface = material_info.surfaces.get("Default").unwrap();
+        assert_eq!(surface.faces.len(), 4);
+        assert_eq!(surface.uvs.len(), 4);
+        assert!((signed_area_xy(&m) - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn polygon_ascii_ply_works() {
+        let path = "src/tests/data/lshape-ascii.ply".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        let file_name = "lshape-ascii.ply".to_string();
+        load_ply(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &file_name,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 6);
+        assert_eq!(m.num_faces(), 4);
+        assert_eq!(material_info.uv.len(), 3 * 4);
+        assert!((signed_area_xy(&m) - 3.0).abs() < 1e-9);
+        // Texture coordinates follow their corners.
+        for fid in 0..m.num_faces() {
+            let vertices = m.face_vertices(FId { val: fid }).unwrap();
+            for (i, vertex) in vertices.iter().enumerate() {
+                let uv = material_info.uv.get_d(3 * fid + i);
+                assert!((uv.x - vertex.x / 2.0).abs() < 1e-6);
+                assert!((uv.y - vertex.y / 2.0).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn polygon_binary_ply_works() {
+        let path = "src/tests/data/lshape-binary.ply".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        let file_name = "lshape-binary.ply".to_string();
+        load_ply(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &file_name,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 6);
+        assert_eq!(m.num_faces(), 4);
+        assert_eq!(material_info.uv.len(), 3 * 4);
+        assert!((signed_area_xy(&m) - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn polygon_off_works() {
+        let path = "src/tests/data/lshape.off".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material_info = MaterialInfo::new();
+        load_off_mesh(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material_info,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 6);
+        assert_eq!(m.num_faces(), 4);
+        assert!((signed_area_xy(&m) - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn load_colored_off() {
+        let path = "src/tests/data/cube_colored.off".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material_info = MaterialInfo::new();
+        load_off_mesh(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material_info,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 8);
+        assert_eq!(m.num_faces(), 12);
+        assert_eq!(material_info.colors.len(), 8);
+        assert!((material_info.colors[1].y - 1.0).abs() < 1e-6);
+        assert!(material_info.colors[1].x.abs() < 1e-6);
+        assert_eq!(material_info.normals.len(), 0);
+        assert_eq!(material_info.uv.len(), 0);
+    }
+
+    #[test]
+    fn load_off_with_all_attributes() {
+        let path = "src/tests/data/quad_attributes.off".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material_info = MaterialInfo::new();
+        load_off_mesh(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material_info,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 4);
+        assert_eq!(m.num_faces(), 2);
+        assert_eq!(material_info.normals.len(), 4);
+        assert_eq!(material_info.normals[2].z, 1.0);
+        assert_eq!(material_info.uv.len(), 4);
+        assert_eq!(material_info.uv.get_d(2).y, 1.0);
+        // The face color of the first face makes the colors per wedge.
+        assert_eq!(material_info.colors.len(), 6);
+        assert_eq!(material_info.colors[0], three_d::vec3(1.0, 0.0, 0.0));
+        assert_eq!(material_info.colors[3], three_d::vec3(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn load_noff_works() {
+        let data = b"nOFF\n3\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n";
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material_info = MaterialInfo::new();
+        load_off_mesh(&mut &data[..], &mut m, &mut material_info).unwrap();
+        assert_eq!(m.num_faces(), 1);
+
+        let data = b"nOFF\n2\n3 0 0\n0 0\n1 0\n0 1\n";
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        match load_off_mesh(&mut &data[..], &mut m, &mut material_info) {
+            Err(Error {
+                kind: ErrorKind::UnsupportedDimension,
+                line: Some(2),
+                ..
+            }) => {}
+            _ => panic!("Expected an unsupported dimension"),
+        }
+    }
+
+    #[test]
+    fn load_off_with_counts_after_keyword() {
+        let data = b"OFF 3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n";
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material_info = MaterialInfo::new();
+        load_off_mesh(&mut &data[..], &mut m, &mut material_info).unwrap();
+        assert_eq!(m.num_vertices(), 3);
+        assert_eq!(m.num_faces(), 1);
+    }
+
+    #[test]
+    fn load_ascii_ply_without_faces() {
+        let path = "src/tests/data/points-ascii.ply".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        let file_name = "points-ascii.ply".to_string();
+        load_ply(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &file_name,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 5);
+        assert_eq!(m.num_faces(), 0);
+        assert_eq!(material_info.colors.len(), 5);
+        assert!((material_info.colors[1].y - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn load_binary_ply_with_empty_faces() {
+        let path = "src/tests/data/points-binary.ply".to_string();
+        let mut m = rust_3d::Mesh3D::<Point3D, PointCloud3D<Point3D>, Vec<usize>>::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        let file_name = "points-binary.ply".to_string();
+        load_ply(
+            &mut BufReader::new(File::open(path).unwrap()),
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &file_name,
+        )
+        .unwrap();
+        assert_eq!(m.num_vertices(), 5);
+        assert_eq!(m.num_faces(), 0);
+        assert_eq!(material_info.colors.len(), 5);
+        assert_eq!(m.vertex(VId { val: 4 }).unwrap().z, 1.0);
+    }
+
+    #[test]
+    fn load_xyz_works() {
+        let path = "src/tests/data/points.xyz".to_string();
+        let mut points = PointCloud3D::<Point3D>::new();
+        load_xyz_points(&mut BufReader::new(File::open(path).unwrap()), &mut points).unwrap();
+        assert_eq!(points.len(), 5);
+        assert_eq!(points.get_d(1).x, 1.0);
+        assert_eq!(points.get_d(3).z, 1.0);
+    }
+
+    type TestMesh = rust_3d::Mesh3D<Point3D, PointCloud3D<Point3D>, Vec<usize>>;
+
+    #[test]
+    fn load_gltf_with_external_buffer() {
+        let data = std::fs::read("src/tests/data/quad.gltf").unwrap();
+        let mut resources = std::collections::HashMap::new();
+        resources.insert(
+            "quad.bin".to_string(),
+            std::fs::read("src/tests/data/quad.bin").unwrap(),
+        );
+        let scene = load_gltf::<TestMesh, Point3D>(&data[..], "quad.gltf", &resources).unwrap();
+        assert_eq!(scene.meshes.len(), 1);
+        let quad = &scene.meshes[0];
+        assert_eq!(quad.name, "quad.gltf/quad");
+        assert_eq!(quad.mesh.num_vertices(), 4);
+        assert_eq!(quad.mesh.num_faces(), 2);
+        assert_eq!(quad.material_info.normals.len(), 4);
+        assert_eq!(quad.material_info.uv.len(), 4);
+        // The texture is flipped vertically to match OBJ texture coordinates.
+        assert_eq!(quad.material_info.uv.get_d(0).y, 0.0);
+        assert_eq!(quad.material_info.uv.get_d(2).y, 1.0);
+        // Scale of the child node, then translation of the parent.
+        assert_eq!(quad.transform[0][0], 2.0);
+        assert_eq!(quad.transform[3], [1.0, 2.0, 3.0, 1.0]);
+        let surface_name = quad.material_info.surfaces.keys().next().unwrap();
+        assert_eq!(surface_name, "quad.gltf#material0");
+        let material = &scene.materials[surface_name];
+        assert_eq!(material.diffuse_color.y, 0.5);
+        assert_eq!(material.texture_name, Some("quad.png".to_string()));
+        // The material is opaque, so the alpha of its texture is ignored.
+        assert!(!material.texture_alpha);
+        assert_eq!(material.opacity, 1.0);
+        let normal_map = material.normal_map.as_ref().unwrap();
+        assert_eq!(normal_map.name, "quad.png");
+        assert_eq!(normal_map.options.bump_multiplier, 0.5);
+        assert_eq!(material.emissive_color, three_d::vec3(0.5, 0.25, 0.0));
+        let pbr = material.pbr.as_ref().unwrap();
+        assert_eq!(pbr.metallic, 0.25);
+        assert_eq!(pbr.roughness, 0.75);
+        // Roughness and metallic share a texture, in different channels.
+        let roughness_map = pbr.roughness_map.as_ref().unwrap();
+        let metallic_map = pbr.metallic_map.as_ref().unwrap();
+        assert_eq!(roughness_map.name, "quad.png");
+        assert_eq!(roughness_map.options.channel, 1);
+        assert_eq!(metallic_map.name, "quad.png");
+        assert_eq!(metallic_map.options.channel, 2);
+        assert_eq!(pbr.occlusion_map.as_ref().unwrap().name, "quad.png");
+        assert_eq!(pbr.occlusion_strength, 0.5);
+        assert!(scene.images.is_empty());
+    }
+
+    #[test]
+    fn gltf_without_buffer_is_missing_resource() {
+        let data = std::fs::read("src/tests/data/quad.gltf").unwrap();
+        let resources = std::collections::HashMap::new();
+        match load_gltf::<TestMesh, Point3D>(&data[..], "quad.gltf", &resources) {
+            Err(Error {
+                kind: ErrorKind::MissingResource(name),
+                ..
+            }) => assert_eq!(name, "quad.bin"),
+            _ => panic!("Expected a missing resource"),
+        }
+    }
+
+    #[test]
+    fn load_glb_works() {
+        let data = std::fs::read("src/tests/data/quad.glb").unwrap();
+        let resources = std::collections::HashMap::new();
+        let scene = load_gltf::<TestMesh, Point3D>(&data[..], "quad.glb", &resources).unwrap();
+        assert_eq!(scene.meshes.len(), 1);
+        assert_eq!(scene.meshes[0].mesh.num_faces(), 2);
+        let material = &scene.materials["quad.glb#material0"];
+        let texture_name = material.texture_name.as_ref().unwrap();
+        assert_eq!(texture_name, "quad.glb#image0");
+        assert!(scene.images.contains_key(texture_name));
+        assert!(image::load_from_memory(&scene.resources[texture_name][..]).is_ok());
+    }
+
+    fn load_saved_stl(data: &[u8]) -> (TestMesh, Vec<Point3D>) {
+        let mut m = TestMesh::default();
+        let mut normals = vec![];
+        rust_3d::io::load_stl_mesh_unique(
+            &mut &data[..],
+            rust_3d::io::StlFormat::Auto,
+            &mut m,
+            &mut normals,
+        )
+        .unwrap();
+        (m, normals)
+    }
+
+    #[test]
+    fn save_stl_works() {
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+        let indices = [0, 1, 2, 0, 2, 3];
+        let transform = three_d::Mat4::from_translation(three_d::vec3(0.0, 0.0, 5.0))
+            * three_d::Mat4::from_scale(10.0);
+        let mut binary = vec![];
+        save_stl_binary(&mut binary, &positions[..], &indices[..], &transform).unwrap();
+        assert_eq!(binary.len(), 84 + 2 * 50);
+        let mut ascii = vec![];
+        save_stl_ascii(&mut ascii, "quad", &positions[..], &indices[..], &transform).unwrap();
+        assert!(ascii.starts_with(b"solid quad"));
+
+        for data in [binary, ascii].iter() {
+            let (m, normals) = load_saved_stl(&data[..]);
+            assert_eq!(m.num_vertices(), 4);
+            assert_eq!(m.num_faces(), 2);
+            assert_eq!(normals.len(), 2);
+            assert!((normals[0].z - 1.0).abs() < 1e-6);
+            let corner = m.face_vertex_ids(FId { val: 0 }).unwrap().c;
+            let vertex = m.vertex(corner).unwrap();
+            assert!((vertex.x - 10.0).abs() < 1e-6);
+            assert!((vertex.y - 10.0).abs() < 1e-6);
+            assert!((vertex.z - 5.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn save_stl_with_invalid_indices_fails() {
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0];
+        let mut data = vec![];
+        let transform = three_d::Mat4::from_scale(1.0);
+        match save_stl_binary(&mut data, &positions[..], &[0, 1, 3], &transform) {
+            Err(Error {
+                kind: ErrorKind::InvalidMeshIndices,
+                element: Some(("facet", 0)),
+                ..
+            }) => {}
+            _ => panic!("Expected invalid mesh indices"),
+        }
+    }
+
+    #[test]
+    fn registry_finds_formats() {
+        let registry = FormatRegistry::new();
+        let obj = std::fs::read("src/tests/data/box.obj").unwrap();
+        assert_eq!(registry.find("box.obj", &obj[..]).unwrap().name(), "OBJ");
+        assert_eq!(registry.find("BOX.OBJ", &obj[..]).unwrap().name(), "OBJ");
+        assert_eq!(registry.find("photo.jpeg", &[]).unwrap().name(), "JPEG");
+        assert_eq!(registry.find("photo.jpg", &[]).unwrap().name(), "JPEG");
+        // Files with a signature are found by their content.
+        let ply = std::fs::read("src/tests/data/lshape-binary.ply").unwrap();
+        assert_eq!(registry.find("lshape.bin", &ply[..]).unwrap().name(), "PLY");
+        let glb = std::fs::read("src/tests/data/quad.glb").unwrap();
+        assert_eq!(registry.find("quad", &glb[..]).unwrap().name(), "glTF");
+        assert!(registry.find("notes.txt", b"Nothing to see").is_none());
+        assert!(registry.accept().contains(".jpeg, .jpg"));
+    }
+
+    #[test]
+    fn registry_loads_scenes() {
+        let registry = FormatRegistry::new();
+        let resources = std::collections::HashMap::new();
+        let data = std::fs::read("src/tests/data/capsule.ply").unwrap();
+        let scene = registry.load("capsule.ply", &data[..], &resources).unwrap();
+        assert_eq!(scene.meshes.len(), 1);
+        assert!(scene.materials.contains_key("capsule.ply"));
+        let data = std::fs::read("src/tests/data/points.xyz").unwrap();
+        let scene = registry.load("points.xyz", &data[..], &resources).unwrap();
+        assert_eq!(scene.meshes.len(), 0);
+        assert_eq!(scene.point_clouds.len(), 1);
+        let data = std::fs::read("src/tests/data/capsule0.jpg").unwrap();
+        let scene = registry
+            .load("capsule0.jpg", &data[..], &resources)
+            .unwrap();
+        assert!(scene.images.contains_key("capsule0.jpg"));
+        assert!(scene.resources.contains_key("capsule0.jpg"));
+        let data = std::fs::read("src/tests/data/quad.gltf").unwrap();
+        match registry.load("quad.gltf", &data[..], &resources) {
+            Err(Error {
+                kind: ErrorKind::MissingResource(name),
+                file: Some(file),
+                ..
+            }) => {
+                assert_eq!(name, "quad.bin");
+                assert_eq!(file, "quad.gltf");
+            }
+            _ => panic!("Expected a missing resource"),
+        }
+        match registry.load("notes.txt", b"Nothing to see", &resources) {
+            Err(Error {
+                kind: ErrorKind::UnknownFormat,
+                ..
+            }) => {}
+            _ => panic!("Expected an unknown format"),
+        }
+    }
+
+    #[test]
+    fn registry_sniffs_content() {
+        let registry = FormatRegistry::new();
+        let format_of = |name: &str, path: &str| {
+            let data = std::fs::read(path).unwrap();
+            registry.find(name, &data[..]).map(|format| format.name())
+        };
+        assert_eq!(
+            format_of("model.txt", "src/tests/data/box.obj"),
+            Some("OBJ")
+        );
+        assert_eq!(
+            format_of("cube", "src/tests/data/cube_colored.off"),
+            Some("OFF")
+        );
+        assert_eq!(
+            format_of("scan.PLY", "src/tests/data/lshape-ascii.ply"),
+            Some("PLY")
+        );
+        assert_eq!(
+            format_of("texture", "src/tests/data/capsule0.jpg"),
+            Some("JPEG")
+        );
+        assert_eq!(
+            format_of("capsule.txt", "src/tests/data/capsule.mtl"),
+            Some("MTL")
+        );
+        assert_eq!(
+            format_of("quad.json", "src/tests/data/quad.gltf"),
+            Some("glTF")
+        );
+        // MTL files with only the statements of physically based
+        // materials.
+        let pbr_mtl = b"newmtl metal\nPr 0.3\nPm 1.0\nPs 0.1\nPc 0.5\nPcr 0.2\n\
+            aniso 0.0\nanisor 0.0\nmap_Pr rough.png\nmap_Pm metal.png\nmap_Ps sheen.png\n\
+            norm normal.png\n";
+        let format = registry.find("metal.txt", &pbr_mtl[..]).unwrap();
+        assert_eq!(format.name(), "MTL");
+        // Numbers only could be more than one format, so the extension decides.
+        assert_eq!(
+            format_of("points.xyz", "src/tests/data/points.xyz"),
+            Some("XYZ")
+        );
+        assert_eq!(format_of("points.txt", "src/tests/data/points.xyz"), None);
+
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0];
+        let indices = [0, 1, 2];
+        let transform = three_d::Mat4::from_scale(1.0);
+        let mut binary = vec![];
+        save_stl_binary(&mut binary, &positions[..], &indices[..], &transform).unwrap();
+        let mut ascii = vec![];
+        save_stl_ascii(&mut ascii, "tri", &positions[..], &indices[..], &transform).unwrap();
+        for data in [binary, ascii].iter() {
+            let format = registry.find("triangle.dat", &data[..]).unwrap();
+            assert_eq!(format.name(), "STL");
+        }
+
+        // Facet counts whose size overflows 32 bits aren't STLs, even
+        // where the size wraps around to the length of the file.
+        for count in [u32::MAX, (1 << 31) + 1].iter() {
+            let mut data = vec![0u8; 84 + 50];
+            data[80..84].copy_from_slice(&count.to_le_bytes());
+            assert!(registry.find("huge.dat", &data[..]).is_none());
+        }
+    }
+
+    #[test]
+    fn obj_skipped_faces_are_warnings() {
+        let data = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nf 1 2 9\n";
+        let mut m = TestMesh::default();
+        let mut material_info = MaterialInfo::new();
+        load_obj_mesh(&mut &data[..], &mut m, &mut material_info).unwrap();
+        assert_eq!(m.num_faces(), 1);
+        assert_eq!(material_info.warnings, vec!["1 faces could not be added"]);
+
+        let registry = FormatRegistry::new();
+        let resources = std::collections::HashMap::new();
+        let scene = registry.load("skipped.obj", &data[..], &resources).unwrap();
+        assert_eq!(scene.meshes[0].material_info.warnings.len(), 1);
+        match registry.load("broken.obj", b"v 0 0 0\nf 1 x 2\n", &resources) {
+            Err(e) => assert!(format!("{:?}", e).contains("line 2")),
+            _ => panic!("Expected a parse error"),
+        }
+    }
+
+    #[test]
+    fn degenerate_polygons_are_warnings() {
+        // A triangle stored as a quad, with its last corner twice.
+        let off = b"OFF\n4 2 0\n0 0 0\n1 0 0\n0 1 0\n1 1 0\n4 0 1 2 2\n3 1 3 2\n";
+        let mut m = TestMesh::default();
+        let mut material_info = MaterialInfo::new();
+        load_off_mesh(&mut &off[..], &mut m, &mut material_info).unwrap();
+        assert_eq!(m.num_faces(), 2);
+        assert_eq!(material_info.warnings, vec!["1 faces could not be added"]);
+
+        let ply = b"ply\nformat ascii 1.0\nelement vertex 3\n\
+            property float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0\n1 0 0\n0 1 0\n4 0 1 2 2\n";
+        let mut m = TestMesh::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        load_ply(
+            &mut &ply[..],
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &"degenerate.ply".to_string(),
+        )
+        .unwrap();
+        assert_eq!(m.num_faces(), 1);
+        assert_eq!(material_info.warnings, vec!["1 faces could not be added"]);
+
+        // Vertices that aren't there are still errors.
+        let off = b"OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n4 0 1 2 7\n";
+        let mut m = TestMesh::default();
+        let mut material_info = MaterialInfo::new();
+        match load_off_mesh(&mut &off[..], &mut m, &mut material_info) {
+            Err(e) => assert!(matches!(e.kind, ErrorKind::InvalidMeshIndices)),
+            Ok(_) => panic!("Expected invalid mesh indices"),
+        }
+    }
+
+    fn load_ply_error(data: &[u8]) -> Error {
+        let mut m = TestMesh::default();
+        let mut material = Material::new();
+        let mut material_info = MaterialInfo::new();
+        match load_ply(
+            &mut &data[..],
+            &mut m,
+            &mut material,
+            &mut material_info,
+            &"broken.ply".to_string(),
+        ) {
+            Err(e) => e,
+            Ok(_) => panic!("Expected an error"),
+        }
+    }
+
+    #[test]
+    fn ply_errors_have_locations() {
+        let data = std::fs::read("src/tests/data/lshape-binary.ply").unwrap();
+        // 6 vertices of 12 bytes follow the header, then the face.
+        let face_offset = (data.len() - 74) as u64;
+
+        let e = load_ply_error(&data[..data.len() - 10]);
+        assert!(matches!(e.kind, ErrorKind::AccessFile));
+        assert_eq!(e.element, Some(("face", 0)));
+        assert_eq!(e.offset, Some(face_offset));
+        assert!(std::error::Error::source(&e).is_some());
+
+        let mut corrupt = data.clone();
+        corrupt[face_offset as usize + 1] = 99;
+        let e = load_ply_error(&corrupt[..]);
+        assert!(matches!(e.kind, ErrorKind::InvalidMeshIndices));
+        assert_eq!(e.element, Some(("face", 0)));
+        assert_eq!(e.offset, Some(face_offset));
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "File contains invalid mesh indices at face 0 at byte {}",
+                face_offset
+            )
+        );
+
+        let data = b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n\
+            property float y\nproperty float z\nend_header\n0 zero 0\n";
+        let e = load_ply_error(&data[..]);
+        assert!(matches!(e.kind, ErrorKind::InvalidVertex));
+        assert_eq!(e.line, Some(8));
+
+        let data = b"ply\nformat ascii 1.0\nelement vertex 1\nproperty half x\n";
+        let e = load_ply_error(&data[..]);
+        assert!(matches!(e.kind, ErrorKind::InvalidType(_)));
+        assert_eq!(e.line, Some(4));
+    }
+
+    #[test]
+    fn corrupt_files_are_errors() {
+        let registry = FormatRegistry::new();
+        let resources = std::collections::HashMap::new();
+        for entry in std::fs::read_dir("src/tests/data/corrupt").unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let data = std::fs::read(&path).unwrap();
+            assert!(
+                registry.load(&name, &data[..], &resources).is_err(),
+                "{} loaded",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_errors() {
+        let registry = FormatRegistry::new();
+        let resources = std::collections::HashMap::new();
+        for name in [
+            "box_quads.obj",
+            "capsule.mtl",
+            "cube_colored-ascii.ply",
+            "cube_colored-binary.ply",
+            "cube_colored.off",
+            "lshape.obj",
+            "points-binary.ply",
+            "quad_attributes.off",
+        ]
+        .iter()
+        {
+            let data = std::fs::read(format!("src/tests/data/{}", name)).unwrap();
+            for length in 0..data.len() {
+                let result = registry.load(name, &data[..length], &resources);
+                // Text files cut after a complete line may still be
+                // valid, formats with element counts are not.
+                if !name.ends_with(".obj") && !name.ends_with(".mtl") {
+                    assert!(result.is_err(), "{} loaded at length {}", name, length);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod test {
+    use crate::light_rig::*;
+    use three_d::*;
+
+    #[test]
+    fn colors_round_trip_through_hex() {
+        assert_eq!(color_to_hex(&vec3(1.0, 0.5, 0.0)), "#ff8000");
+        assert_eq!(color_to_hex(&vec3(2.0, -1.0, 0.8)), "#ff00cc");
+        let color = hex_to_color("#ff8000").unwrap();
+        assert_eq!(color, vec3(1.0, 128.0 / 255.0, 0.0));
+        assert_eq!(hex_to_color("#cccccc"), Some(vec3(0.8, 0.8, 0.8)));
+        assert_eq!(hex_to_color("ff8000"), None);
+        assert_eq!(hex_to_color("#ff80"), None);
+        assert_eq!(hex_to_color("#ff80zz"), None);
+    }
+
+    #[test]
+    fn lights_are_added_edited_and_removed() {
+        let mut lights = Preset::Flat.lights();
+        assert!(lights.directional.is_empty() && lights.point.is_empty());
+        add_light(&mut lights, Light::directional());
+        add_light(&mut lights, Light::point());
+        add_light(&mut lights, Light::point());
+        assert_eq!(lights.directional.len(), 1);
+        assert_eq!(lights.point.len(), 2);
+
+        let edited = Light::point().with_intensity(2.0);
+        set_light(&mut lights, LightId::Point(1), edited);
+        assert_eq!(get_light(&lights, LightId::Point(1)), Some(edited));
+
+        // A point light made directional moves after the directional
+        // lights, keeping its color and intensity.
+        let toggled = edited.with_color(vec3(1.0, 0.0, 0.0)).toggled_kind();
+        set_light(&mut lights, LightId::Point(0), toggled);
+        assert_eq!(lights.point.len(), 1);
+        assert_eq!(lights.directional.len(), 2);
+        assert_eq!(lights.directional[1].color, vec3(1.0, 0.0, 0.0));
+        assert_eq!(lights.directional[1].intensity, 2.0);
+
+        remove_light(&mut lights, LightId::Directional(0));
+        remove_light(&mut lights, LightId::Point(5));
+        assert_eq!(lights.directional.len(), 1);
+        assert_eq!(lights.point.len(), 1);
+        // Lights that aren't there aren't added by editing them.
+        set_light(&mut lights, LightId::Point(3), Light::directional());
+        assert_eq!(lights.directional.len(), 1);
+    }
+
+    #[test]
+    fn edits_in_a_row_keep_each_other() {
+        let mut lights = Preset::Default.lights();
+        let id = LightId::Directional(0);
+        edit_light(&mut lights, id, LightEdit::Intensity(2.0));
+        edit_light(&mut lights, id, LightEdit::Color(vec3(1.0, 0.0, 0.0)));
+        edit_light(&mut lights, id, LightEdit::Vector(0, 3.0));
+        edit_light(&mut lights, id, LightEdit::Vector(1, 4.0));
+        let light = lights.directional[0];
+        assert_eq!(light.intensity, 2.0);
+        assert_eq!(light.color, vec3(1.0, 0.0, 0.0));
+        assert_eq!(light.direction, vec3(3.0, 4.0, 0.0));
+
+        // Toggling twice goes back to where it started.
+        edit_light(&mut lights, id, LightEdit::ToggleAttached);
+        assert!(lights.directional[0].attached_to_camera);
+        edit_light(&mut lights, id, LightEdit::ToggleAttached);
+        assert!(!lights.directional[0].attached_to_camera);
+
+        // What isn't a number changes nothing.
+        edit_light(&mut lights, id, LightEdit::Intensity(f32::NAN));
+        edit_light(&mut lights, id, LightEdit::Vector(2, f32::NAN));
+        assert_eq!(lights.directional[0].intensity, 2.0);
+        assert_eq!(lights.directional[0].direction, vec3(3.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn headlight_follows_the_camera() {
+        let lights = Preset::Headlight.lights();
+        assert_eq!(lights.directional.len(), 1);
+        assert!(lights.directional[0].attached_to_camera);
+        assert_eq!(Preset::ALL.len(), 4);
+        assert_ne!(Preset::Studio.lights(), Preset::Default.lights());
+    }
+}
@@ -0,0 +1,16 @@
+#[cfg(test)]
+mod test {
+    use crate::mesh_exporter::StlOptions;
+
+    #[test]
+    fn stl_scale_must_be_above_zero() {
+        let options = StlOptions::new();
+        assert!(options.validate().is_ok());
+        let scaled = |scale| StlOptions { scale, ..options };
+        assert!(scaled(0.001).validate().is_ok());
+        assert!(scaled(1000.0).validate().is_ok());
+        for scale in [0.0, -0.0, -1.0, f32::NAN, f32::INFINITY, f32::NEG_INFINITY].iter() {
+            assert!(scaled(*scale).validate().is_err(), "{}", scale);
+        }
+    }
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod test {
+    use crate::mesh_loader::{compute_tangents, has_translucent_pixels};
+
+    // A quad in the XY plane, facing +Z.
+    const POSITIONS: [f32; 12] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+    const NORMALS: [f32; 12] = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+    const INDICES: [u32; 6] = [0, 1, 2, 0, 2, 3];
+
+    #[test]
+    fn tangents_follow_uvs() {
+        // U increases along Y, and V along X.
+        let uvs = [0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0, 0.0, 0.0];
+        let (tangents, handedness) = compute_tangents(&INDICES, &POSITIONS, &NORMALS, &uvs);
+        assert_eq!(tangents.len(), 12);
+        for tangent in tangents.chunks_exact(3) {
+            assert!((tangent[1] - 1.0).abs() < 1e-6);
+        }
+        // The normal cross the tangent points along -X, against V.
+        assert_eq!(handedness, vec![-1.0; 4]);
+
+        let uvs = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0];
+        let (tangents, handedness) = compute_tangents(&INDICES, &POSITIONS, &NORMALS, &uvs);
+        for tangent in tangents.chunks_exact(3) {
+            assert!((tangent[0] - 1.0).abs() < 1e-6);
+        }
+        assert_eq!(handedness, vec![1.0; 4]);
+    }
+
+    #[test]
+    fn tangents_without_uv_directions_are_perpendicular() {
+        let uvs = [0.0; 12];
+        let (tangents, handedness) = compute_tangents(&INDICES, &POSITIONS, &NORMALS, &uvs);
+        for tangent in tangents.chunks_exact(3) {
+            let length = (tangent[0] * tangent[0] + tangent[1] * tangent[1]).sqrt();
+            assert!((length - 1.0).abs() < 1e-6);
+            assert_eq!(tangent[2], 0.0);
+        }
+        assert_eq!(handedness.len(), 4);
+    }
+
+    #[test]
+    fn translucent_pixels_are_found() {
+        let mut image = image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255]));
+        assert!(!has_translucent_pixels(&image::DynamicImage::ImageRgba8(
+            image.clone()
+        )));
+        image.put_pixel(1, 1, image::Rgba([255, 0, 0, 128]));
+        assert!(has_translucent_pixels(&image::DynamicImage::ImageRgba8(
+            image
+        )));
+        let rgb = image::RgbImage::new(2, 2);
+        assert!(!has_translucent_pixels(&image::DynamicImage::ImageRgb8(
+            rgb
+        )));
+    }
+}
@@ -1 +1,9 @@
+mod bounds;
+mod deferred_renderer;
+mod edge_renderer;
+mod environment;
 mod io;
+mod light_rig;
+mod mesh_exporter;
+mod mesh_loader;
+mod origin;
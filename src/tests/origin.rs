@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod test {
+    use crate::origin::{model_matrix, origin_of, to_original, transform_point};
+    use three_d::*;
+
+    #[test]
+    fn origin_is_the_rounded_center() {
+        let points = vec![
+            [500_000.25, 4_000_000.0, 10.0],
+            [500_100.5, 4_000_050.0, 12.0],
+        ];
+        assert_eq!(
+            origin_of(points.into_iter()),
+            [500_050.0, 4_000_025.0, 11.0]
+        );
+        // Points around zero stay where they are.
+        let points = vec![[-0.5, 0.0, 0.25], [0.75, 0.25, 0.5]];
+        assert_eq!(origin_of(points.into_iter()), [0.0, 0.0, 0.0]);
+        assert_eq!(origin_of(std::iter::empty()), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn meshes_far_from_zero_line_up() {
+        // Two meshes in UTM coordinates, a metre apart, each relative to
+        // an origin of its own.
+        let first = [500_000.0, 4_000_000.0, 100.0];
+        let second = [500_001.0, 4_000_000.0, 100.0];
+        let scene_origin = first;
+        let first_matrix = model_matrix(&Mat4::identity(), &first, &scene_origin);
+        let second_matrix = model_matrix(&Mat4::identity(), &second, &scene_origin);
+        assert_eq!(first_matrix, Mat4::identity());
+        assert_eq!(second_matrix[3], vec4(1.0, 0.0, 0.0, 1.0));
+        // A millimetre from the origin of the second mesh is still a
+        // millimetre, where f32 coordinates would be off by a quarter of
+        // a metre.
+        let vertex = vec4(0.001, 0.0, 0.0, 1.0);
+        assert!(((second_matrix * vertex).x - 1.001).abs() < 1e-6);
+        let original = to_original(&second, &[0.001, 0.0, 0.0]);
+        assert!((original[0] - 500_001.001).abs() < 1e-6);
+    }
+
+    #[test]
+    fn transforms_apply_to_the_origin() {
+        let transform = Mat4::from_translation(vec3(1.0, 2.0, 3.0)) * Mat4::from_scale(2.0);
+        let origin = [1_000_000.0, 0.0, 0.0];
+        assert_eq!(
+            transform_point(&transform, &origin),
+            [2_000_001.0, 2.0, 3.0]
+        );
+        let matrix = model_matrix(&transform, &origin, &[2_000_000.0, 0.0, 0.0]);
+        assert_eq!(matrix[0][0], 2.0);
+        assert_eq!(matrix[3], vec4(1.0, 2.0, 3.0, 1.0));
+    }
+}
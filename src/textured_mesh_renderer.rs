@@ -1,50 +1,255 @@
-use crate::io::Material;
-use crate::render_buffers::{MeshSurface, VertexData};
+use crate::deferred_renderer::{use_material, MaterialPrograms, Pass};
+use crate::io::{Material, TextureMap, TextureOptions};
+use crate::render_buffers::{MeshSurface, TangentData};
+use crate::MeshContainer;
+use std::collections::HashMap;
 use three_d::*;
 
+/// Textures of a material that are loaded.
+pub struct MaterialTextures<'a> {
+    pub diffuse: Option<&'a Texture2D>,
+    pub specular: Option<&'a Texture2D>,
+    pub opacity: Option<&'a Texture2D>,
+    pub emissive: Option<&'a Texture2D>,
+    pub bump: Option<&'a Texture2D>,
+    pub normal: Option<&'a Texture2D>,
+    pub metallic: Option<&'a Texture2D>,
+    pub roughness: Option<&'a Texture2D>,
+    pub occlusion: Option<&'a Texture2D>,
+}
+
+impl<'a> MaterialTextures<'a> {
+    pub fn new(
+        material: &Material,
+        images: &'a HashMap<String, Texture2D>,
+    ) -> MaterialTextures<'a> {
+        let find = |map: Option<&TextureMap>| map.and_then(|map| images.get(&map.name));
+        let pbr = material.pbr.as_ref();
+        MaterialTextures {
+            diffuse: material
+                .texture_name
+                .as_ref()
+                .and_then(|name| images.get(name)),
+            specular: find(material.specular_map.as_ref()),
+            opacity: find(material.opacity_map.as_ref()),
+            emissive: find(material.emissive_map.as_ref()),
+            bump: find(material.bump_map.as_ref()),
+            normal: find(material.normal_map.as_ref()),
+            metallic: find(pbr.and_then(|pbr| pbr.metallic_map.as_ref())),
+            roughness: find(pbr.and_then(|pbr| pbr.roughness_map.as_ref())),
+            occlusion: find(pbr.and_then(|pbr| pbr.occlusion_map.as_ref())),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diffuse.is_none()
+            && self.specular.is_none()
+            && self.opacity.is_none()
+            && self.emissive.is_none()
+            && self.metallic.is_none()
+            && self.roughness.is_none()
+            && self.occlusion.is_none()
+            && !self.has_surface_detail()
+    }
+
+    /// Are there maps that change the normals of the surface?
+    pub fn has_surface_detail(&self) -> bool {
+        self.bump.is_some() || self.normal.is_some()
+    }
+}
+
+/// A 1x1 white texture, used in place of textures that a material
+/// doesn't have.
+pub(crate) fn white_texture(gl: &Gl) -> Texture2D {
+    Texture2D::new_with_u8(
+        gl,
+        Interpolation::Nearest,
+        Interpolation::Nearest,
+        None,
+        Wrapping::Repeat,
+        Wrapping::Repeat,
+        1,
+        1,
+        &[255, 255, 255, 255],
+    )
+    .unwrap()
+}
+
+/// Programs for surfaces with UVs, whose fragment shader uses the
+/// functions of texture_maps.frag. `defines` go before both shaders.
+pub(crate) fn texture_maps_programs(
+    gl: &Gl,
+    defines: &str,
+    fragment_shader: &str,
+) -> MaterialPrograms {
+    MaterialPrograms::new(
+        gl,
+        &format!("{}{}", defines, include_str!("shaders/textured.vert")),
+        &format!(
+            "{}{}\n{}",
+            defines,
+            include_str!("shaders/texture_maps.frag"),
+            fragment_shader
+        ),
+    )
+}
+
+/// Value of the `texture_alpha` uniform for a material.
+pub(crate) fn texture_alpha(material: &Material) -> f32 {
+    if material.texture_alpha {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Sets a texture and its transform, as `sample_map` uses them.
+pub(crate) fn use_texture_map(
+    program: &program::Program,
+    name: &str,
+    texture: Option<&Texture2D>,
+    options: Option<&TextureOptions>,
+    white: &Texture2D,
+) {
+    let no_options = TextureOptions::new();
+    let options = options.unwrap_or(&no_options);
+    program.use_texture(texture.unwrap_or(white), name).unwrap();
+    program
+        .add_uniform_vec4(
+            &format!("{}_transform", name),
+            &vec4(
+                options.scale.x,
+                options.scale.y,
+                options.offset.x,
+                options.offset.y,
+            ),
+        )
+        .unwrap();
+}
+
+/// Sets the bump and normal maps, and the tangents, for programs with
+/// `NORMAL_MAP` defined.
+pub(crate) fn use_surface_detail(
+    program: &program::Program,
+    material: &Material,
+    textures: &MaterialTextures,
+    tangents: &TangentData,
+    white: &Texture2D,
+) {
+    let normal_options = material.normal_map.as_ref().map(|map| &map.options);
+    let bump_options = material.bump_map.as_ref().map(|map| &map.options);
+    use_texture_map(
+        program,
+        "normal_map",
+        textures.normal,
+        normal_options,
+        white,
+    );
+    use_texture_map(program, "bump_map", textures.bump, bump_options, white);
+    // Maps that aren't loaded are white, used at no strength.
+    let scale =
+        |texture: Option<&Texture2D>, options: Option<&TextureOptions>| match (texture, options) {
+            (Some(_), Some(options)) => options.bump_multiplier,
+            _ => 0.0,
+        };
+    program
+        .add_uniform_float("normal_scale", &scale(textures.normal, normal_options))
+        .unwrap();
+    program
+        .add_uniform_float("bump_scale", &scale(textures.bump, bump_options))
+        .unwrap();
+    program
+        .use_attribute_vec3_float(&tangents.tangent_buffer, "tangent")
+        .unwrap();
+    program
+        .use_attribute_float(&tangents.handedness_buffer, "handedness")
+        .unwrap();
+}
+
 pub struct TexturedMeshRenderer {
-    shader: program::Program,
+    shader: MaterialPrograms,
+    /// Variant for materials with bump or normal maps, on meshes with
+    /// tangents.
+    normal_mapped_shader: MaterialPrograms,
+    /// Used in place of textures that a material doesn't have.
+    white: Texture2D,
 }
 
 impl TexturedMeshRenderer {
     pub fn new(gl: &Gl) -> TexturedMeshRenderer {
+        let fragment_shader = include_str!("shaders/textured.frag");
         TexturedMeshRenderer {
-            shader: program::Program::from_source(
-                &gl,
-                include_str!("shaders/textured.vert"),
-                include_str!("shaders/textured.frag"),
-            )
-            .unwrap(),
+            shader: texture_maps_programs(gl, "", fragment_shader),
+            normal_mapped_shader: texture_maps_programs(
+                gl,
+                "#define NORMAL_MAP\n",
+                fragment_shader,
+            ),
+            white: white_texture(gl),
         }
     }
 
     pub fn render(
         &self,
-        transformation: &Mat4,
+        pass: Pass,
         camera: &camera::Camera,
-        vertex_data: &VertexData,
+        mesh: &MeshContainer,
         mesh_surface: &MeshSurface,
         material: &Material,
-        image: &Texture2D,
+        textures: &MaterialTextures,
     ) {
-        let program = &self.shader;
-        program
-            .add_uniform_float("diffuse_intensity", &material.diffuse_intensity)
-            .unwrap();
+        let transformation = &mesh.model_matrix;
+        let vertex_data = &mesh.vertices;
+        let tangents = match &vertex_data.maybe_tangents {
+            Some(tangents) if textures.has_surface_detail() => Some(tangents),
+            _ => None,
+        };
+        let programs = if tangents.is_some() {
+            &self.normal_mapped_shader
+        } else {
+            &self.shader
+        };
+        let program = programs.program(pass, camera);
+        use_material(program, material);
         program
-            .add_uniform_float("specular_intensity", &material.specular_intensity)
+            .add_uniform_vec3("color", &material.diffuse_color)
             .unwrap();
-        let specular_power = 5.0;
         program
-            .add_uniform_float("specular_power", &specular_power)
+            .add_uniform_float("texture_alpha", &texture_alpha(material))
             .unwrap();
+        let maps = [
+            (
+                "texture0",
+                textures.diffuse,
+                Some(&material.texture_options),
+            ),
+            (
+                "specular_map",
+                textures.specular,
+                material.specular_map.as_ref().map(|map| &map.options),
+            ),
+            (
+                "opacity_map",
+                textures.opacity,
+                material.opacity_map.as_ref().map(|map| &map.options),
+            ),
+            (
+                "emissive_map",
+                textures.emissive,
+                material.emissive_map.as_ref().map(|map| &map.options),
+            ),
+        ];
+        for (name, texture, options) in maps.iter() {
+            use_texture_map(program, name, *texture, *options, &self.white);
+        }
+        if let Some(tangents) = tangents {
+            use_surface_detail(program, material, textures, tangents, &self.white);
+        }
 
         program
-            .add_uniform_mat4("modelMatrix", &transformation)
+            .add_uniform_mat4("modelMatrix", transformation)
             .unwrap();
 
-        program.use_texture(image, "texture0").unwrap();
-
         program.use_uniform_block(camera.matrix_buffer(), "Camera");
         program
             .add_uniform_mat4(
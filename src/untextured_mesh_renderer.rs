@@ -1,48 +1,56 @@
+use crate::deferred_renderer::{use_material, MaterialPrograms, Pass};
 use crate::io::Material;
-use crate::render_buffers::{MeshSurface, VertexData};
+use crate::render_buffers::MeshSurface;
+use crate::MeshContainer;
 use three_d::*;
 
 pub struct UntexturedMeshRenderer {
-    shader: program::Program,
+    shader: MaterialPrograms,
+    vertex_color_shader: MaterialPrograms,
 }
 
 impl UntexturedMeshRenderer {
     pub fn new(gl: &Gl) -> UntexturedMeshRenderer {
         UntexturedMeshRenderer {
-            shader: program::Program::from_source(
-                &gl,
+            shader: MaterialPrograms::new(
+                gl,
                 include_str!("shaders/mesh_shaded.vert"),
                 include_str!("shaders/shaded.frag"),
-            )
-            .unwrap(),
+            ),
+            vertex_color_shader: MaterialPrograms::new(
+                gl,
+                include_str!("shaders/mesh_vertex_colored.vert"),
+                include_str!("shaders/vertex_colored.frag"),
+            ),
         }
     }
 
     pub fn render(
         &self,
-        transformation: &Mat4,
+        pass: Pass,
         camera: &camera::Camera,
-        vertex_data: &VertexData,
+        mesh: &MeshContainer,
         mesh_surface: &MeshSurface,
         material: &Material,
     ) {
-        let program = &self.shader;
-        program
-            .add_uniform_float("diffuse_intensity", &material.diffuse_intensity)
-            .unwrap();
-        program
-            .add_uniform_float("specular_intensity", &material.specular_intensity)
-            .unwrap();
-        let specular_power = 5.0;
-        program
-            .add_uniform_float("specular_power", &specular_power)
-            .unwrap();
+        let transformation = &mesh.model_matrix;
+        let vertex_data = &mesh.vertices;
+        // Vertex colors, when present, take the place of the material color.
+        let programs = match vertex_data.maybe_colors {
+            Some(_) => &self.vertex_color_shader,
+            None => &self.shader,
+        };
+        let program = programs.program(pass, camera);
+        use_material(program, material);
 
+        match &vertex_data.maybe_colors {
+            Some(colors) => program.use_attribute_vec3_float(colors, "color").unwrap(),
+            None => program
+                .add_uniform_vec3("color", &material.diffuse_color)
+                .unwrap(),
+        }
         program
-            .add_uniform_vec3("color", &material.diffuse_color)
-            .unwrap();
-        program
-            .add_uniform_mat4("modelMatrix", &transformation)
+            .add_uniform_mat4("modelMatrix", transformation)
             .unwrap();
         program.use_uniform_block(camera.matrix_buffer(), "Camera");
         program